@@ -0,0 +1,162 @@
+use std::future::Future;
+
+use tokio::sync::mpsc::UnboundedSender;
+use tokio::task::AbortHandle;
+
+use crate::api::models::{Account, Category, CategorySummary, Transaction};
+use crate::api::{FinTrakClient, Sent};
+use crate::error::FinTrakError;
+use crate::history::Mutation;
+use crate::offline::SyncReport;
+use crate::ui::report::CashFlow;
+
+/// Results of background work, delivered to the event loop over the action channel.
+///
+/// Pages never await the network themselves: they spawn a request through the
+/// `Dispatcher` and `App::handle_action` hands the matching result back to them.
+pub enum Action {
+    SessionResumed {
+        email: String,
+        username: String, // As stored, used when the server cannot be reached
+        result: Result<String, FinTrakError>,
+    },
+    SignedUp(Result<(), FinTrakError>),
+    LoggedIn {
+        email: String,
+        result: Result<String, FinTrakError>,
+    },
+    Overviews {
+        report: Result<Vec<String>, FinTrakError>,
+        category: Result<Vec<String>, FinTrakError>,
+        account: Result<Vec<String>, FinTrakError>,
+        cash_flow: Result<CashFlow, FinTrakError>,
+    },
+    Accounts {
+        accounts: Result<Vec<Account>, FinTrakError>,
+        transactions: Result<Vec<Transaction>, FinTrakError>, // For the balances
+    },
+    /// The ledger the account detail page picks its history from.
+    AccountHistory(Result<Vec<Transaction>, FinTrakError>),
+    /// Creates and edits carry the mutation to keep for undo once they went through.
+    AccountCreated {
+        mutation: Mutation,
+        result: Result<Sent, FinTrakError>,
+    },
+    AccountUpdated {
+        mutation: Mutation,
+        result: Result<Sent, FinTrakError>,
+    },
+    AccountDeleted {
+        account_name: String,
+        result: Result<Sent, FinTrakError>,
+    },
+    Categories {
+        categories: Result<Vec<Category>, FinTrakError>,
+        transactions: Result<Vec<Transaction>, FinTrakError>, // For the delete confirmation
+    },
+    CategoryCreated {
+        mutation: Mutation,
+        result: Result<Sent, FinTrakError>,
+    },
+    CategoryUpdated {
+        mutation: Mutation,
+        result: Result<Sent, FinTrakError>,
+    },
+    /// The ledger the category detail page splits into budget periods.
+    CategoryHistory(Result<Vec<Transaction>, FinTrakError>),
+    CategoryDeleted {
+        nickname: String,
+        result: Result<Sent, FinTrakError>,
+    },
+    ReportDetails {
+        summaries: Result<Vec<CategorySummary>, FinTrakError>,
+        transactions: Result<Vec<Transaction>, FinTrakError>, // To show kinds and signs
+    },
+    Transactions(Result<Vec<Transaction>, FinTrakError>),
+    /// The names the transaction form's pickers complete to.
+    TransactionOptions {
+        categories: Result<Vec<Category>, FinTrakError>,
+        accounts: Result<Vec<Account>, FinTrakError>,
+    },
+    /// A category or account created from inside the transaction form.
+    InlineCategoryCreated {
+        nickname: String,
+        mutation: Mutation,
+        result: Result<Sent, FinTrakError>,
+    },
+    InlineAccountCreated {
+        account_name: String,
+        mutation: Mutation,
+        result: Result<Sent, FinTrakError>,
+    },
+    /// The transaction to prefill the edit form with.
+    TransactionLoaded {
+        trans_id: i32,
+        result: Result<Transaction, FinTrakError>,
+    },
+    TransactionCreated {
+        mutation: Mutation,
+        result: Result<Sent, FinTrakError>,
+    },
+    TransactionUpdated {
+        mutation: Mutation,
+        result: Result<Sent, FinTrakError>,
+    },
+    /// The accounts the transfer form picks from.
+    TransferAccounts(Result<Vec<Account>, FinTrakError>),
+    TransferCreated {
+        mutation: Mutation,
+        result: Result<Sent, FinTrakError>,
+    },
+    TransactionDeleted {
+        trans_id: i32,
+        result: Result<Sent, FinTrakError>,
+    },
+    /// A pass over the offline journal finished.
+    Synced(SyncReport),
+    /// A mutation taken back, it goes on the redo stack if that worked. `session`
+    /// is `App::session` when it started, results from before a logout are dropped.
+    Undone {
+        session: u64,
+        mutation: Mutation,
+        result: Result<Sent, FinTrakError>,
+    },
+    /// A mutation made again after an undo.
+    Redone {
+        session: u64,
+        mutation: Mutation,
+        result: Result<Sent, FinTrakError>,
+    },
+}
+
+/// Runs requests in the background and posts their results to the event loop.
+#[derive(Clone)]
+pub struct Dispatcher {
+    client: FinTrakClient,
+    tx: UnboundedSender<Action>,
+}
+
+impl Dispatcher {
+    pub fn new(client: FinTrakClient, tx: UnboundedSender<Action>) -> Self {
+        Self { client, tx }
+    }
+
+    pub fn client(&self) -> &FinTrakClient {
+        &self.client
+    }
+
+    /// Spawn `task` with its own client handle; the `Action` it returns is sent to the loop.
+    pub fn spawn<F, Fut>(&self, task: F) -> AbortHandle
+    where
+        F: FnOnce(FinTrakClient) -> Fut,
+        Fut: Future<Output = Action> + Send + 'static,
+    {
+        let tx = self.tx.clone();
+        let future = task(self.client.clone());
+        tokio::spawn(async move {
+            // The receiver only goes away when the app is shutting down
+            let _ = tx.send(future.await);
+        })
+        .abort_handle()
+    }
+}
@@ -0,0 +1,661 @@
+//! In-memory stand-in for the FinTrak backend, used by `--demo`.
+//!
+//! Requests are still built with `reqwest`, so they go through exactly the same code
+//! as against the real server; only the transport is replaced by `MockBackend::handle`.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use chrono::{Days, Local};
+use reqwest::{Method, Request, StatusCode};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::money::Money;
+
+use super::models::{
+    Account, Category, CategorySummary, LoginData, LoginResponse, NewAccount, NewCategory,
+    SessionInfo, SignupData, Transaction, TransactionData,
+    TransactionKind::{Expense, Income, Refund},
+    TransferData,
+};
+
+/// Credentials of the seeded demo user, shown on the login page.
+pub const DEMO_EMAIL: &str = "demo@fintrak.app";
+pub const DEMO_PASSWORD: &str = "demo";
+
+/// Pretend to be a server on the network, so spinners and cancelling can be tried out.
+const LATENCY: Duration = Duration::from_millis(150);
+
+type Response = (StatusCode, String);
+
+struct User {
+    username: String,
+    email: String,
+    password: String,
+}
+
+/// Everything the demo server knows. Lost when the app exits.
+pub struct MockBackend {
+    users: Vec<User>,
+    tokens: HashMap<String, String>, // token -> email
+    accounts: Vec<Account>,
+    categories: Vec<Category>,
+    transactions: Vec<Transaction>,
+    next_id: i32,
+}
+
+impl MockBackend {
+    /// A backend with one demo user and a few weeks worth of data.
+    pub fn seeded() -> Self {
+        let mut backend = Self {
+            users: vec![User {
+                username: "Demo".to_string(),
+                email: DEMO_EMAIL.to_string(),
+                password: DEMO_PASSWORD.to_string(),
+            }],
+            tokens: HashMap::new(),
+            accounts: Vec::new(),
+            categories: Vec::new(),
+            transactions: Vec::new(),
+            next_id: 1,
+        };
+        for (account_name, account_type, opening_balance) in [
+            ("Checking", "Debit", 120000),
+            ("Visa", "Credit", 0),
+            ("Savings", "Savings", 500000),
+        ] {
+            let id = backend.next_id();
+            backend.accounts.push(Account {
+                account_id: id,
+                email: DEMO_EMAIL.to_string(),
+                account_type: account_type.to_string(),
+                account_name: account_name.to_string(),
+                opening_balance: Money::from_cents(opening_balance),
+            });
+        }
+        for (nickname, category_type, budget, budget_freq) in [
+            ("Groceries", "Expense", 40000, "monthly"),
+            ("Dining", "Expense", 12000, "monthly"),
+            ("Rent", "Expense", 150000, "monthly"),
+            ("Transport", "Expense", 3000, "weekly"),
+            ("Salary", "Income", 0, "monthly"),
+        ] {
+            backend.categories.push(Category {
+                email: DEMO_EMAIL.to_string(),
+                nickname: nickname.to_string(),
+                category_type: category_type.to_string(),
+                budget: Money::from_cents(budget),
+                budget_freq: budget_freq.to_string(),
+            });
+        }
+        // Dated relative to today, so the demo always looks current
+        let today = Local::now().date_naive();
+        for (days_ago, category_name, account_name, amount, kind, notes) in [
+            (21, "Salary", "Checking", 320000, Income, "Paycheck"),
+            (20, "Rent", "Checking", 150000, Expense, "Monthly rent"),
+            (18, "Groceries", "Visa", 8245, Expense, "Weekly shop"),
+            (15, "Dining", "Visa", 6400, Expense, "Birthday dinner"),
+            (11, "Groceries", "Visa", 7630, Expense, "Weekly shop"),
+            (9, "Transport", "Checking", 2400, Expense, "Taxi home"),
+            (6, "Dining", "Checking", 4525, Expense, "Pizza night"),
+            (4, "Groceries", "Checking", 2310, Expense, "Farmers market"),
+            (3, "Dining", "Visa", 1200, Refund, "Overcharged, refunded"),
+            (2, "Dining", "Visa", 1850, Expense, "Lunch with the team"),
+            (1, "Transport", "Visa", 1275, Expense, "Bus pass top-up"),
+        ] {
+            let id = backend.next_id();
+            backend.transactions.push(Transaction {
+                trans_id: id,
+                email: DEMO_EMAIL.to_string(),
+                category_name: category_name.to_string(),
+                account_name: account_name.to_string(),
+                amount: Money::from_cents(amount),
+                kind,
+                notes: notes.to_string(),
+                date: today.checked_sub_days(Days::new(days_ago)).unwrap_or(today),
+                transfer_id: None,
+            });
+        }
+        backend.add_transfer(TransferData {
+            email: DEMO_EMAIL.to_string(),
+            from_account: "Checking".to_string(),
+            to_account: "Savings".to_string(),
+            amount: Money::from_cents(50000),
+            notes: "Monthly savings".to_string(),
+            date: today.checked_sub_days(Days::new(19)).unwrap_or(today),
+        });
+        backend
+    }
+
+    fn next_id(&mut self) -> i32 {
+        let id = self.next_id;
+        self.next_id += 1;
+        id
+    }
+
+    /// Answer one request the way the real backend would.
+    pub fn handle(&mut self, request: &Request) -> Response {
+        let path = request.url().path().trim_start_matches('/').to_string();
+        let query: HashMap<String, String> = request.url().query_pairs().into_owned().collect();
+        let body = request
+            .body()
+            .and_then(|body| body.as_bytes())
+            .unwrap_or_default();
+
+        // Everything but signing up and logging in needs a valid session
+        let token = request
+            .headers()
+            .get(reqwest::header::AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .unwrap_or_default();
+        let user = self.tokens.get(token).cloned();
+
+        match (request.method().clone(), path.as_str()) {
+            (Method::POST, "signup") => self.signup(body),
+            (Method::POST, "login") => self.login(body),
+            (Method::POST, "logout") => match self.tokens.remove(token) {
+                Some(_) => (StatusCode::OK, "Logged out".to_string()),
+                None => (StatusCode::UNAUTHORIZED, "Not logged in".to_string()),
+            },
+            (method, endpoint) => match user {
+                None => (StatusCode::UNAUTHORIZED, "Not logged in".to_string()),
+                Some(email) => self.authenticated(method, endpoint, &email, &query, body),
+            },
+        }
+    }
+
+    fn authenticated(
+        &mut self,
+        method: Method,
+        endpoint: &str,
+        email: &str,
+        query: &HashMap<String, String>,
+        body: &[u8],
+    ) -> Response {
+        let param = |name: &str| query.get(name).cloned().unwrap_or_default();
+        // Users only ever see their own data
+        if query
+            .get("email")
+            .is_some_and(|requested| requested != email)
+        {
+            return (StatusCode::UNAUTHORIZED, "Not your data".to_string());
+        }
+        match (method, endpoint) {
+            (Method::GET, "session") => {
+                let username = self
+                    .users
+                    .iter()
+                    .find(|user| user.email == email)
+                    .map(|user| user.username.clone())
+                    .unwrap_or_default();
+                json(StatusCode::OK, &SessionInfo { username })
+            }
+            (Method::GET, "account_summary") => {
+                let accounts: Vec<&Account> = self
+                    .accounts
+                    .iter()
+                    .filter(|account| account.email == email)
+                    .collect();
+                json(StatusCode::OK, &accounts)
+            }
+            (Method::POST, "account_create") => match parse::<NewAccount>(body) {
+                Ok(new_account) => self.create_account(new_account),
+                Err(response) => response,
+            },
+            (Method::PUT, "update_account") => {
+                let Ok(account_id) = param("account_id").parse::<i32>() else {
+                    return (StatusCode::BAD_REQUEST, "Invalid account id".to_string());
+                };
+                match parse::<NewAccount>(body) {
+                    Ok(account) => self.update_account(account_id, account),
+                    Err(response) => response,
+                }
+            }
+            (Method::DELETE, "delete_account") => {
+                let account_name = param("account_name");
+                let before = self.accounts.len();
+                self.accounts
+                    .retain(|a| !(a.email == email && a.account_name == account_name));
+                deleted(before != self.accounts.len(), "Account")
+            }
+            (Method::GET, "category_summary") => {
+                let categories: Vec<&Category> = self
+                    .categories
+                    .iter()
+                    .filter(|category| category.email == email)
+                    .collect();
+                json(StatusCode::OK, &categories)
+            }
+            (Method::POST, "category_create") => match parse::<NewCategory>(body) {
+                Ok(new_category) => self.create_category(new_category),
+                Err(response) => response,
+            },
+            (Method::PUT, "update_category") => match parse::<NewCategory>(body) {
+                Ok(category) => self.update_category(&param("category_nickname"), category),
+                Err(response) => response,
+            },
+            (Method::DELETE, "delete_category") => {
+                let nickname = param("category_nickname");
+                let before = self.categories.len();
+                self.categories
+                    .retain(|c| !(c.email == email && c.nickname == nickname));
+                deleted(before != self.categories.len(), "Category")
+            }
+            (Method::GET, "transactions") => {
+                let transactions: Vec<&Transaction> = self
+                    .transactions
+                    .iter()
+                    .filter(|t| t.email == email)
+                    .collect();
+                json(StatusCode::OK, &transactions)
+            }
+            (Method::GET, "report_overview") => json(StatusCode::OK, &self.overview(email)),
+            (Method::GET, "report_details") => json(StatusCode::OK, &self.details(email)),
+            (Method::POST, "add_trans") => match parse::<TransactionData>(body) {
+                Ok(transaction) => self.add_transaction(transaction),
+                Err(response) => response,
+            },
+            (Method::POST, "add_transfer") => match parse::<TransferData>(body) {
+                Ok(transfer) => self.add_transfer(transfer),
+                Err(response) => response,
+            },
+            (Method::PUT, "update_trans") => {
+                let Ok(trans_id) = param("trans_id").parse::<i32>() else {
+                    return (
+                        StatusCode::BAD_REQUEST,
+                        "Invalid transaction id".to_string(),
+                    );
+                };
+                match parse::<TransactionData>(body) {
+                    Ok(transaction) => self.update_transaction(email, trans_id, transaction),
+                    Err(response) => response,
+                }
+            }
+            (Method::DELETE, "delete_trans") => {
+                let Ok(trans_id) = param("trans_id").parse::<i32>() else {
+                    return (
+                        StatusCode::BAD_REQUEST,
+                        "Invalid transaction id".to_string(),
+                    );
+                };
+                // One leg of a transfer takes the other one with it
+                let transfer_id = self
+                    .transactions
+                    .iter()
+                    .find(|t| t.email == email && t.trans_id == trans_id)
+                    .and_then(|t| t.transfer_id);
+                let before = self.transactions.len();
+                self.transactions.retain(|t| {
+                    !(t.email == email
+                        && (t.trans_id == trans_id
+                            || (transfer_id.is_some() && t.transfer_id == transfer_id)))
+                });
+                deleted(before != self.transactions.len(), "Transaction")
+            }
+            _ => (StatusCode::NOT_FOUND, "Not Found".to_string()),
+        }
+    }
+
+    fn signup(&mut self, body: &[u8]) -> Response {
+        let data = match parse::<SignupData>(body) {
+            Ok(data) => data,
+            Err(response) => return response,
+        };
+        if self.users.iter().any(|user| user.email == data.email) {
+            // What the real backend answers for a known email
+            return (StatusCode::OK, "Login successful".to_string());
+        }
+        self.users.push(User {
+            username: data.username,
+            email: data.email,
+            password: data.password,
+        });
+        (StatusCode::CREATED, "User created".to_string())
+    }
+
+    fn login(&mut self, body: &[u8]) -> Response {
+        let data = match parse::<LoginData>(body) {
+            Ok(data) => data,
+            Err(response) => return response,
+        };
+        let Some(user) = self.users.iter().find(|user| user.email == data.email) else {
+            return (StatusCode::NOT_FOUND, "Email not found".to_string());
+        };
+        if user.password != data.password {
+            return (StatusCode::UNAUTHORIZED, "Invalid password".to_string());
+        }
+        let username = user.username.clone();
+        let response = LoginResponse {
+            token: format!("demo-token-{}", self.next_id()),
+            username,
+        };
+        self.tokens.insert(response.token.clone(), data.email);
+        json(StatusCode::OK, &response)
+    }
+
+    fn create_account(&mut self, new_account: NewAccount) -> Response {
+        if self
+            .accounts
+            .iter()
+            .any(|a| a.email == new_account.email && a.account_name == new_account.account_name)
+        {
+            return (
+                StatusCode::BAD_REQUEST,
+                "Account name already exists".to_string(),
+            );
+        }
+        let account_id = self.next_id();
+        self.accounts.push(Account {
+            account_id,
+            email: new_account.email,
+            account_type: new_account.account_type,
+            account_name: new_account.account_name,
+            opening_balance: new_account.opening_balance,
+        });
+        (StatusCode::CREATED, "Account created".to_string())
+    }
+
+    /// All or nothing: the account changes and its transactions follow a rename.
+    fn update_account(&mut self, account_id: i32, account: NewAccount) -> Response {
+        let email = account.email.clone();
+        if self.accounts.iter().any(|a| {
+            a.email == email && a.account_id != account_id && a.account_name == account.account_name
+        }) {
+            return (
+                StatusCode::BAD_REQUEST,
+                "Account name already exists".to_string(),
+            );
+        }
+        let Some(existing) = self
+            .accounts
+            .iter_mut()
+            .find(|a| a.email == email && a.account_id == account_id)
+        else {
+            return (StatusCode::NOT_FOUND, "Account not found".to_string());
+        };
+        let old_name = std::mem::replace(&mut existing.account_name, account.account_name.clone());
+        existing.account_type = account.account_type;
+        existing.opening_balance = account.opening_balance;
+        for transaction in self
+            .transactions
+            .iter_mut()
+            .filter(|t| t.email == email && t.account_name == old_name)
+        {
+            transaction.account_name = account.account_name.clone();
+        }
+        (StatusCode::OK, "Account updated".to_string())
+    }
+
+    fn create_category(&mut self, new_category: NewCategory) -> Response {
+        if self
+            .categories
+            .iter()
+            .any(|c| c.email == new_category.email && c.nickname == new_category.nickname)
+        {
+            return (
+                StatusCode::BAD_REQUEST,
+                "Category already exists".to_string(),
+            );
+        }
+        self.categories.push(Category {
+            email: new_category.email,
+            nickname: new_category.nickname,
+            category_type: new_category.category_type,
+            budget: new_category.budget,
+            budget_freq: new_category.budget_freq,
+        });
+        (StatusCode::CREATED, "Category created".to_string())
+    }
+
+    /// All or nothing: the category changes and its transactions follow a rename.
+    fn update_category(&mut self, nickname: &str, category: NewCategory) -> Response {
+        let email = category.email.clone();
+        if category.nickname != nickname
+            && self
+                .categories
+                .iter()
+                .any(|c| c.email == email && c.nickname == category.nickname)
+        {
+            return (
+                StatusCode::BAD_REQUEST,
+                "Category already exists".to_string(),
+            );
+        }
+        let Some(existing) = self
+            .categories
+            .iter_mut()
+            .find(|c| c.email == email && c.nickname == nickname)
+        else {
+            return (StatusCode::NOT_FOUND, "Category not found".to_string());
+        };
+        existing.nickname = category.nickname.clone();
+        existing.category_type = category.category_type;
+        existing.budget = category.budget;
+        existing.budget_freq = category.budget_freq;
+        for transaction in self
+            .transactions
+            .iter_mut()
+            .filter(|t| t.email == email && t.category_name == nickname && !t.is_transfer())
+        {
+            transaction.category_name = category.nickname.clone();
+        }
+        (StatusCode::OK, "Category updated".to_string())
+    }
+
+    /// The category and account a transaction points at have to exist.
+    fn check_references(&self, data: &TransactionData) -> Result<(), Response> {
+        if !self
+            .categories
+            .iter()
+            .any(|c| c.email == data.email && c.nickname == data.category_name)
+        {
+            return Err((StatusCode::BAD_REQUEST, "Category not found".to_string()));
+        }
+        if !self
+            .accounts
+            .iter()
+            .any(|a| a.email == data.email && a.account_name == data.account_name)
+        {
+            return Err((StatusCode::BAD_REQUEST, "Account not found".to_string()));
+        }
+        Ok(())
+    }
+
+    fn add_transaction(&mut self, data: TransactionData) -> Response {
+        if let Err(response) = self.check_references(&data) {
+            return response;
+        }
+        let trans_id = self.next_id();
+        self.transactions.push(Transaction {
+            trans_id,
+            email: data.email,
+            category_name: data.category_name,
+            account_name: data.account_name,
+            amount: data.amount,
+            kind: data.kind,
+            notes: data.notes,
+            date: data.date,
+            transfer_id: None,
+        });
+        (StatusCode::CREATED, "Transaction created".to_string())
+    }
+
+    /// Two legs, out of one account and into the other, linked by a transfer id.
+    fn add_transfer(&mut self, data: TransferData) -> Response {
+        for account_name in [&data.from_account, &data.to_account] {
+            if !self
+                .accounts
+                .iter()
+                .any(|a| a.email == data.email && &a.account_name == account_name)
+            {
+                return (StatusCode::BAD_REQUEST, "Account not found".to_string());
+            }
+        }
+        if data.from_account == data.to_account {
+            return (
+                StatusCode::BAD_REQUEST,
+                "Cannot transfer to the same account".to_string(),
+            );
+        }
+        if data.amount <= Money::ZERO {
+            return (
+                StatusCode::BAD_REQUEST,
+                "Amount must be positive".to_string(),
+            );
+        }
+        let transfer_id = self.next_id();
+        for (account_name, kind) in [(&data.from_account, Expense), (&data.to_account, Income)] {
+            let trans_id = self.next_id();
+            self.transactions.push(Transaction {
+                trans_id,
+                email: data.email.clone(),
+                category_name: String::new(),
+                account_name: account_name.clone(),
+                amount: data.amount,
+                kind,
+                notes: data.notes.clone(),
+                date: data.date,
+                transfer_id: Some(transfer_id),
+            });
+        }
+        (StatusCode::CREATED, "Transfer created".to_string())
+    }
+
+    fn update_transaction(
+        &mut self,
+        email: &str,
+        trans_id: i32,
+        data: TransactionData,
+    ) -> Response {
+        if let Err(response) = self.check_references(&data) {
+            return response;
+        }
+        let Some(transaction) = self
+            .transactions
+            .iter_mut()
+            .find(|t| t.email == email && t.trans_id == trans_id)
+        else {
+            return (StatusCode::NOT_FOUND, "Transaction not found".to_string());
+        };
+        if transaction.is_transfer() {
+            return (
+                StatusCode::BAD_REQUEST,
+                "Transfers cannot be edited as transactions".to_string(),
+            );
+        }
+        transaction.category_name = data.category_name;
+        transaction.account_name = data.account_name;
+        transaction.amount = data.amount;
+        transaction.kind = data.kind;
+        transaction.notes = data.notes;
+        transaction.date = data.date;
+        (StatusCode::OK, "Transaction updated".to_string())
+    }
+
+    /// Expenses minus refunds, income counts against it. Transfers are not spending.
+    fn spent(&self, email: &str, category: &str) -> Money {
+        -self
+            .transactions
+            .iter()
+            .filter(|t| t.email == email && t.category_name == category && !t.is_transfer())
+            .map(Transaction::signed_amount)
+            .sum::<Money>()
+    }
+
+    fn overview(&self, email: &str) -> Vec<String> {
+        let mut lines = vec!["Category Summary:".to_string()];
+        for category in self.categories.iter().filter(|c| c.email == email) {
+            let spent = self.spent(email, &category.nickname);
+            lines.push(if is_income(category) {
+                format!("{}: ${} received", category.nickname, -spent)
+            } else {
+                format!(
+                    "{}: ${} of ${} {}",
+                    category.nickname, spent, category.budget, category.budget_freq
+                )
+            });
+        }
+        lines.push("Account Summary:".to_string());
+        for account in self.accounts.iter().filter(|a| a.email == email) {
+            let balance = account.balance(self.transactions.iter().filter(|t| t.email == email));
+            lines.push(format!("{}: ${} balance", account.account_name, balance));
+        }
+        lines
+    }
+
+    fn details(&self, email: &str) -> Vec<CategorySummary> {
+        self.categories
+            .iter()
+            .filter(|c| c.email == email)
+            .map(|category| {
+                let transactions: Vec<&Transaction> = self
+                    .transactions
+                    .iter()
+                    .filter(|t| {
+                        t.email == email && t.category_name == category.nickname && !t.is_transfer()
+                    })
+                    .collect();
+                let spent = self.spent(email, &category.nickname);
+                // Income categories have no budget to go over, they show what came in
+                let income = is_income(category);
+                CategorySummary {
+                    nickname: category.nickname.clone(),
+                    budget: category.budget,
+                    budget_freq: category.budget_freq.clone(),
+                    overbudget: !income && spent > category.budget,
+                    total: if income { -spent } else { spent },
+                    transaction_idz: transactions.iter().map(|t| t.trans_id).collect(),
+                    cat_trans: transactions
+                        .iter()
+                        .map(|t| {
+                            format!(
+                                "${:+} | {} | {}",
+                                t.signed_amount(),
+                                t.account_name,
+                                t.notes
+                            )
+                        })
+                        .collect(),
+                }
+            })
+            .collect()
+    }
+}
+
+/// Hand `request` to the demo backend after a short, network-like pause.
+pub async fn respond(backend: &Arc<Mutex<MockBackend>>, request: Request) -> Response {
+    tokio::time::sleep(LATENCY).await;
+    match backend.lock() {
+        Ok(mut backend) => backend.handle(&request),
+        Err(_) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Demo backend crashed".to_string(),
+        ),
+    }
+}
+
+fn is_income(category: &Category) -> bool {
+    category.category_type.eq_ignore_ascii_case("income")
+}
+
+fn parse<T: DeserializeOwned>(body: &[u8]) -> Result<T, Response> {
+    serde_json::from_slice(body).map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))
+}
+
+fn json<T: Serialize + ?Sized>(status: StatusCode, value: &T) -> Response {
+    match serde_json::to_string(value) {
+        Ok(body) => (status, body),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+    }
+}
+
+fn deleted(found: bool, what: &str) -> Response {
+    if found {
+        (StatusCode::OK, format!("{} deleted", what))
+    } else {
+        (StatusCode::NOT_FOUND, format!("{} not found", what))
+    }
+}
@@ -0,0 +1,503 @@
+pub mod mock;
+pub mod models;
+
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::Duration;
+
+use reqwest::{Client, Method, RequestBuilder, StatusCode};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use models::{
+    Account, Category, CategorySummary, LoginData, LoginResponse, NewAccount, NewCategory,
+    SessionInfo, SignupData, Transaction, TransactionData, TransferData,
+};
+
+use crate::error::{AuthError, FinTrakError};
+use crate::offline::{self, PendingWrite, SyncReport};
+use mock::MockBackend;
+
+/// Where the FinTrak backend listens when nothing else is configured.
+pub const DEFAULT_BASE_URL: &str = "http://localhost:8000";
+
+/// What happened to a write, see `FinTrakClient::submit`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Sent {
+    /// The server accepted it.
+    Delivered,
+    /// The server was unreachable, the write waits in the offline journal.
+    Queued,
+}
+
+/// Thin typed wrapper around the FinTrak REST API.
+///
+/// Cloning is cheap (the underlying `reqwest::Client` is reference counted), so every
+/// page keeps its own copy instead of building a new client per request. The session
+/// token is shared between all clones, so logging in or out is seen by every page.
+///
+/// Reads are cached on disk and served from that cache while the server is
+/// unreachable, writes are queued in the offline journal until it is back.
+///
+/// `FinTrakClient::demo` answers from an in-memory `MockBackend` instead of the network.
+#[derive(Clone)]
+pub struct FinTrakClient {
+    http: Client,
+    base_url: String,
+    token: Arc<RwLock<Option<String>>>,
+    offline: Arc<AtomicBool>, // The last request could not reach the server
+    mock: Option<Arc<Mutex<MockBackend>>>, // Set in demo mode
+}
+
+impl FinTrakClient {
+    pub fn new(base_url: &str, timeout: Duration) -> Self {
+        let http = Client::builder()
+            .timeout(timeout)
+            .build()
+            .unwrap_or_else(|_| Client::new());
+        Self {
+            http,
+            base_url: base_url.trim_end_matches('/').to_string(),
+            token: Arc::new(RwLock::new(None)),
+            offline: Arc::new(AtomicBool::new(false)),
+            mock: None,
+        }
+    }
+
+    /// A client backed by seeded sample data, nothing leaves the process.
+    pub fn demo() -> Self {
+        Self {
+            mock: Some(Arc::new(Mutex::new(MockBackend::seeded()))),
+            ..Self::new(DEFAULT_BASE_URL, Duration::from_secs(1))
+        }
+    }
+
+    pub fn is_demo(&self) -> bool {
+        self.mock.is_some()
+    }
+
+    /// Whether the last request failed to reach the server.
+    pub fn is_offline(&self) -> bool {
+        self.offline.load(Ordering::Relaxed)
+    }
+
+    pub fn token(&self) -> Option<String> {
+        self.token.read().ok().and_then(|token| token.clone())
+    }
+
+    fn set_token(&self, token: Option<String>) {
+        if let Ok(mut current) = self.token.write() {
+            *current = token;
+        }
+    }
+
+    /// Start a request to `path`, authenticated with the session token if there is one.
+    fn request(&self, method: Method, path: &str) -> RequestBuilder {
+        let request = self
+            .http
+            .request(method, format!("{}/{}", self.base_url, path));
+        match self.token() {
+            Some(token) => request.bearer_auth(token),
+            None => request,
+        }
+    }
+
+    async fn send(&self, request: RequestBuilder) -> Result<(StatusCode, String), FinTrakError> {
+        if let Some(ref mock) = self.mock {
+            let request = request.build().map_err(network_error)?;
+            return Ok(mock::respond(mock, request).await);
+        }
+        let result = async {
+            let response = request.send().await.map_err(network_error)?;
+            let status = response.status();
+            let body = response.text().await.map_err(network_error)?;
+            Ok((status, body))
+        }
+        .await;
+        self.offline.store(
+            matches!(result, Err(ref e) if FinTrakError::is_unreachable(e)),
+            Ordering::Relaxed,
+        );
+        result
+    }
+
+    /// Send the request and return the body if the server answered with `expected`.
+    async fn expect(
+        &self,
+        request: RequestBuilder,
+        expected: StatusCode,
+    ) -> Result<String, FinTrakError> {
+        let (status, body) = self.send(request).await?;
+        if status == expected {
+            Ok(body)
+        } else {
+            Err(FinTrakError::from_status(status, &body))
+        }
+    }
+
+    async fn get_json<T: DeserializeOwned>(
+        &self,
+        request: RequestBuilder,
+    ) -> Result<T, FinTrakError> {
+        let body = self.expect(request, StatusCode::OK).await?;
+        serde_json::from_str(&body).map_err(|e| FinTrakError::Decode(e.to_string()))
+    }
+
+    /// `get_json`, falling back to the last cached answer when the server is unreachable.
+    async fn get_cached<T>(
+        &self,
+        request: RequestBuilder,
+        email: &str,
+        key: &str,
+    ) -> Result<T, FinTrakError>
+    where
+        T: Serialize + DeserializeOwned,
+    {
+        // Demo data must not end up in the cache of a real account
+        if self.is_demo() {
+            return self.get_json(request).await;
+        }
+        match self.get_json(request).await {
+            Ok(value) => {
+                // A failed cache write only costs us the offline copy
+                let _ = offline::store_cached(email, key, &value);
+                Ok(value)
+            }
+            Err(e) if e.is_unreachable() => offline::load_cached(email, key).ok_or(e),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Send one write to the server as is.
+    async fn deliver(&self, write: &PendingWrite) -> Result<(), FinTrakError> {
+        let (request, expected) = match write {
+            PendingWrite::AddTransaction(transaction) => (
+                self.request(Method::POST, "add_trans").json(transaction),
+                StatusCode::CREATED,
+            ),
+            PendingWrite::AddTransfer(transfer) => (
+                self.request(Method::POST, "add_transfer").json(transfer),
+                StatusCode::CREATED,
+            ),
+            PendingWrite::CreateAccount(new_account) => (
+                self.request(Method::POST, "account_create")
+                    .json(new_account),
+                StatusCode::CREATED,
+            ),
+            PendingWrite::DeleteAccount {
+                email,
+                account_name,
+            } => (
+                self.request(Method::DELETE, "delete_account")
+                    .query(&[("email", email), ("account_name", account_name)]),
+                StatusCode::OK,
+            ),
+            PendingWrite::UpdateAccount {
+                account_id,
+                account,
+            } => (
+                self.request(Method::PUT, "update_account")
+                    .query(&[("account_id", account_id)])
+                    .json(account),
+                StatusCode::OK,
+            ),
+            PendingWrite::CreateCategory(new_category) => (
+                self.request(Method::POST, "category_create")
+                    .json(new_category),
+                StatusCode::CREATED,
+            ),
+            PendingWrite::DeleteCategory { email, nickname } => (
+                self.request(Method::DELETE, "delete_category")
+                    .query(&[("email", email), ("category_nickname", nickname)]),
+                StatusCode::OK,
+            ),
+            PendingWrite::UpdateCategory { nickname, category } => (
+                self.request(Method::PUT, "update_category")
+                    .query(&[("email", &category.email), ("category_nickname", nickname)])
+                    .json(category),
+                StatusCode::OK,
+            ),
+            PendingWrite::DeleteTransaction { trans_id, .. } => (
+                self.request(Method::DELETE, "delete_trans")
+                    .query(&[("trans_id", trans_id)]),
+                StatusCode::OK,
+            ),
+            PendingWrite::UpdateTransaction {
+                trans_id,
+                transaction,
+            } => (
+                self.request(Method::PUT, "update_trans")
+                    .query(&[("trans_id", trans_id)])
+                    .json(transaction),
+                StatusCode::OK,
+            ),
+        };
+        self.expect(request, expected).await.map(drop)
+    }
+
+    /// Deliver `write`, or queue it in the offline journal if the server is unreachable.
+    async fn submit(&self, write: PendingWrite) -> Result<Sent, FinTrakError> {
+        match self.deliver(&write).await {
+            Ok(()) => Ok(Sent::Delivered),
+            Err(e) if e.is_unreachable() => match offline::enqueue(write) {
+                Ok(()) => Ok(Sent::Queued),
+                // Without a journal there is nothing better than the original error
+                Err(_) => Err(e),
+            },
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Send the queued writes of `email` in order until the server stops answering.
+    ///
+    /// Writes the server rejects go to the conflict report instead of blocking the rest.
+    pub async fn replay_journal(&self, email: &str) -> SyncReport {
+        let mut report = SyncReport::default();
+        for entry in offline::pending(email) {
+            match self.deliver(&entry.write).await {
+                Ok(()) => {
+                    let _ = offline::resolve(entry.id);
+                    report.replayed += 1;
+                }
+                // Still offline or logged out: try again later, nothing was rejected
+                Err(e) if e.is_unreachable() || matches!(e, FinTrakError::Auth(_)) => break,
+                Err(e) => {
+                    let _ = offline::reject(entry.id, e.to_string());
+                    report.rejected += 1;
+                }
+            }
+        }
+        report
+    }
+
+    pub async fn signup(
+        &self,
+        username: &str,
+        email: &str,
+        password: &str,
+    ) -> Result<(), FinTrakError> {
+        let signup_data = SignupData {
+            username: username.to_string(),
+            email: email.to_string(),
+            password: password.to_string(),
+        };
+        let request = self.request(Method::POST, "signup").json(&signup_data);
+        match self.send(request).await? {
+            (StatusCode::CREATED, _) => Ok(()),
+            (StatusCode::OK, body) if body.contains("Login successful") => {
+                Err(FinTrakError::Validation(
+                    "Email already registered. Please try another one.".to_string(),
+                ))
+            }
+            (status, body) => Err(FinTrakError::from_status(status, &body)),
+        }
+    }
+
+    /// Log in, keep the session token for every later request and return the username.
+    pub async fn login(&self, email: &str, password: &str) -> Result<String, FinTrakError> {
+        let login_data = LoginData {
+            email: email.to_string(),
+            password: password.to_string(),
+        };
+        let request = self.request(Method::POST, "login").json(&login_data);
+        match self.send(request).await? {
+            (StatusCode::OK, body) => {
+                let session: LoginResponse =
+                    serde_json::from_str(&body).map_err(|e| FinTrakError::Decode(e.to_string()))?;
+                self.set_token(Some(session.token));
+                Ok(session.username)
+            }
+            (StatusCode::UNAUTHORIZED, _) => Err(FinTrakError::Auth(AuthError::InvalidCredentials)),
+            (StatusCode::NOT_FOUND, _) => Err(FinTrakError::Auth(AuthError::UnknownEmail)),
+            (status, body) => Err(FinTrakError::from_status(status, &body)),
+        }
+    }
+
+    /// Reuse a token from an earlier run, see `App::resume_session`.
+    pub fn set_session_token(&self, token: String) {
+        self.set_token(Some(token));
+    }
+
+    /// Ask the server who the current token belongs to, returning the username.
+    pub async fn current_user(&self) -> Result<String, FinTrakError> {
+        let request = self.request(Method::GET, "session");
+        let session: SessionInfo = self.get_json(request).await?;
+        Ok(session.username)
+    }
+
+    /// Forget the session right away and return a future that revokes it on the server.
+    ///
+    /// The token is dropped before anything is awaited, so a login that happens while
+    /// the revoke is still in flight keeps its new token.
+    pub fn logout(&self) -> impl Future<Output = Result<(), FinTrakError>> + Send + 'static {
+        let token = self.token.write().ok().and_then(|mut token| token.take());
+        let client = self.clone();
+        async move {
+            let Some(token) = token else {
+                return Ok(());
+            };
+            let request = client
+                .http
+                .post(format!("{}/logout", client.base_url))
+                .bearer_auth(token);
+            client.expect(request, StatusCode::OK).await.map(drop)
+        }
+    }
+
+    pub async fn account_summary(&self, email: &str) -> Result<Vec<Account>, FinTrakError> {
+        let request = self
+            .request(Method::GET, "account_summary")
+            .query(&[("email", email)]);
+        self.get_cached(request, email, offline::ACCOUNTS).await
+    }
+
+    pub async fn create_account(&self, new_account: &NewAccount) -> Result<Sent, FinTrakError> {
+        self.submit(PendingWrite::CreateAccount(new_account.clone()))
+            .await
+    }
+
+    pub async fn delete_account(
+        &self,
+        email: &str,
+        account_name: &str,
+    ) -> Result<Sent, FinTrakError> {
+        self.submit(PendingWrite::DeleteAccount {
+            email: email.to_string(),
+            account_name: account_name.to_string(),
+        })
+        .await
+    }
+
+    /// Replace name, type and opening balance of `account_id`, transactions follow a rename.
+    pub async fn update_account(
+        &self,
+        account_id: i32,
+        account: &NewAccount,
+    ) -> Result<Sent, FinTrakError> {
+        self.submit(PendingWrite::UpdateAccount {
+            account_id,
+            account: account.clone(),
+        })
+        .await
+    }
+
+    pub async fn category_summary(&self, email: &str) -> Result<Vec<Category>, FinTrakError> {
+        let request = self
+            .request(Method::GET, "category_summary")
+            .query(&[("email", email)]);
+        self.get_cached(request, email, offline::CATEGORIES).await
+    }
+
+    pub async fn create_category(&self, new_category: &NewCategory) -> Result<Sent, FinTrakError> {
+        self.submit(PendingWrite::CreateCategory(new_category.clone()))
+            .await
+    }
+
+    /// Replace `nickname` with `category` in one step, transactions follow a rename.
+    pub async fn update_category(
+        &self,
+        nickname: &str,
+        category: &NewCategory,
+    ) -> Result<Sent, FinTrakError> {
+        self.submit(PendingWrite::UpdateCategory {
+            nickname: nickname.to_string(),
+            category: category.clone(),
+        })
+        .await
+    }
+
+    pub async fn delete_category(&self, email: &str, nickname: &str) -> Result<Sent, FinTrakError> {
+        self.submit(PendingWrite::DeleteCategory {
+            email: email.to_string(),
+            nickname: nickname.to_string(),
+        })
+        .await
+    }
+
+    pub async fn report_overview(&self, email: &str) -> Result<Vec<String>, FinTrakError> {
+        let request = self
+            .request(Method::GET, "report_overview")
+            .query(&[("email", email)]);
+        self.get_cached(request, email, offline::REPORT_OVERVIEW)
+            .await
+    }
+
+    pub async fn report_details(&self, email: &str) -> Result<Vec<CategorySummary>, FinTrakError> {
+        let request = self
+            .request(Method::GET, "report_details")
+            .query(&[("email", email)]);
+        self.get_cached(request, email, offline::REPORT_DETAILS)
+            .await
+    }
+
+    /// Every transaction of `email`, newest or oldest first is up to the caller.
+    pub async fn transactions(&self, email: &str) -> Result<Vec<Transaction>, FinTrakError> {
+        let request = self
+            .request(Method::GET, "transactions")
+            .query(&[("email", email)]);
+        self.get_cached(request, email, offline::TRANSACTIONS).await
+    }
+
+    /// One transaction of the ledger, looked up to prefill the edit form.
+    pub async fn transaction(
+        &self,
+        email: &str,
+        trans_id: i32,
+    ) -> Result<Transaction, FinTrakError> {
+        self.transactions(email)
+            .await?
+            .into_iter()
+            .find(|transaction| transaction.trans_id == trans_id)
+            .ok_or_else(|| {
+                FinTrakError::Validation(format!("Transaction #{} no longer exists", trans_id))
+            })
+    }
+
+    pub async fn add_transaction(
+        &self,
+        transaction: &TransactionData,
+    ) -> Result<Sent, FinTrakError> {
+        self.submit(PendingWrite::AddTransaction(transaction.clone()))
+            .await
+    }
+
+    /// Move money between two accounts, recorded as a linked pair of transactions.
+    pub async fn add_transfer(&self, transfer: &TransferData) -> Result<Sent, FinTrakError> {
+        self.submit(PendingWrite::AddTransfer(transfer.clone()))
+            .await
+    }
+
+    /// Replace category, account, amount, notes and date of `trans_id`.
+    pub async fn update_transaction(
+        &self,
+        trans_id: i32,
+        transaction: &TransactionData,
+    ) -> Result<Sent, FinTrakError> {
+        self.submit(PendingWrite::UpdateTransaction {
+            trans_id,
+            transaction: transaction.clone(),
+        })
+        .await
+    }
+
+    pub async fn delete_transaction(
+        &self,
+        email: &str,
+        trans_id: i32,
+    ) -> Result<Sent, FinTrakError> {
+        self.submit(PendingWrite::DeleteTransaction {
+            email: email.to_string(),
+            trans_id,
+        })
+        .await
+    }
+}
+
+fn network_error(e: reqwest::Error) -> FinTrakError {
+    if e.is_timeout() {
+        FinTrakError::Timeout
+    } else {
+        FinTrakError::Network(e)
+    }
+}
@@ -0,0 +1,217 @@
+use chrono::{Local, NaiveDate};
+use serde::{Deserialize, Serialize};
+
+use crate::money::Money;
+
+#[derive(Serialize, Deserialize)]
+pub struct SignupData {
+    pub username: String,
+    pub email: String,
+    pub password: String,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct LoginData {
+    pub email: String,
+    pub password: String,
+}
+
+/// Returned by `/login`: the bearer token for every later request.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct LoginResponse {
+    pub token: String,
+    pub username: String,
+}
+
+/// Returned by `/session` when the bearer token is still valid.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct SessionInfo {
+    pub username: String,
+}
+
+#[allow(dead_code)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Account {
+    pub account_id: i32,
+    pub email: String,
+    pub account_type: String,
+    pub account_name: String,
+    // What was in the account before the first transaction, older servers have none
+    #[serde(default)]
+    pub opening_balance: Money,
+}
+
+impl Account {
+    /// The opening balance plus every transaction on this account, transfers included.
+    pub fn balance<'a>(&self, transactions: impl IntoIterator<Item = &'a Transaction>) -> Money {
+        self.opening_balance
+            + transactions
+                .into_iter()
+                .filter(|t| t.account_name == self.account_name)
+                .map(Transaction::signed_amount)
+                .sum::<Money>()
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct NewAccount {
+    pub email: String,
+    pub account_type: String,
+    pub account_name: String,
+    #[serde(default)]
+    pub opening_balance: Money,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Category {
+    #[allow(dead_code)]
+    pub email: String,
+    pub nickname: String,
+    pub category_type: String,
+    pub budget: Money,
+    pub budget_freq: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct NewCategory {
+    pub email: String,
+    pub nickname: String,
+    pub category_type: String,
+    pub budget: Money,
+    pub budget_freq: String,
+}
+
+#[derive(Deserialize, Debug, Serialize)]
+pub struct CategorySummary {
+    pub nickname: String,
+    pub budget: Money,
+    pub budget_freq: String,
+    pub overbudget: bool,
+    pub total: Money,
+    // a vector of corresponding transaction ids of the entries below
+    pub transaction_idz: Vec<i32>,
+    // a vector of all the relevant transactions within budget freq frame
+    pub cat_trans: Vec<String>,
+}
+
+/// Which way the money of a transaction goes.
+///
+/// Amounts are always stored positive, the kind gives the sign: expenses take money
+/// out, income and refunds bring it in. A refund also lowers its category's spending.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum TransactionKind {
+    #[default]
+    Expense,
+    Income,
+    Refund,
+}
+
+impl TransactionKind {
+    pub const ALL: [TransactionKind; 3] = [
+        TransactionKind::Expense,
+        TransactionKind::Income,
+        TransactionKind::Refund,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            TransactionKind::Expense => "Expense",
+            TransactionKind::Income => "Income",
+            TransactionKind::Refund => "Refund",
+        }
+    }
+
+    /// `amount` with the sign this kind gives it.
+    pub fn signed(self, amount: Money) -> Money {
+        match self {
+            TransactionKind::Expense => -amount,
+            TransactionKind::Income | TransactionKind::Refund => amount,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct TransactionData {
+    pub email: String,
+    pub category_name: String,
+    pub amount: Money,
+    // Servers and queued writes that predate kinds only had expenses
+    #[serde(default)]
+    pub kind: TransactionKind,
+    pub notes: String,
+    pub account_name: String,
+    // Writes queued offline before dates existed have none, they keep the old behaviour
+    #[serde(default = "today")]
+    pub date: NaiveDate, // "YYYY-MM-DD"
+}
+
+fn today() -> NaiveDate {
+    Local::now().date_naive()
+}
+
+/// One row of `/transactions`, the whole ledger of a user.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Transaction {
+    pub trans_id: i32,
+    pub email: String,
+    pub category_name: String,
+    pub account_name: String,
+    pub amount: Money,
+    #[serde(default)]
+    pub kind: TransactionKind,
+    pub notes: String,
+    pub date: NaiveDate, // "YYYY-MM-DD"
+    // Shared by the two legs of a transfer, which have no category
+    #[serde(default)]
+    pub transfer_id: Option<i32>,
+}
+
+impl Transaction {
+    /// Negative for money going out.
+    pub fn signed_amount(&self) -> Money {
+        self.kind.signed(self.amount)
+    }
+
+    /// One leg of a transfer between two accounts, not income or spending.
+    pub fn is_transfer(&self) -> bool {
+        self.transfer_id.is_some()
+    }
+
+    /// What `/add_trans` needs to post this transaction again.
+    pub fn data(&self) -> TransactionData {
+        TransactionData {
+            email: self.email.clone(),
+            category_name: self.category_name.clone(),
+            amount: self.amount,
+            kind: self.kind,
+            notes: self.notes.clone(),
+            account_name: self.account_name.clone(),
+            date: self.date,
+        }
+    }
+
+    /// The category, or "Transfer" for the legs of a transfer.
+    pub fn category_label(&self) -> &str {
+        if self.is_transfer() {
+            "Transfer"
+        } else {
+            &self.category_name
+        }
+    }
+}
+
+/// Money moved between two accounts of the same user, sent to `/add_transfer`.
+///
+/// The server records it as a linked pair: an expense leg on `from_account` and an
+/// income leg on `to_account`, sharing a `transfer_id` and without a category, so
+/// budgets never see it.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TransferData {
+    pub email: String,
+    pub from_account: String,
+    pub to_account: String,
+    pub amount: Money,
+    pub notes: String,
+    pub date: NaiveDate, // "YYYY-MM-DD"
+}
@@ -1,13 +1,34 @@
-use crate::ui::report::{get_report_overview, get_category_overview, get_account_overview};
+use crate::action::{Action, Dispatcher};
+use crate::api::mock::{DEMO_EMAIL, DEMO_PASSWORD};
+use crate::api::{FinTrakClient, Sent};
+use crate::error::{AuthError, FinTrakError};
+use crate::history::{History, Mutation};
+use crate::config::Config;
+use crate::offline;
+use crate::session;
+use crate::ui::report::{
+    get_account_overview_and_cash_flow, get_category_overview, get_report_overview,
+};
+use crate::ui::components::{PopupOutcome, Toast};
 use crate::ui::transaction_create::TransactionCreate;
+use crate::ui::transaction_main::TransactionMain;
+use crate::ui::transfer_create::TransferCreate;
 use crate::ui::{
-    account_main::AccountMain, category_main::CategoryMain, cover::CoverPage, homepage::Homepage,
+    account_detail::AccountDetail, account_main::AccountMain, category_detail::CategoryDetail,
+    category_main::CategoryMain, cover::CoverPage, homepage::Homepage,
     login::LoginPage, report_main::ReportMain, signup::SignupPage,
 };
-#[allow(unused_imports)]
-use crossterm::event::{self, Event, KeyCode, KeyModifiers};
-#[allow(unused_imports)]
+use crossterm::event::{Event, EventStream, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use futures::StreamExt;
+use ratatui::Frame;
 use std::time::Duration;
+use tokio::sync::mpsc::{UnboundedReceiver, UnboundedSender};
+
+/// How often the screen is redrawn when no key or network result arrives.
+const TICK_RATE: Duration = Duration::from_millis(100);
+
+/// Ticks between two looks at the offline journal (5 seconds).
+const SYNC_EVERY: u32 = 50;
 
 pub enum State {
     Cover,             // Cover page
@@ -15,9 +36,13 @@ pub enum State {
     Login,             // Login page
     Homepage,          // Homepage
     AccountMain,       // Account Main page
+    AccountDetail,     // One account's history, opened from Account Main
     CategoryMain,      // Category Main page
+    CategoryDetail,    // One category's budget periods, opened from Category Main
     ReportMain,        // Report page
     TransactionCreate, // Transaction Create page
+    TransactionMain,   // Transactions ledger page
+    TransferCreate,    // Transfer between accounts page
 }
 
 pub struct App {
@@ -27,184 +52,1035 @@ pub struct App {
     pub login_page: LoginPage,                         // Login page
     pub homepage: Option<Homepage>, // Homepage (initialized after successful login)
     pub account_main: Option<AccountMain>, // Account Main (accessed from homepage)
+    pub account_detail: Option<AccountDetail>, // Account history (accessed from Account Main)
     pub category_main: Option<CategoryMain>, // Category Main (accessed from homepage)
+    pub category_detail: Option<CategoryDetail>, // Category history (accessed from Category Main)
     pub report_main: Option<ReportMain>, // Report page (accessed from homepage)
     pub transaction_create: Option<TransactionCreate>, // Transaction Create page
+    pub transaction_main: Option<TransactionMain>, // Transactions ledger (accessed from homepage)
+    pub transfer_create: Option<TransferCreate>, // Transfer form (homepage or accounts page)
+    pub dispatcher: Dispatcher,     // Spawns requests, results come back as Actions
+    sync_ticks: u32,                // Ticks since the offline journal was last checked
+    syncing: bool,                  // A journal replay is in flight
+    history: History,               // Changes of this session, for undo and redo
+    undoing: bool,                  // An undo or redo is in flight
+    session: u64,                   // Bumped on logout, older undo results are dropped
+    resuming: bool,                 // The stored session is being checked, see `resume_session`
+    toast: Option<Toast>,           // Note in the corner, shown on every page
 }
 
 impl App {
-    pub fn new() -> Self {
+    pub fn new(config: &Config, actions: UnboundedSender<Action>) -> Self {
+        let client = if config.demo {
+            FinTrakClient::demo()
+        } else {
+            FinTrakClient::new(&config.server, config.timeout)
+        };
+        let dispatcher = Dispatcher::new(client, actions);
+        let mut login_page = LoginPage::new(dispatcher.clone());
+        if config.demo {
+            login_page.set_email(DEMO_EMAIL);
+            login_page.set_password(DEMO_PASSWORD);
+            login_page.response_message =
+                format!("Demo mode: log in as {} / {}", DEMO_EMAIL, DEMO_PASSWORD);
+        } else if let Some(ref email) = config.default_email {
+            login_page.set_email(email);
+        }
         Self {
             state: State::Cover,
             cover_page: CoverPage::new(),
-            signup_page: SignupPage::new(),
-            login_page: LoginPage::new(),
+            signup_page: SignupPage::new(dispatcher.clone()),
+            login_page,
             homepage: None,           // Initially, homepage is not set
             account_main: None,       // Initially, account_main is not set
+            account_detail: None,     // Initially, account_detail is not set
             category_main: None,      // Initially, category_main is not set
+            category_detail: None,    // Initially, category_detail is not set
             report_main: None,        // Initially, report page is not set
             transaction_create: None, // Initially, transaction_create is not set
+            transaction_main: None,   // Initially, transaction_main is not set
+            transfer_create: None,    // Initially, transfer_create is not set
+            dispatcher,
+            sync_ticks: 0,
+            syncing: false,
+            history: History::default(),
+            undoing: false,
+            session: 0,
+            resuming: false,
+            toast: None,
         }
     }
-}
 
-pub async fn run_app<B: ratatui::backend::Backend>(
-    mut terminal: ratatui::Terminal<B>,
-    mut app: App,
-) -> std::io::Result<()> {
-    loop {
-        // Render the current state of the app
-        // before rendering, load the overview everytime
-        if let Some(ref mut curr_homepage) = app.homepage {
-            curr_homepage.report_overview = get_report_overview(curr_homepage.email.clone()).await;
-            curr_homepage.category_overview = get_category_overview(curr_homepage.email.clone()).await;
-            curr_homepage.account_overview = get_account_overview(curr_homepage.email.clone()).await;
-        }
-        terminal.draw(|f| match app.state {
-            State::Cover => app.cover_page.render(f),
-            State::Signup => app.signup_page.render(f),
-            State::Login => app.login_page.render(f),
+    /// Skip the login if "remember me" left a session that the server still accepts.
+    pub fn resume_session(&mut self) {
+        // The demo backend has never seen the stored token
+        if self.dispatcher.client().is_demo() {
+            return;
+        }
+        let Some(stored) = session::load() else {
+            return;
+        };
+        self.dispatcher.client().set_session_token(stored.token);
+        self.login_page.set_email(&stored.email);
+        self.resuming = true;
+        self.dispatcher.spawn(move |client| async move {
+            let result = client.current_user().await;
+            Action::SessionResumed {
+                email: stored.email,
+                username: stored.username,
+                result,
+            }
+        });
+    }
+
+    fn enter_homepage(&mut self, username: String, email: String) {
+        self.resuming = false; // A login by hand wins over the stored session
+        self.homepage = Some(Homepage::new(username, email));
+        self.go_home();
+        self.sync(); // Flush whatever was queued during an earlier offline run
+    }
+
+    /// Switch to the homepage and refresh its overviews, which may be stale by now.
+    fn go_home(&mut self) {
+        self.state = State::Homepage;
+        self.refresh_sync_status();
+        self.refresh_overviews();
+    }
+
+    /// Fetch everything the homepage shows again.
+    fn refresh_overviews(&mut self) {
+        if let Some(ref homepage) = self.homepage {
+            let email = homepage.email.clone();
+            self.dispatcher.spawn(move |client| async move {
+                let (report, category, (account, cash_flow)) = tokio::join!(
+                    get_report_overview(&client, &email),
+                    get_category_overview(&client, &email),
+                    get_account_overview_and_cash_flow(&client, &email),
+                );
+                Action::Overviews {
+                    report,
+                    category,
+                    account,
+                    cash_flow,
+                }
+            });
+        }
+    }
+
+    /// Reload the journal counters and conflict report shown on the homepage.
+    fn refresh_sync_status(&mut self) {
+        let offline = self.dispatcher.client().is_offline();
+        if let Some(ref mut homepage) = self.homepage {
+            homepage.offline = offline;
+            homepage.queued = offline::pending(&homepage.email).len();
+            homepage.conflicts = offline::conflicts(&homepage.email);
+            if homepage.conflicts.is_empty() {
+                homepage.show_conflicts = false;
+            }
+        }
+    }
+
+    /// Replay the offline journal in the background if it has anything for this user.
+    fn sync(&mut self) {
+        self.refresh_sync_status();
+        let Some(ref homepage) = self.homepage else {
+            return;
+        };
+        if self.syncing || homepage.queued == 0 {
+            return;
+        }
+        self.syncing = true;
+        let email = homepage.email.clone();
+        self.dispatcher.spawn(move |client| async move {
+            Action::Synced(client.replay_journal(&email).await)
+        });
+    }
+
+    /// An edit goes back to the report it came from, refreshed if something was saved.
+    /// A new transaction goes back to the homepage.
+    fn close_transaction_form(&mut self, saved: bool) {
+        let from_report = self
+            .transaction_create
+            .as_ref()
+            .is_some_and(|form| form.is_edit());
+        match self.report_main {
+            Some(ref mut report_main) if from_report => {
+                if saved {
+                    report_main.initialize();
+                }
+                self.state = State::ReportMain;
+            }
+            _ => self.go_home(),
+        }
+    }
+
+    fn open_transfer_form(&mut self, mut transfer_create: TransferCreate) {
+        transfer_create.initialize();
+        self.transfer_create = Some(transfer_create);
+        self.state = State::TransferCreate;
+    }
+
+    /// Back to the accounts page the form was opened from, with fresh balances if
+    /// something was saved, or the homepage.
+    fn close_transfer_form(&mut self, saved: bool) {
+        let from_accounts = self
+            .transfer_create
+            .as_ref()
+            .is_some_and(|form| form.is_from_accounts());
+        match self.account_main {
+            Some(ref mut account_main) if from_accounts => {
+                if saved {
+                    account_main.initialize();
+                }
+                self.state = State::AccountMain;
+            }
+            _ => self.go_home(),
+        }
+    }
+
+    fn logout(&mut self) {
+        // Drop the server session and every page holding user data
+        tokio::spawn(self.dispatcher.client().logout());
+        if !self.dispatcher.client().is_demo() {
+            let _ = session::clear();
+        }
+        self.homepage = None;
+        self.account_main = None;
+        self.account_detail = None;
+        self.category_main = None;
+        self.category_detail = None;
+        self.report_main = None;
+        self.transaction_create = None;
+        self.transaction_main = None;
+        self.transfer_create = None;
+        self.history.clear();
+        self.undoing = false;
+        self.session += 1;
+        self.toast = None;
+        self.login_page.clear_password();
+        self.login_page.response_message = "Logged out.".to_string();
+        self.state = State::Login;
+    }
+
+    pub fn render(&mut self, f: &mut Frame) {
+        self.render_page(f);
+        if let Some(ref toast) = self.toast {
+            toast.render(f);
+        }
+    }
+
+    fn render_page(&mut self, f: &mut Frame) {
+        match self.state {
+            State::Cover => self.cover_page.render(f),
+            State::Signup => self.signup_page.render(f),
+            State::Login => self.login_page.render(f),
             State::Homepage => {
-                if let Some(ref homepage) = app.homepage {
+                if let Some(ref homepage) = self.homepage {
                     homepage.render(f);
                 }
             }
             State::AccountMain => {
-                if let Some(ref mut account_main) = app.account_main {
+                if let Some(ref mut account_main) = self.account_main {
                     account_main.render(f);
                 }
             }
+            State::AccountDetail => {
+                if let Some(ref mut account_detail) = self.account_detail {
+                    account_detail.render(f);
+                }
+            }
             State::CategoryMain => {
-                if let Some(ref mut category_main) = app.category_main {
+                if let Some(ref mut category_main) = self.category_main {
                     category_main.render(f);
                 }
             }
+            State::CategoryDetail => {
+                if let Some(ref mut category_detail) = self.category_detail {
+                    category_detail.render(f);
+                }
+            }
             State::ReportMain => {
-                if let Some(ref mut report_main) = app.report_main {
+                if let Some(ref mut report_main) = self.report_main {
                     report_main.render(f);
                 }
             }
             State::TransactionCreate => {
-                if let Some(ref transaction_create) = app.transaction_create {
+                if let Some(ref transaction_create) = self.transaction_create {
                     transaction_create.render(f);
                 }
             }
-        })?;
+            State::TransactionMain => {
+                if let Some(ref mut transaction_main) = self.transaction_main {
+                    transaction_main.render(f);
+                }
+            }
+            State::TransferCreate => {
+                if let Some(ref transfer_create) = self.transfer_create {
+                    transfer_create.render(f);
+                }
+            }
+        }
+    }
+
+    /// Ctrl-Z and Ctrl-Y undo and redo anywhere once logged in. While a toast offers
+    /// it, plain `u` or `r` does the same on a page that is just being browsed.
+    fn undo_key(&self, key_event: &KeyEvent) -> Option<KeyCode> {
+        self.homepage.as_ref()?;
+        match key_event.code {
+            KeyCode::Char('z') | KeyCode::Char('y')
+                if key_event.modifiers.contains(KeyModifiers::CONTROL) =>
+            {
+                Some(key_event.code)
+            }
+            KeyCode::Char(c @ ('u' | 'r'))
+                if key_event.modifiers.is_empty()
+                    && self.toast.as_ref().is_some_and(|toast| toast.key == Some(c))
+                    && self.is_browsing() =>
+            {
+                Some(if c == 'u' {
+                    KeyCode::Char('z')
+                } else {
+                    KeyCode::Char('y')
+                })
+            }
+            _ => None,
+        }
+    }
+
+    /// The page on screen is a list with no form, popup or request in the way.
+    fn is_browsing(&self) -> bool {
+        match self.state {
+            State::Homepage => self.homepage.as_ref().is_some_and(|h| h.error.is_none()),
+            State::AccountMain => self.account_main.as_ref().is_some_and(AccountMain::is_browsing),
+            State::CategoryMain => self.category_main.as_ref().is_some_and(CategoryMain::is_browsing),
+            State::ReportMain => self.report_main.as_ref().is_some_and(ReportMain::is_browsing),
+            _ => false,
+        }
+    }
+
+    /// A create, edit or delete went through, remember it and say how to take it back.
+    fn record(&mut self, mutation: Mutation) {
+        // Finished after a logout, there is no history to keep it in
+        if self.homepage.is_none() {
+            return;
+        }
+        self.toast = Some(Toast::new(
+            format!("{} — press u to undo", mutation.summary()),
+            Some('u'),
+        ));
+        self.history.record(mutation);
+    }
+
+    fn undo(&mut self) {
+        if self.undoing {
+            return;
+        }
+        let Some(mutation) = self.history.take_undo() else {
+            self.toast = Some(Toast::new("Nothing to undo".to_string(), None));
+            return;
+        };
+        self.undoing = true;
+        self.toast = Some(Toast::new(format!("Undoing: {}...", mutation.summary()), None));
+        let session = self.session;
+        self.dispatcher.spawn(move |client| async move {
+            let mut mutation = mutation;
+            let result = mutation.undo(&client).await;
+            Action::Undone {
+                session,
+                mutation,
+                result,
+            }
+        });
+    }
+
+    fn redo(&mut self) {
+        if self.undoing {
+            return;
+        }
+        let Some(mutation) = self.history.take_redo() else {
+            self.toast = Some(Toast::new("Nothing to redo".to_string(), None));
+            return;
+        };
+        self.undoing = true;
+        self.toast = Some(Toast::new(format!("Redoing: {}...", mutation.summary()), None));
+        let session = self.session;
+        self.dispatcher.spawn(move |client| async move {
+            let result = mutation.redo(&client).await;
+            Action::Redone {
+                session,
+                mutation,
+                result,
+            }
+        });
+    }
+
+    /// Fetch the page on screen again after an undo or redo changed what it shows.
+    fn refresh_page(&mut self) {
+        match self.state {
+            State::Homepage => self.refresh_overviews(),
+            State::AccountMain => {
+                if let Some(ref mut account_main) = self.account_main {
+                    account_main.initialize();
+                }
+            }
+            State::CategoryMain => {
+                if let Some(ref mut category_main) = self.category_main {
+                    category_main.initialize();
+                }
+            }
+            State::ReportMain => {
+                if let Some(ref mut report_main) = self.report_main {
+                    report_main.initialize();
+                }
+            }
+            State::TransactionMain => {
+                if let Some(ref mut transaction_main) = self.transaction_main {
+                    transaction_main.initialize();
+                }
+            }
+            _ => {}
+        }
+    }
 
-        // Handle user input (outside of draw)
-        if let crossterm::event::Event::Key(key_event) = crossterm::event::read()? {
-            match app.state {
-                State::Cover => {
-                    if key_event.code == KeyCode::Esc {
-                        break; // Quit on Esc
+    /// Handle one key press. Returns `true` when the app should quit.
+    pub fn handle_key(&mut self, key_event: KeyEvent) -> bool {
+        match self.undo_key(&key_event) {
+            Some(KeyCode::Char('z')) => {
+                self.undo();
+                return false;
+            }
+            Some(_) => {
+                self.redo();
+                return false;
+            }
+            None => {}
+        }
+        match self.state {
+            State::Cover => {
+                if key_event.code == KeyCode::Esc {
+                    return true; // Quit on Esc
+                }
+                match key_event.code {
+                    KeyCode::Char('1') => self.state = State::Signup,
+                    KeyCode::Char('2') => self.state = State::Login,
+                    _ => {}
+                }
+            }
+            State::Signup => {
+                if key_event.code == KeyCode::Esc && !self.signup_page.is_busy() {
+                    self.state = State::Cover; // Return to Cover when Esc is pressed
+                } else {
+                    self.signup_page
+                        .handle_input(key_event.code, key_event.modifiers);
+                }
+            }
+            State::Login => {
+                if key_event.code == KeyCode::Esc && !self.login_page.is_busy() {
+                    self.state = State::Cover; // Return to Cover on Esc
+                } else {
+                    self.login_page
+                        .handle_input(key_event.code, key_event.modifiers);
+                }
+            }
+            State::Homepage => {
+                // An error popup takes every key until it is closed
+                if let Some(popup) = self.homepage.as_mut().and_then(|h| h.error.take()) {
+                    match popup.handle_input(key_event.code) {
+                        PopupOutcome::Open(popup) => {
+                            if let Some(ref mut homepage) = self.homepage {
+                                homepage.error = Some(popup);
+                            }
+                        }
+                        PopupOutcome::Dismissed => {}
+                        PopupOutcome::Retry(()) => self.go_home(),
                     }
+                    return false;
+                }
+                if key_event.code == KeyCode::Esc {
+                    return true; // Quit from Homepage
+                }
+                if let Some(ref mut homepage) = self.homepage {
+                    let email = homepage.email.clone();
                     match key_event.code {
-                        KeyCode::Char('1') => app.state = State::Signup,
-                        KeyCode::Char('2') => app.state = State::Login,
+                        KeyCode::Char('c') if !homepage.conflicts.is_empty() => {
+                            homepage.show_conflicts = !homepage.show_conflicts;
+                        }
+                        KeyCode::Char('x') if homepage.show_conflicts => {
+                            if let Err(e) = offline::clear_conflicts(&email) {
+                                homepage.sync_message = format!("Could not dismiss conflicts: {}", e);
+                            }
+                            self.refresh_sync_status();
+                        }
+                        KeyCode::Char('1') => {
+                            let mut account_main = AccountMain::new(self.dispatcher.clone(), email);
+                            account_main.initialize();
+                            self.account_main = Some(account_main);
+                            self.state = State::AccountMain;
+                        }
+                        KeyCode::Char('2') => {
+                            let mut category_main =
+                                CategoryMain::new(self.dispatcher.clone(), email);
+                            category_main.initialize();
+                            self.category_main = Some(category_main);
+                            self.state = State::CategoryMain;
+                        }
+                        KeyCode::Char('3') => {
+                            let mut report_main = ReportMain::new(self.dispatcher.clone(), email);
+                            report_main.initialize();
+                            self.report_main = Some(report_main);
+                            self.state = State::ReportMain;
+                        }
+                        KeyCode::Char('4') => {
+                            let mut transaction_main =
+                                TransactionMain::new(self.dispatcher.clone(), email);
+                            transaction_main.initialize();
+                            self.transaction_main = Some(transaction_main);
+                            self.state = State::TransactionMain;
+                        }
+                        KeyCode::Char('l') => self.logout(),
+                        KeyCode::Char('n') => {
+                            let mut transaction_create =
+                                TransactionCreate::new(self.dispatcher.clone(), email);
+                            transaction_create.initialize();
+                            self.transaction_create = Some(transaction_create);
+                            self.state = State::TransactionCreate;
+                        }
+                        KeyCode::Char('t') => {
+                            let transfer_create = TransferCreate::new(self.dispatcher.clone(), email);
+                            self.open_transfer_form(transfer_create);
+                        }
                         _ => {}
                     }
                 }
-                State::Signup => {
-                    if key_event.code == KeyCode::Esc {
-                        app.state = State::Cover; // Return to Cover when Esc is pressed
-                    } else if app
-                        .signup_page
-                        .handle_input(key_event.code, key_event.modifiers)
-                        .await
-                    {
-                        app.state = State::Login; // After successful signup, go to Login
-                    }
-                }
-                State::Login => {
-                    if key_event.code == KeyCode::Esc {
-                        app.state = State::Cover; // Return to Cover on Esc
-                    } else if app
-                        .login_page
-                        .handle_input(key_event.code, key_event.modifiers, &mut app.homepage)
-                        .await
-                    {
-                        if app.homepage.is_some() {
-                            app.state = State::Homepage; // Transition to Homepage
-                        }
+            }
+            State::AccountMain => {
+                let Some(ref mut account_main) = self.account_main else {
+                    return false;
+                };
+                // 't' opens the transfer form, moving money out of the selected account
+                if key_event.code == KeyCode::Char('t') && account_main.is_browsing() {
+                    let from = account_main
+                        .selected_account()
+                        .map(|account| account.account_name.clone());
+                    if let Some(email) = self.homepage.as_ref().map(|h| h.email.clone()) {
+                        let transfer_create =
+                            TransferCreate::from_account(self.dispatcher.clone(), email, from);
+                        self.open_transfer_form(transfer_create);
                     }
+                } else if key_event.code == KeyCode::Enter && account_main.is_browsing() {
+                    // Enter opens the history of the selected account
+                    if let Some(account) = account_main.selected_account().cloned() {
+                        let mut account_detail = AccountDetail::new(self.dispatcher.clone(), account);
+                        account_detail.initialize();
+                        self.account_detail = Some(account_detail);
+                        self.state = State::AccountDetail;
+                    }
+                } else if account_main.handle_input(key_event.code, key_event.modifiers) {
+                    self.go_home();
                 }
-                State::Homepage => {
-                    if key_event.code == KeyCode::Esc {
-                        break; // Quit from Homepage
+            }
+            State::AccountDetail => {
+                if let Some(ref mut account_detail) = self.account_detail {
+                    if account_detail.handle_input(key_event.code, key_event.modifiers) {
+                        self.state = State::AccountMain;
                     }
-                    if let Some(ref homepage) = app.homepage {
-                        match key_event.code {
-                            KeyCode::Char('1') => {
-                                let mut account_main = AccountMain::new(homepage.email.clone());
-                                account_main.initialize().await;
-                                app.account_main = Some(account_main);
-                                app.state = State::AccountMain;
-                            }
-                            KeyCode::Char('2') => {
-                                let mut category_main = CategoryMain::new(homepage.email.clone());
-                                category_main.initialize().await;
-                                app.category_main = Some(category_main);
-                                app.state = State::CategoryMain;
-                            }
-                            KeyCode::Char('3') => {
-                                let mut report_main = ReportMain::new(homepage.email.clone());
-                                report_main.initialize().await;
-                                app.report_main = Some(report_main);
-                                app.state = State::ReportMain;
-                            }
-                            KeyCode::Char('n') => {
-                                app.transaction_create =
-                                    Some(TransactionCreate::new(homepage.email.clone()));
-                                app.state = State::TransactionCreate;
-                            }
-                            _ => {}
+                }
+            }
+            State::CategoryMain => {
+                if let Some(ref mut category_main) = self.category_main {
+                    // Enter opens the budget history of the selected category
+                    if key_event.code == KeyCode::Enter && category_main.is_browsing() {
+                        if let Some(category) = category_main.selected_category().cloned() {
+                            let mut category_detail =
+                                CategoryDetail::new(self.dispatcher.clone(), category);
+                            category_detail.initialize();
+                            self.category_detail = Some(category_detail);
+                            self.state = State::CategoryDetail;
                         }
+                    } else if category_main.handle_input(key_event.code, key_event.modifiers) {
+                        self.go_home();
                     }
                 }
-                State::AccountMain => {
-                    if let Some(ref mut account_main) = app.account_main {
-                        if account_main
-                            .handle_input(key_event.code, key_event.modifiers)
-                            .await
-                        {
-                            app.state = State::Homepage;
-                        }
+            }
+            State::CategoryDetail => {
+                if let Some(ref mut category_detail) = self.category_detail {
+                    if category_detail.handle_input(key_event.code, key_event.modifiers) {
+                        self.state = State::CategoryMain;
                     }
                 }
-                State::CategoryMain => {
-                    if let Some(ref mut category_main) = app.category_main {
-                        if category_main
-                            .handle_input(key_event.code, key_event.modifiers)
-                            .await
-                        {
-                            app.state = State::Homepage;
-                        }
+            }
+            State::ReportMain => {
+                if let Some(ref mut report_main) = self.report_main {
+                    if report_main.handle_input(key_event.code, key_event.modifiers) {
+                        self.go_home();
                     }
                 }
-                State::ReportMain => {
-                    if let Some(ref mut report_main) = app.report_main {
-                        if report_main
-                            .handle_input(key_event.code, key_event.modifiers)
-                            .await
-                        {
-                            app.state = State::Homepage;
-                        }
+            }
+            State::TransactionCreate => {
+                if let Some(ref mut transaction_create) = self.transaction_create {
+                    // Esc cancels a pending submit or closes an error first, then leaves the form
+                    if key_event.code == KeyCode::Esc && !transaction_create.is_busy() {
+                        self.close_transaction_form(false);
+                    } else {
+                        transaction_create.handle_input(key_event.code, key_event.modifiers);
+                    }
+                }
+            }
+            State::TransactionMain => {
+                if let Some(ref mut transaction_main) = self.transaction_main {
+                    if transaction_main.handle_input(key_event.code, key_event.modifiers) {
+                        self.go_home();
+                    }
+                }
+            }
+            State::TransferCreate => {
+                if let Some(ref mut transfer_create) = self.transfer_create {
+                    // Esc cancels a pending transfer or closes an error first, then leaves the form
+                    if key_event.code == KeyCode::Esc && !transfer_create.is_busy() {
+                        self.close_transfer_form(false);
+                    } else {
+                        transfer_create.handle_input(key_event.code, key_event.modifiers);
+                    }
+                }
+            }
+        }
+        false
+    }
+
+    /// Bracketed paste: the text goes to the page on screen as one piece, pages
+    /// without a text field ignore it.
+    pub fn handle_paste(&mut self, text: &str) {
+        match self.state {
+            State::Signup => self.signup_page.handle_paste(text),
+            State::Login => self.login_page.handle_paste(text),
+            State::AccountMain => {
+                if let Some(ref mut account_main) = self.account_main {
+                    account_main.handle_paste(text);
+                }
+            }
+            State::CategoryMain => {
+                if let Some(ref mut category_main) = self.category_main {
+                    category_main.handle_paste(text);
+                }
+            }
+            State::TransactionCreate => {
+                if let Some(ref mut transaction_create) = self.transaction_create {
+                    transaction_create.handle_paste(text);
+                }
+            }
+            State::TransactionMain => {
+                if let Some(ref mut transaction_main) = self.transaction_main {
+                    transaction_main.handle_paste(text);
+                }
+            }
+            State::TransferCreate => {
+                if let Some(ref mut transfer_create) = self.transfer_create {
+                    transfer_create.handle_paste(text);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Advance the spinner of the page on screen, called on every tick.
+    ///
+    /// Every few seconds this also retries the offline journal while logged in.
+    pub fn on_tick(&mut self) {
+        if self.toast.as_mut().is_some_and(|toast| !toast.tick()) {
+            self.toast = None;
+        }
+        self.sync_ticks += 1;
+        if self.sync_ticks >= SYNC_EVERY {
+            self.sync_ticks = 0;
+            self.sync();
+        }
+        match self.state {
+            State::Signup => self.signup_page.tick(),
+            State::Login => self.login_page.tick(),
+            State::AccountMain => {
+                if let Some(ref mut account_main) = self.account_main {
+                    account_main.tick();
+                }
+            }
+            State::AccountDetail => {
+                if let Some(ref mut account_detail) = self.account_detail {
+                    account_detail.tick();
+                }
+            }
+            State::CategoryMain => {
+                if let Some(ref mut category_main) = self.category_main {
+                    category_main.tick();
+                }
+            }
+            State::CategoryDetail => {
+                if let Some(ref mut category_detail) = self.category_detail {
+                    category_detail.tick();
+                }
+            }
+            State::ReportMain => {
+                if let Some(ref mut report_main) = self.report_main {
+                    report_main.tick();
+                }
+            }
+            State::TransactionCreate => {
+                if let Some(ref mut transaction_create) = self.transaction_create {
+                    transaction_create.tick();
+                }
+            }
+            State::TransactionMain => {
+                if let Some(ref mut transaction_main) = self.transaction_main {
+                    transaction_main.tick();
+                }
+            }
+            State::TransferCreate => {
+                if let Some(ref mut transfer_create) = self.transfer_create {
+                    transfer_create.tick();
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Hand a finished request back to the page that asked for it.
+    ///
+    /// Pages that were closed in the meantime are `None`, so late results are dropped.
+    pub fn handle_action(&mut self, action: Action) {
+        match action {
+            Action::SessionResumed {
+                email,
+                username,
+                result,
+            } => {
+                // The user logged in by hand meanwhile, and may have saved a new session
+                if !self.resuming {
+                    return;
+                }
+                self.resuming = false;
+                match result {
+                    Ok(username) => self.enter_homepage(username, email),
+                    // Server unreachable: keep working from the offline cache
+                    Err(e) if e.is_unreachable() => self.enter_homepage(username, email),
+                    Err(FinTrakError::Auth(AuthError::SessionExpired)) => {
+                        // Expired or revoked, it will never work again
+                        let _ = session::clear();
+                        self.login_page.response_message =
+                            "Session expired. Please log in again.".to_string();
+                    }
+                    // Anything else: keep the stored session for the next launch
+                    Err(_) => {}
+                }
+            }
+            Action::SignedUp(result) => {
+                if self.signup_page.signup_finished(result) {
+                    self.state = State::Login; // After successful signup, go to Login
+                }
+            }
+            Action::LoggedIn { email, result } => {
+                if let Some(username) = self.login_page.login_finished(&email, result) {
+                    self.enter_homepage(username, email); // Transition to Homepage
+                }
+            }
+            Action::Overviews {
+                report,
+                category,
+                account,
+                cash_flow,
+            } => {
+                if let Some(ref mut homepage) = self.homepage {
+                    homepage.overviews_loaded(report, category, account, cash_flow);
+                }
+                self.refresh_sync_status();
+            }
+            Action::Undone {
+                session,
+                mutation,
+                result,
+            } => {
+                // Started before a logout, it belongs to a history that is gone
+                if session != self.session || self.homepage.is_none() {
+                    return;
+                }
+                self.undoing = false;
+                match result {
+                    Ok(sent) => {
+                        let queued = if sent == Sent::Queued { " (queued offline)" } else { "" };
+                        self.toast = Some(Toast::new(
+                            format!("{}{} — press r to redo", mutation.undone_summary(), queued),
+                            Some('r'),
+                        ));
+                        self.history.undone(mutation);
+                        self.refresh_page();
+                    }
+                    Err(e) => {
+                        self.toast = Some(Toast::error(format!(
+                            "Could not undo \"{}\": {}",
+                            mutation.summary(),
+                            e
+                        )));
+                        self.history.undo_failed(mutation);
+                    }
+                }
+            }
+            Action::Redone {
+                session,
+                mutation,
+                result,
+            } => {
+                if session != self.session || self.homepage.is_none() {
+                    return;
+                }
+                self.undoing = false;
+                match result {
+                    Ok(sent) => {
+                        let queued = if sent == Sent::Queued { " (queued offline)" } else { "" };
+                        self.toast = Some(Toast::new(
+                            format!("{} again{} — press u to undo", mutation.summary(), queued),
+                            Some('u'),
+                        ));
+                        self.history.redone(mutation);
+                        self.refresh_page();
                     }
+                    Err(e) => {
+                        self.toast = Some(Toast::error(format!(
+                            "Could not redo \"{}\": {}",
+                            mutation.summary(),
+                            e
+                        )));
+                        self.history.redo_failed(mutation);
+                    }
+                }
+            }
+            Action::Synced(report) => {
+                self.syncing = false;
+                if let Some(ref mut homepage) = self.homepage {
+                    if report.replayed > 0 || report.rejected > 0 {
+                        homepage.sync_message = format!(
+                            "Synced {} offline change(s), {} rejected",
+                            report.replayed, report.rejected
+                        );
+                    }
+                }
+                // The overviews still show the offline copy, fetch the real thing
+                if report.replayed > 0 && matches!(self.state, State::Homepage) {
+                    self.go_home();
+                } else {
+                    self.refresh_sync_status();
+                }
+            }
+            Action::Accounts {
+                accounts,
+                transactions,
+            } => {
+                if let Some(ref mut account_main) = self.account_main {
+                    account_main.accounts_loaded(accounts, transactions);
+                }
+            }
+            Action::AccountHistory(result) => {
+                if let Some(ref mut account_detail) = self.account_detail {
+                    account_detail.history_loaded(result);
+                }
+            }
+            Action::AccountCreated { mutation, result } => {
+                let saved = result.is_ok();
+                if let Some(ref mut account_main) = self.account_main {
+                    account_main.account_saved(result);
+                }
+                if saved {
+                    self.record(mutation);
+                }
+            }
+            Action::AccountUpdated { mutation, result } => {
+                // A rename shows on the homepage too, which would otherwise wait for the way back
+                let updated = result.is_ok();
+                if let Some(ref mut account_main) = self.account_main {
+                    account_main.account_saved(result);
                 }
-                State::TransactionCreate => {
-                    if key_event.code == KeyCode::Esc {
-                        app.state = State::Homepage; // Return to Homepage on Esc
+                if updated {
+                    self.record(mutation);
+                    self.refresh_overviews();
+                }
+            }
+            Action::AccountDeleted {
+                account_name,
+                result,
+            } => {
+                let deleted = self
+                    .account_main
+                    .as_mut()
+                    .and_then(|account_main| account_main.account_deleted(account_name, result));
+                if let Some(mutation) = deleted {
+                    self.record(mutation);
+                }
+            }
+            Action::Categories {
+                categories,
+                transactions,
+            } => {
+                if let Some(ref mut category_main) = self.category_main {
+                    category_main.categories_loaded(categories, transactions);
+                }
+            }
+            Action::CategoryCreated { mutation, result }
+            | Action::CategoryUpdated { mutation, result } => {
+                let saved = result.is_ok();
+                if let Some(ref mut category_main) = self.category_main {
+                    category_main.category_saved(result);
+                }
+                if saved {
+                    self.record(mutation);
+                }
+            }
+            Action::CategoryHistory(result) => {
+                if let Some(ref mut category_detail) = self.category_detail {
+                    category_detail.history_loaded(result);
+                }
+            }
+            Action::CategoryDeleted { nickname, result } => {
+                let deleted = self
+                    .category_main
+                    .as_mut()
+                    .and_then(|category_main| category_main.category_deleted(nickname, result));
+                if let Some(mutation) = deleted {
+                    self.record(mutation);
+                }
+            }
+            Action::ReportDetails {
+                summaries,
+                transactions,
+            } => {
+                if let Some(ref mut report_main) = self.report_main {
+                    report_main.report_loaded(summaries, transactions);
+                }
+            }
+            Action::TransferAccounts(result) => {
+                if let Some(ref mut transfer_create) = self.transfer_create {
+                    transfer_create.accounts_loaded(result);
+                }
+            }
+            Action::TransferCreated { mutation, result } => {
+                if let Some(ref mut transfer_create) = self.transfer_create {
+                    let saved = transfer_create.transfer_saved(result);
+                    // Leave the form once saved, unless the user already left
+                    if saved && matches!(self.state, State::TransferCreate) {
+                        self.close_transfer_form(true);
                     }
-                    if let Some(ref mut transaction_create) = app.transaction_create {
-                        if transaction_create
-                            .handle_input(key_event.code, key_event.modifiers)
-                            .await
-                        {
-                            app.state = State::Homepage; // Return to Homepage after transaction create
+                    if saved {
+                        self.record(mutation);
+                    }
+                }
+            }
+            Action::TransactionDeleted { trans_id, result } => {
+                let deleted = self
+                    .report_main
+                    .as_mut()
+                    .and_then(|report_main| report_main.transaction_deleted(trans_id, result));
+                if let Some(mutation) = deleted {
+                    self.record(mutation);
+                }
+            }
+            Action::Transactions(result) => {
+                if let Some(ref mut transaction_main) = self.transaction_main {
+                    transaction_main.transactions_loaded(result);
+                }
+            }
+            Action::TransactionOptions {
+                categories,
+                accounts,
+            } => {
+                if let Some(ref mut transaction_create) = self.transaction_create {
+                    transaction_create.options_loaded(categories, accounts);
+                }
+            }
+            Action::InlineCategoryCreated {
+                nickname,
+                mutation,
+                result,
+            } => {
+                let saved = result.is_ok();
+                if let Some(ref mut transaction_create) = self.transaction_create {
+                    transaction_create.category_created(nickname, result);
+                }
+                if saved {
+                    self.record(mutation);
+                }
+            }
+            Action::InlineAccountCreated {
+                account_name,
+                mutation,
+                result,
+            } => {
+                let saved = result.is_ok();
+                if let Some(ref mut transaction_create) = self.transaction_create {
+                    transaction_create.account_created(account_name, result);
+                }
+                if saved {
+                    self.record(mutation);
+                }
+            }
+            Action::TransactionLoaded { trans_id, result } => {
+                if let Some(ref mut report_main) = self.report_main {
+                    let loaded = report_main.transaction_loaded(trans_id, result);
+                    // Open the edit form, unless the user already left the report
+                    if let Some(transaction) = loaded {
+                        if matches!(self.state, State::ReportMain) {
+                            let mut transaction_create =
+                                TransactionCreate::edit(self.dispatcher.clone(), &transaction);
+                            transaction_create.initialize();
+                            self.transaction_create = Some(transaction_create);
+                            self.state = State::TransactionCreate;
                         }
                     }
                 }
             }
+            Action::TransactionCreated { mutation, result }
+            | Action::TransactionUpdated { mutation, result } => {
+                if let Some(ref mut transaction_create) = self.transaction_create {
+                    let saved = transaction_create.transaction_saved(result);
+                    // Leave the form once saved, unless the user already left
+                    if saved && matches!(self.state, State::TransactionCreate) {
+                        self.close_transaction_form(true);
+                    }
+                    if saved {
+                        self.record(mutation);
+                    }
+                }
+            }
+        }
+    }
+}
+
+pub async fn run_app<B: ratatui::backend::Backend>(
+    mut terminal: ratatui::Terminal<B>,
+    mut app: App,
+    mut actions: UnboundedReceiver<Action>,
+) -> std::io::Result<()> {
+    let mut events = EventStream::new();
+    let mut ticker = tokio::time::interval(TICK_RATE);
+    let shutdown = crate::terminal::shutdown_signal();
+    tokio::pin!(shutdown);
+    app.resume_session();
+
+    loop {
+        // Render the current state of the app
+        terminal.draw(|f| app.render(f))?;
+
+        // Wait for whatever comes first: a key, a finished request or the next tick
+        tokio::select! {
+            event = events.next() => match event {
+                Some(Ok(Event::Key(key_event))) if key_event.kind == KeyEventKind::Press => {
+                    if app.handle_key(key_event) {
+                        break;
+                    }
+                }
+                Some(Ok(Event::Paste(text))) => app.handle_paste(&text),
+                Some(Ok(_)) => {} // Resize, mouse, key release: just redraw
+                Some(Err(e)) => return Err(e),
+                None => break, // stdin closed
+            },
+            Some(action) = actions.recv() => app.handle_action(action),
+            _ = ticker.tick() => app.on_tick(),
+            // SIGTERM and friends: leave through the normal path so the terminal is restored
+            _ = &mut shutdown => break,
         }
     }
     Ok(())
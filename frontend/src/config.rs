@@ -0,0 +1,179 @@
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use serde::Deserialize;
+
+use crate::api::DEFAULT_BASE_URL;
+
+/// Environment variable that overrides the backend base URL.
+pub const SERVER_ENV: &str = "FINTRAK_SERVER";
+
+const DEFAULT_TIMEOUT_SECS: u64 = 10;
+
+pub const USAGE: &str = "\
+Usage: frontend [OPTIONS]
+
+Options:
+  --server <URL>      Backend base URL (env: FINTRAK_SERVER, default: http://localhost:8000)
+  --timeout <SECS>    Request timeout in seconds (default: 10)
+  --email <EMAIL>     Email to prefill on the login page
+  --config <PATH>     Config file to read (default: ~/.config/fintrak/config.toml)
+  --demo              Explore the app with built-in sample data, no backend needed
+  -h, --help          Print this help";
+
+/// Settings that decide how the TUI talks to the backend.
+///
+/// Values are layered: built-in defaults, then the config file, then `FINTRAK_SERVER`,
+/// then command line flags, each one overriding the previous.
+#[derive(Debug, Clone)]
+pub struct Config {
+    pub server: String,
+    pub timeout: Duration,
+    pub default_email: Option<String>,
+    pub demo: bool, // Talk to the in-memory demo backend instead of `server`
+}
+
+/// The shape of `config.toml`. Every key is optional.
+#[derive(Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+struct FileConfig {
+    server: Option<String>,
+    timeout_secs: Option<u64>,
+    default_email: Option<String>,
+}
+
+/// Flags given on the command line.
+#[derive(Default)]
+struct CliArgs {
+    server: Option<String>,
+    timeout_secs: Option<u64>,
+    email: Option<String>,
+    config_path: Option<PathBuf>,
+    demo: bool,
+}
+
+/// What `main` should do after looking at the command line.
+pub enum Startup {
+    Run(Config),
+    PrintHelp,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            server: DEFAULT_BASE_URL.to_string(),
+            timeout: Duration::from_secs(DEFAULT_TIMEOUT_SECS),
+            default_email: None,
+            demo: false,
+        }
+    }
+}
+
+impl Config {
+    /// Build the config from the process arguments (without the program name).
+    pub fn load(args: impl Iterator<Item = String>) -> Result<Startup, String> {
+        let cli = match parse_args(args)? {
+            Some(cli) => cli,
+            None => return Ok(Startup::PrintHelp),
+        };
+
+        let mut config = Config::default();
+
+        // An explicit --config must exist, the default location is optional
+        let file = match cli.config_path {
+            Some(ref path) => Some(read_file(path)?),
+            None => match default_config_path() {
+                Some(path) if path.exists() => Some(read_file(&path)?),
+                _ => None,
+            },
+        };
+        if let Some(file) = file {
+            if let Some(server) = file.server {
+                config.server = server;
+            }
+            if let Some(secs) = file.timeout_secs.filter(|secs| *secs > 0) {
+                config.timeout = Duration::from_secs(secs);
+            }
+            config.default_email = file.default_email;
+        }
+
+        if let Ok(server) = std::env::var(SERVER_ENV) {
+            if !server.trim().is_empty() {
+                config.server = server;
+            }
+        }
+
+        if let Some(server) = cli.server {
+            config.server = server;
+        }
+        if let Some(secs) = cli.timeout_secs {
+            config.timeout = Duration::from_secs(secs);
+        }
+        if cli.email.is_some() {
+            config.default_email = cli.email;
+        }
+        config.demo = cli.demo;
+
+        config.server = normalize_server(&config.server);
+        Ok(Startup::Run(config))
+    }
+}
+
+/// `~/.config/fintrak/config.toml` (or the platform equivalent).
+pub fn default_config_path() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join("fintrak").join("config.toml"))
+}
+
+fn read_file(path: &Path) -> Result<FileConfig, String> {
+    let raw = std::fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read config {}: {}", path.display(), e))?;
+    toml::from_str(&raw).map_err(|e| format!("Invalid config {}: {}", path.display(), e))
+}
+
+/// Returns `None` when help was requested.
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Option<CliArgs>, String> {
+    let mut cli = CliArgs::default();
+    while let Some(arg) = args.next() {
+        // Accept both `--flag value` and `--flag=value`
+        let (flag, inline) = match arg.split_once('=') {
+            Some((flag, value)) if flag.starts_with("--") => {
+                (flag.to_string(), Some(value.to_string()))
+            }
+            _ => (arg, None),
+        };
+        let mut value = |name: &str| {
+            inline
+                .clone()
+                .or_else(|| args.next())
+                .ok_or_else(|| format!("Missing value for {}", name))
+        };
+        match flag.as_str() {
+            "-h" | "--help" => return Ok(None),
+            "--demo" => cli.demo = true,
+            "--server" => cli.server = Some(value("--server")?),
+            "--email" => cli.email = Some(value("--email")?),
+            "--config" => cli.config_path = Some(PathBuf::from(value("--config")?)),
+            "--timeout" => {
+                let raw = value("--timeout")?;
+                let secs = raw
+                    .parse::<u64>()
+                    .ok()
+                    .filter(|secs| *secs > 0)
+                    .ok_or_else(|| format!("Invalid timeout '{}', expected whole seconds", raw))?;
+                cli.timeout_secs = Some(secs);
+            }
+            other => return Err(format!("Unknown argument '{}'\n\n{}", other, USAGE)),
+        }
+    }
+    Ok(Some(cli))
+}
+
+/// Allow `localhost:9000` as shorthand for `http://localhost:9000`.
+fn normalize_server(server: &str) -> String {
+    let server = server.trim().trim_end_matches('/');
+    if server.contains("://") {
+        server.to_string()
+    } else {
+        format!("http://{}", server)
+    }
+}
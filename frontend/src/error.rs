@@ -0,0 +1,119 @@
+use std::fmt;
+
+use reqwest::StatusCode;
+
+/// Longest server message we show, error pages can be whole HTML documents.
+const MAX_MESSAGE_CHARS: usize = 200;
+
+/// Why the backend would not let us in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuthError {
+    /// Login with a wrong password.
+    InvalidCredentials,
+    /// Login with an email that has no account yet.
+    UnknownEmail,
+    /// The session token is missing, expired or was revoked.
+    SessionExpired,
+}
+
+/// Everything that can go wrong while talking to the backend.
+///
+/// Every page shows these the same way, through `ui::components::ErrorPopup`.
+#[derive(Debug)]
+pub enum FinTrakError {
+    /// The request never got an answer (server down, DNS, connection reset...).
+    Network(reqwest::Error),
+    /// The server did not answer within the configured timeout.
+    Timeout,
+    /// The server does not accept who we are.
+    Auth(AuthError),
+    /// The server rejected what we sent, the message is meant for the user.
+    Validation(String),
+    /// The server failed, or answered with a status the endpoint does not expect.
+    Server { status: StatusCode, message: String },
+    /// The response body could not be parsed into the expected type.
+    Decode(String),
+}
+
+impl FinTrakError {
+    /// Map a status the endpoint did not expect, keeping whatever the body explains.
+    pub fn from_status(status: StatusCode, body: &str) -> Self {
+        match status {
+            StatusCode::BAD_REQUEST | StatusCode::CONFLICT | StatusCode::UNPROCESSABLE_ENTITY => {
+                FinTrakError::Validation(server_message(status, body))
+            }
+            StatusCode::UNAUTHORIZED => FinTrakError::Auth(AuthError::SessionExpired),
+            _ => FinTrakError::Server {
+                status,
+                message: server_message(status, body),
+            },
+        }
+    }
+
+    /// The server could not be reached at all, as opposed to answering with an error.
+    pub fn is_unreachable(&self) -> bool {
+        matches!(self, FinTrakError::Network(_) | FinTrakError::Timeout)
+    }
+
+    /// Whether sending the very same request again has a chance of working.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            FinTrakError::Network(_) | FinTrakError::Timeout => true,
+            FinTrakError::Server { status, .. } => status.is_server_error(),
+            _ => false,
+        }
+    }
+
+    /// Short heading for the error popup.
+    pub fn title(&self) -> &'static str {
+        match self {
+            FinTrakError::Network(_) => "Cannot reach the server",
+            FinTrakError::Timeout => "Request timed out",
+            FinTrakError::Auth(AuthError::SessionExpired) => "Session expired",
+            FinTrakError::Auth(_) => "Login failed",
+            FinTrakError::Validation(_) => "Request rejected",
+            FinTrakError::Server { .. } => "Server error",
+            FinTrakError::Decode(_) => "Unexpected response",
+        }
+    }
+}
+
+impl fmt::Display for FinTrakError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FinTrakError::Network(e) => write!(f, "Request failed: {}", e),
+            FinTrakError::Timeout => write!(f, "The server did not answer in time."),
+            FinTrakError::Auth(AuthError::InvalidCredentials) => {
+                write!(f, "Invalid password. Please try again.")
+            }
+            FinTrakError::Auth(AuthError::UnknownEmail) => {
+                write!(f, "Email not registered. Please sign up first.")
+            }
+            FinTrakError::Auth(AuthError::SessionExpired) => {
+                write!(f, "Session expired. Please log in again.")
+            }
+            FinTrakError::Validation(message) => write!(f, "{}", message),
+            FinTrakError::Server { status, message } => {
+                write!(f, "The server answered {}: {}", status, message)
+            }
+            FinTrakError::Decode(e) => write!(f, "Failed to parse response: {}", e),
+        }
+    }
+}
+
+/// Something readable out of an error body: trimmed, no markup dumps, bounded length.
+fn server_message(status: StatusCode, body: &str) -> String {
+    let body = body.trim();
+    if body.is_empty() || body.starts_with('<') {
+        return status
+            .canonical_reason()
+            .unwrap_or("No details given")
+            .to_string();
+    }
+    if body.chars().count() > MAX_MESSAGE_CHARS {
+        let cut: String = body.chars().take(MAX_MESSAGE_CHARS).collect();
+        format!("{}...", cut)
+    } else {
+        body.to_string()
+    }
+}
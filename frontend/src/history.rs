@@ -0,0 +1,416 @@
+use crate::api::models::{
+    NewAccount, NewCategory, Transaction, TransactionData, TransactionKind, TransferData,
+};
+use crate::api::{FinTrakClient, Sent};
+use crate::error::FinTrakError;
+
+/// How many mutations can be undone, the oldest are forgotten first.
+const LIMIT: usize = 20;
+
+/// For the confirmation of anything that `Mutation` can take back.
+pub const UNDO_HINT: &str = "Ctrl-Z or u right after undoes this.";
+
+/// A change the user made, with enough data to make it again or take it back.
+// Named like `PendingWrite`
+#[derive(Debug, Clone)]
+pub enum Mutation {
+    /// Undone by deleting the newest transaction like it, the server picks the id.
+    CreateTransaction {
+        transaction: TransactionData,
+    },
+    CreateTransfer {
+        transfer: TransferData,
+    },
+    CreateCategory {
+        category: NewCategory,
+    },
+    CreateAccount {
+        account: NewAccount,
+    },
+    /// `before` is what undo writes back, `after` what redo writes again.
+    UpdateTransaction {
+        trans_id: i32,
+        before: TransactionData,
+        after: TransactionData,
+    },
+    UpdateCategory {
+        before: NewCategory,
+        after: NewCategory,
+    },
+    UpdateAccount {
+        account_id: i32,
+        before: NewAccount,
+        after: NewAccount,
+    },
+    /// `trans_id` is the id it had, a restored transaction gets a new one: `restored`,
+    /// known once the server has it.
+    DeleteTransaction {
+        trans_id: i32,
+        transaction: TransactionData,
+        restored: Option<i32>,
+    },
+    /// `transactions` were filed under it, undo links them back.
+    DeleteCategory {
+        category: NewCategory,
+        transactions: Vec<Transaction>,
+    },
+    DeleteAccount {
+        account: NewAccount,
+        transactions: Vec<Transaction>,
+    },
+}
+
+impl Mutation {
+    /// What was changed, for the toast: "transaction 42", "category Groceries with
+    /// its 3 transactions".
+    pub fn describe(&self) -> String {
+        let (what, transactions) = match self {
+            Mutation::CreateTransaction { transaction: t } => {
+                return format!(
+                    "transaction {:+} in {}",
+                    t.kind.signed(t.amount),
+                    t.category_name
+                )
+            }
+            Mutation::CreateTransfer { transfer: t } => {
+                return format!(
+                    "transfer of {} from {} to {}",
+                    t.amount, t.from_account, t.to_account
+                )
+            }
+            Mutation::CreateCategory { category }
+            | Mutation::UpdateCategory {
+                after: category, ..
+            } => return format!("category {}", category.nickname),
+            Mutation::CreateAccount { account }
+            | Mutation::UpdateAccount { after: account, .. } => {
+                return format!("account {}", account.account_name)
+            }
+            Mutation::DeleteTransaction { trans_id, .. }
+            | Mutation::UpdateTransaction { trans_id, .. } => {
+                return format!("transaction {}", trans_id)
+            }
+            Mutation::DeleteCategory {
+                category,
+                transactions,
+            } => (format!("category {}", category.nickname), transactions),
+            Mutation::DeleteAccount {
+                account,
+                transactions,
+            } => (format!("account {}", account.account_name), transactions),
+        };
+        match transactions.len() {
+            0 => what,
+            1 => format!("{} with its transaction", what),
+            count => format!("{} with its {} transactions", what, count),
+        }
+    }
+
+    /// What the user did, for the toast: "Deleted transaction 42".
+    pub fn summary(&self) -> String {
+        let verb = match self {
+            Mutation::CreateTransaction { .. }
+            | Mutation::CreateTransfer { .. }
+            | Mutation::CreateCategory { .. }
+            | Mutation::CreateAccount { .. } => "Created",
+            Mutation::UpdateTransaction { .. }
+            | Mutation::UpdateCategory { .. }
+            | Mutation::UpdateAccount { .. } => "Edited",
+            Mutation::DeleteTransaction { .. }
+            | Mutation::DeleteCategory { .. }
+            | Mutation::DeleteAccount { .. } => "Deleted",
+        };
+        format!("{} {}", verb, self.describe())
+    }
+
+    /// What undo did, for the toast: "Restored transaction 42".
+    pub fn undone_summary(&self) -> String {
+        let verb = match self {
+            Mutation::CreateTransaction { .. }
+            | Mutation::CreateTransfer { .. }
+            | Mutation::CreateCategory { .. }
+            | Mutation::CreateAccount { .. } => "Removed",
+            Mutation::UpdateTransaction { .. }
+            | Mutation::UpdateCategory { .. }
+            | Mutation::UpdateAccount { .. } => "Reverted",
+            Mutation::DeleteTransaction { .. }
+            | Mutation::DeleteCategory { .. }
+            | Mutation::DeleteAccount { .. } => "Restored",
+        };
+        format!("{} {}", verb, self.describe())
+    }
+
+    /// Take the mutation back: created things are deleted, edits are written back
+    /// the way they were, deleted things are posted again and what was filed under
+    /// them is filed under them again.
+    pub async fn undo(&mut self, client: &FinTrakClient) -> Result<Sent, FinTrakError> {
+        match self {
+            Mutation::CreateTransaction { transaction } => {
+                let ledger = client.transactions(&transaction.email).await?;
+                let newest = ledger
+                    .iter()
+                    .filter(|t| t.trans_id > 0 && !t.is_transfer() && t.data() == *transaction)
+                    .map(|t| t.trans_id)
+                    .max()
+                    .ok_or_else(|| not_synced("transaction"))?;
+                client.delete_transaction(&transaction.email, newest).await
+            }
+            Mutation::CreateTransfer { transfer } => {
+                // Deleting the outgoing leg takes the incoming one with it
+                let ledger = client.transactions(&transfer.email).await?;
+                let newest = ledger
+                    .iter()
+                    .filter(|t| {
+                        t.trans_id > 0
+                            && t.is_transfer()
+                            && t.kind == TransactionKind::Expense
+                            && t.account_name == transfer.from_account
+                            && t.amount == transfer.amount
+                            && t.notes == transfer.notes
+                            && t.date == transfer.date
+                    })
+                    .map(|t| t.trans_id)
+                    .max()
+                    .ok_or_else(|| not_synced("transfer"))?;
+                client.delete_transaction(&transfer.email, newest).await
+            }
+            Mutation::CreateCategory { category } => {
+                client
+                    .delete_category(&category.email, &category.nickname)
+                    .await
+            }
+            Mutation::CreateAccount { account } => {
+                client
+                    .delete_account(&account.email, &account.account_name)
+                    .await
+            }
+            Mutation::UpdateTransaction {
+                trans_id, before, ..
+            } => client.update_transaction(*trans_id, before).await,
+            Mutation::UpdateCategory { before, after } => {
+                client.update_category(&after.nickname, before).await
+            }
+            Mutation::UpdateAccount {
+                account_id, before, ..
+            } => client.update_account(*account_id, before).await,
+            Mutation::DeleteTransaction {
+                transaction,
+                restored,
+                ..
+            } => {
+                // Identical transactions can exist, the restored one is the id that is new
+                let ids = |ledger: Vec<Transaction>| -> Vec<i32> {
+                    ledger
+                        .into_iter()
+                        .filter(|t| t.trans_id > 0 && t.data() == *transaction)
+                        .map(|t| t.trans_id)
+                        .collect()
+                };
+                let before = ids(client.transactions(&transaction.email).await?);
+                let sent = client.add_transaction(transaction).await?;
+                *restored = match sent {
+                    Sent::Delivered => ids(client.transactions(&transaction.email).await?)
+                        .into_iter()
+                        .find(|id| !before.contains(id)),
+                    Sent::Queued => None,
+                };
+                Ok(sent)
+            }
+            Mutation::DeleteCategory {
+                category,
+                transactions,
+            } => {
+                // A retry after a failed relink finds the category already back
+                let exists = client
+                    .category_summary(&category.email)
+                    .await?
+                    .iter()
+                    .any(|c| c.nickname == category.nickname);
+                let sent = if exists {
+                    Sent::Delivered
+                } else {
+                    client.create_category(category).await?
+                };
+                relink(client, transactions, |data| {
+                    data.category_name = category.nickname.clone()
+                })
+                .await
+                .map_err(|e| half_restored(&format!("Category {}", category.nickname), e))?;
+                Ok(sent)
+            }
+            Mutation::DeleteAccount {
+                account,
+                transactions,
+            } => {
+                let exists = client
+                    .account_summary(&account.email)
+                    .await?
+                    .iter()
+                    .any(|a| a.account_name == account.account_name);
+                let sent = if exists {
+                    Sent::Delivered
+                } else {
+                    client.create_account(account).await?
+                };
+                relink(client, transactions, |data| {
+                    data.account_name = account.account_name.clone()
+                })
+                .await
+                .map_err(|e| half_restored(&format!("Account {}", account.account_name), e))?;
+                Ok(sent)
+            }
+        }
+    }
+
+    /// Make the mutation again after it was undone.
+    pub async fn redo(&self, client: &FinTrakClient) -> Result<Sent, FinTrakError> {
+        match self {
+            Mutation::CreateTransaction { transaction } => {
+                client.add_transaction(transaction).await
+            }
+            Mutation::CreateTransfer { transfer } => client.add_transfer(transfer).await,
+            Mutation::CreateCategory { category } => client.create_category(category).await,
+            Mutation::CreateAccount { account } => client.create_account(account).await,
+            Mutation::UpdateTransaction {
+                trans_id, after, ..
+            } => client.update_transaction(*trans_id, after).await,
+            Mutation::UpdateCategory { before, after } => {
+                client.update_category(&before.nickname, after).await
+            }
+            Mutation::UpdateAccount {
+                account_id, after, ..
+            } => client.update_account(*account_id, after).await,
+            Mutation::DeleteTransaction {
+                transaction,
+                restored,
+                ..
+            } => {
+                // Only by id, an identical transaction next to it must not go instead
+                let Some(restored) = *restored else {
+                    return Err(FinTrakError::Validation(
+                        "The restored transaction was saved offline, delete it from the ledger instead"
+                            .to_string(),
+                    ));
+                };
+                client
+                    .delete_transaction(&transaction.email, restored)
+                    .await
+            }
+            Mutation::DeleteCategory { category, .. } => {
+                client
+                    .delete_category(&category.email, &category.nickname)
+                    .await
+            }
+            Mutation::DeleteAccount { account, .. } => {
+                client
+                    .delete_account(&account.email, &account.account_name)
+                    .await
+            }
+        }
+    }
+}
+
+/// A new transaction or transfer saved offline has no id yet to delete it by.
+fn not_synced(what: &str) -> FinTrakError {
+    FinTrakError::Validation(format!(
+        "The new {} was saved offline, delete it from the ledger once it has synced",
+        what
+    ))
+}
+
+/// The category or account is back but some of its transactions are not. Undo
+/// stays available, and trying again only relinks what is missing.
+fn half_restored(what: &str, error: FinTrakError) -> FinTrakError {
+    FinTrakError::Validation(format!(
+        "{} is back, but not all of its transactions: {}",
+        what, error
+    ))
+}
+
+/// File `transactions` under a restored category or account again.
+///
+/// The server keeps them when their category or account goes, but should one have
+/// been moved elsewhere or dropped since, `link` moves it back or it is posted again.
+async fn relink(
+    client: &FinTrakClient,
+    transactions: &[Transaction],
+    link: impl Fn(&mut TransactionData),
+) -> Result<(), FinTrakError> {
+    let Some(first) = transactions.first() else {
+        return Ok(());
+    };
+    let ledger = client.transactions(&first.email).await?;
+    // Saved offline and not synced, they have no id to look them up by
+    for before in transactions.iter().filter(|t| t.trans_id > 0) {
+        match ledger.iter().find(|t| t.trans_id == before.trans_id) {
+            Some(now) => {
+                let mut data = now.data();
+                link(&mut data);
+                if data != now.data() {
+                    client.update_transaction(now.trans_id, &data).await?;
+                }
+            }
+            // The server makes both legs of a transfer at once, one cannot be posted alone
+            None if !before.is_transfer() => {
+                let mut data = before.data();
+                link(&mut data);
+                client.add_transaction(&data).await?;
+            }
+            None => {}
+        }
+    }
+    Ok(())
+}
+
+/// The mutations of this session, newest last, for undo and redo.
+#[derive(Default)]
+pub struct History {
+    undo: Vec<Mutation>,
+    redo: Vec<Mutation>,
+}
+
+impl History {
+    /// A new mutation, whatever was undone before can no longer be redone.
+    pub fn record(&mut self, mutation: Mutation) {
+        self.redo.clear();
+        self.push_undo(mutation);
+    }
+
+    fn push_undo(&mut self, mutation: Mutation) {
+        if self.undo.len() == LIMIT {
+            self.undo.remove(0);
+        }
+        self.undo.push(mutation);
+    }
+
+    /// The mutation to undo next, put it back with `undone` or `undo_failed`.
+    pub fn take_undo(&mut self) -> Option<Mutation> {
+        self.undo.pop()
+    }
+
+    /// The mutation to redo next, put it back with `redone` or `redo_failed`.
+    pub fn take_redo(&mut self) -> Option<Mutation> {
+        self.redo.pop()
+    }
+
+    pub fn undone(&mut self, mutation: Mutation) {
+        self.redo.push(mutation);
+    }
+
+    pub fn undo_failed(&mut self, mutation: Mutation) {
+        self.undo.push(mutation);
+    }
+
+    pub fn redone(&mut self, mutation: Mutation) {
+        self.push_undo(mutation);
+    }
+
+    pub fn redo_failed(&mut self, mutation: Mutation) {
+        self.redo.push(mutation);
+    }
+
+    pub fn clear(&mut self) {
+        self.undo.clear();
+        self.redo.clear();
+    }
+}
@@ -1,30 +1,46 @@
+mod action;
+mod api;
 mod app;
+mod config;
+mod error;
+mod history;
+mod money;
+mod offline;
+mod session;
+mod terminal;
 mod ui;
 
 use app::{run_app, App};
-use crossterm::{execute, terminal::{self, Clear, ClearType}};
+use config::{Config, Startup, USAGE};
 use ratatui::backend::CrosstermBackend;
 use ratatui::Terminal;
+use terminal::TerminalGuard;
 
 #[tokio::main]
 async fn main() -> std::io::Result<()> {
-    let mut stdout = std::io::stdout();
-
-    // Enable raw mode for TUI interaction
-    terminal::enable_raw_mode()?;
-
-    let backend = CrosstermBackend::new(&mut stdout);
+    // Read config before touching the terminal so errors print normally
+    let config = match Config::load(std::env::args().skip(1)) {
+        Ok(Startup::Run(config)) => config,
+        Ok(Startup::PrintHelp) => {
+            println!("{}", USAGE);
+            return Ok(());
+        }
+        Err(message) => {
+            eprintln!("{}", message);
+            std::process::exit(2);
+        }
+    };
+
+    // Raw mode and the alternate screen until `_guard` drops, panics included
+    terminal::install_panic_hook();
+    let _guard = TerminalGuard::enter()?;
+
+    let backend = CrosstermBackend::new(std::io::stdout());
     let terminal = Terminal::new(backend)?;
-    let app = App::new();
-
-    // Run the TUI app
-    let result = run_app(terminal, app).await;
-
-    // Disable raw mode before clearing the screen
-    terminal::disable_raw_mode()?;
-
-    // Clear the screen after the TUI ends
-    execute!(stdout, Clear(ClearType::All))?;
+    // Pages send finished requests back to the event loop over this channel
+    let (action_tx, action_rx) = tokio::sync::mpsc::unbounded_channel();
+    let app = App::new(&config, action_tx);
 
-    result
+    // Run the TUI app, the guard restores the terminal however it ends
+    run_app(terminal, app, action_rx).await
 }
\ No newline at end of file
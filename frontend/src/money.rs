@@ -0,0 +1,312 @@
+use std::fmt;
+use std::iter::Sum;
+use std::ops::{Add, AddAssign, Neg, Sub};
+use std::str::FromStr;
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// Symbols and codes that may come before or after an amount, they are ignored.
+const CURRENCY_SYMBOLS: [&str; 9] = ["$", "€", "£", "¥", "₹", "USD", "EUR", "GBP", "CAD"];
+
+/// Largest amount that can be typed, a trillion. Well inside what an `f64` on the
+/// wire holds to the cent, and far enough from `i64` that sums cannot overflow.
+const MAX_CENTS: i64 = 100_000_000_000_000;
+
+/// An amount of money in cents.
+///
+/// Sums and comparisons are exact, unlike `f64`. On the wire it is still a plain
+/// JSON number with two decimals, which is what the backend sends and expects.
+/// Arithmetic saturates instead of overflowing, whatever the server sends.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Money(i64);
+
+impl Money {
+    pub const ZERO: Money = Money(0);
+
+    pub const fn from_cents(cents: i64) -> Self {
+        Money(cents)
+    }
+
+    pub const fn is_zero(self) -> bool {
+        self.0 == 0
+    }
+
+    pub const fn is_negative(self) -> bool {
+        self.0 < 0
+    }
+
+    /// Parse what a person types as an amount.
+    ///
+    /// Accepts a sign, currency symbols or codes on either side, thousand separators
+    /// (`,` `.` space or `'`) and a decimal comma or point with up to two decimals:
+    /// `12.50`, `$12`, `-€1.234,56`, `1,234.5 USD`. A single separator followed by
+    /// exactly three digits is read as a thousand separator, so `1,234` is 1234.
+    pub fn parse(input: &str) -> Result<Self, String> {
+        let mut text = input.trim();
+        if text.is_empty() {
+            return Err("Enter an amount".to_string());
+        }
+
+        // Sign and currency can come in any order: -$5, $-5, 5 USD
+        let mut negative = false;
+        loop {
+            let before = text;
+            if let Some(rest) = text.strip_prefix('-') {
+                if negative {
+                    return Err("Only one minus sign please".to_string());
+                }
+                negative = true;
+                text = rest;
+            } else if let Some(rest) = text.strip_prefix('+') {
+                text = rest;
+            }
+            for symbol in CURRENCY_SYMBOLS {
+                text = text
+                    .strip_prefix(symbol)
+                    .or_else(|| text.strip_suffix(symbol))
+                    .unwrap_or(text);
+            }
+            text = text.trim();
+            if text == before {
+                break;
+            }
+        }
+        if text.is_empty() {
+            return Err("Enter an amount".to_string());
+        }
+        if let Some(bad) = text
+            .chars()
+            .find(|c| !c.is_ascii_digit() && !is_separator(*c))
+        {
+            return Err(format!("Unexpected \"{}\" in the amount", bad));
+        }
+
+        let (whole, fraction) = split_decimal(text)?;
+        let whole = parse_whole(whole)?;
+        if fraction.len() > 2 {
+            return Err("At most two decimals".to_string());
+        }
+        // "5.5" is 50 cents, "5." has none
+        let cents = format!("{:0<2}", fraction).parse::<i64>().unwrap_or(0);
+        let total = whole
+            .checked_mul(100)
+            .and_then(|whole| whole.checked_add(cents))
+            .filter(|total| *total <= MAX_CENTS)
+            .ok_or_else(|| "That amount is too large".to_string())?;
+        Ok(Money(if negative { -total } else { total }))
+    }
+}
+
+fn is_separator(c: char) -> bool {
+    matches!(c, '.' | ',' | ' ' | '\'' | '\u{a0}')
+}
+
+/// Split at the decimal separator, if there is one.
+fn split_decimal(text: &str) -> Result<(&str, &str), String> {
+    let last_point = text.rfind('.');
+    let last_comma = text.rfind(',');
+    let decimal = match (last_point, last_comma) {
+        // Both used: the later one separates the decimals
+        (Some(point), Some(comma)) => Some(point.max(comma)),
+        (Some(index), None) | (None, Some(index)) => {
+            let mark = text[index..].chars().next().unwrap_or('.');
+            let digits_after = text.len() - index - 1;
+            let used_once = text.matches(mark).count() == 1;
+            // "1,234" and "1.234.567" group thousands, "12,5" and "12.50" have decimals
+            if used_once && digits_after != 3 {
+                Some(index)
+            } else {
+                None
+            }
+        }
+        (None, None) => None,
+    };
+    match decimal {
+        Some(index) => {
+            let fraction = &text[index + 1..];
+            if !fraction.chars().all(|c| c.is_ascii_digit()) {
+                return Err("Decimals can only be digits".to_string());
+            }
+            Ok((&text[..index], fraction))
+        }
+        None => Ok((text, "")),
+    }
+}
+
+/// The part before the decimals, thousand separators have to group by three.
+fn parse_whole(whole: &str) -> Result<i64, String> {
+    if whole.is_empty() {
+        return Ok(0);
+    }
+    let groups: Vec<&str> = whole.split(is_separator).collect();
+    let grouped = groups.len() > 1;
+    let well_formed = groups.iter().enumerate().all(|(i, group)| {
+        let digits = group.len();
+        match (grouped, i) {
+            (false, _) => digits > 0,
+            (true, 0) => (1..=3).contains(&digits),
+            (true, _) => digits == 3,
+        }
+    });
+    if !well_formed {
+        return Err("Thousand separators must group digits by three".to_string());
+    }
+    // "0,001" is neither a thousand nor a tenth of a cent
+    if grouped && groups[0].starts_with('0') {
+        return Err("Use a decimal point, with at most two decimals".to_string());
+    }
+    groups
+        .concat()
+        .parse::<i64>()
+        .map_err(|_| "That amount is too large".to_string())
+}
+
+impl FromStr for Money {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Money::parse(s)
+    }
+}
+
+/// Two decimals, a minus sign when negative, no currency: `-1234.50`.
+///
+/// Width and alignment flags apply, so `{:>10}` lines amounts up, and `{:+}` also
+/// signs positive amounts.
+impl fmt::Display for Money {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let sign = if self.0 < 0 {
+            "-"
+        } else if f.sign_plus() {
+            "+"
+        } else {
+            ""
+        };
+        let cents = self.0.unsigned_abs();
+        f.pad(&format!("{}{}.{:02}", sign, cents / 100, cents % 100))
+    }
+}
+
+impl Add for Money {
+    type Output = Money;
+
+    fn add(self, other: Money) -> Money {
+        Money(self.0.saturating_add(other.0))
+    }
+}
+
+impl AddAssign for Money {
+    fn add_assign(&mut self, other: Money) {
+        self.0 = self.0.saturating_add(other.0);
+    }
+}
+
+impl Sub for Money {
+    type Output = Money;
+
+    fn sub(self, other: Money) -> Money {
+        Money(self.0.saturating_sub(other.0))
+    }
+}
+
+impl Neg for Money {
+    type Output = Money;
+
+    fn neg(self) -> Money {
+        Money(self.0.saturating_neg())
+    }
+}
+
+impl Sum for Money {
+    fn sum<I: Iterator<Item = Money>>(iter: I) -> Money {
+        iter.fold(Money::ZERO, Add::add)
+    }
+}
+
+impl Serialize for Money {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_f64(self.0 as f64 / 100.0)
+    }
+}
+
+impl<'de> Deserialize<'de> for Money {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = f64::deserialize(deserializer)?;
+        if !value.is_finite() {
+            return Err(serde::de::Error::custom("amount is not a number"));
+        }
+        // Rounding, 0.1 + 0.2 style noise from the server must not lose a cent
+        Ok(Money((value * 100.0).round() as i64))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cents(input: &str) -> i64 {
+        Money::parse(input).unwrap().0
+    }
+
+    #[test]
+    fn parses_what_people_type() {
+        assert_eq!(cents("12.50"), 1250);
+        assert_eq!(cents("12,5"), 1250);
+        assert_eq!(cents("5."), 500);
+        assert_eq!(cents("$12"), 1200);
+        assert_eq!(cents("-€1.234,56"), -123456);
+        assert_eq!(cents("$-5"), -500);
+        assert_eq!(cents("1,234.5 USD"), 123450);
+        assert_eq!(cents("1 234 567"), 123456700);
+        assert_eq!(cents("1,234"), 123400);
+    }
+
+    #[test]
+    fn rejects_what_is_not_an_amount() {
+        for input in [
+            "", "  ", "$", "--5", "12a", "1.234.5", "12,3456", "1,23,456", "1.2.3",
+        ] {
+            assert!(Money::parse(input).is_err(), "{:?} parsed", input);
+        }
+    }
+
+    #[test]
+    fn rejects_a_thousand_group_led_by_zero() {
+        for input in ["0,001", "0.001", "-0,001", "01,000"] {
+            assert!(Money::parse(input).is_err(), "{:?} parsed", input);
+        }
+        assert_eq!(cents("0,01"), 1);
+    }
+
+    #[test]
+    fn rejects_more_than_a_trillion() {
+        assert_eq!(cents("1000000000000"), MAX_CENTS);
+        assert!(Money::parse("1000000000000.01").is_err());
+        assert!(Money::parse("99999999999999999999").is_err());
+    }
+
+    #[test]
+    fn arithmetic_saturates() {
+        let max = Money(i64::MAX);
+        let min = Money(i64::MIN);
+        assert_eq!(max + Money(1), max);
+        assert_eq!(min - Money(1), min);
+        assert_eq!(-min, max);
+        assert_eq!([max, max].into_iter().sum::<Money>(), max);
+    }
+
+    #[test]
+    fn displays_two_decimals() {
+        assert_eq!(Money(123450).to_string(), "1234.50");
+        assert_eq!(Money(-5).to_string(), "-0.05");
+        assert_eq!(format!("{:+}", Money(100)), "+1.00");
+        assert_eq!(format!("{:>8}", Money(100)), "    1.00");
+    }
+
+    #[test]
+    fn round_trips_json_to_the_cent() {
+        let money: Money = serde_json::from_str("0.30000000000000004").unwrap();
+        assert_eq!(money, Money(30));
+        assert_eq!(serde_json::to_string(&Money(1250)).unwrap(), "12.5");
+    }
+}
@@ -0,0 +1,558 @@
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+use crate::api::models::{
+    Account, Category, CategorySummary, NewAccount, NewCategory, Transaction, TransactionData,
+    TransactionKind, TransferData,
+};
+use crate::session;
+
+// What the server last answered, one file per user and endpoint
+pub const ACCOUNTS: &str = "accounts";
+pub const CATEGORIES: &str = "categories";
+pub const REPORT_OVERVIEW: &str = "report_overview";
+pub const REPORT_DETAILS: &str = "report_details";
+pub const TRANSACTIONS: &str = "transactions";
+
+const JOURNAL: &str = "journal.json";
+const CONFLICTS: &str = "conflicts.json";
+
+/// Serializes every read-modify-write of the journal and the cache, requests run
+/// concurrently and would otherwise lose each other's updates.
+static LOCK: Mutex<()> = Mutex::new(());
+
+/// A write the server has not seen yet because it was unreachable.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum PendingWrite {
+    AddTransaction(TransactionData),
+    AddTransfer(TransferData),
+    CreateAccount(NewAccount),
+    DeleteAccount {
+        email: String,
+        account_name: String,
+    },
+    UpdateAccount {
+        account_id: i32,
+        account: NewAccount,
+    },
+    CreateCategory(NewCategory),
+    DeleteCategory {
+        email: String,
+        nickname: String,
+    },
+    UpdateCategory {
+        nickname: String, // Before the update, the category may be renamed
+        category: NewCategory,
+    },
+    DeleteTransaction {
+        email: String,
+        trans_id: i32,
+    },
+    UpdateTransaction {
+        trans_id: i32,
+        transaction: TransactionData,
+    },
+}
+
+impl PendingWrite {
+    /// The user the write belongs to, only their own writes are replayed.
+    pub fn email(&self) -> &str {
+        match self {
+            PendingWrite::AddTransaction(transaction)
+            | PendingWrite::UpdateTransaction { transaction, .. } => &transaction.email,
+            PendingWrite::AddTransfer(transfer) => &transfer.email,
+            PendingWrite::CreateAccount(account) | PendingWrite::UpdateAccount { account, .. } => {
+                &account.email
+            }
+            PendingWrite::CreateCategory(category)
+            | PendingWrite::UpdateCategory { category, .. } => &category.email,
+            PendingWrite::DeleteAccount { email, .. }
+            | PendingWrite::DeleteCategory { email, .. }
+            | PendingWrite::DeleteTransaction { email, .. } => email,
+        }
+    }
+
+    /// One line for the conflict report.
+    pub fn describe(&self) -> String {
+        match self {
+            PendingWrite::AddTransaction(t) => format!(
+                "Add transaction {:+} to {} ({})",
+                t.kind.signed(t.amount),
+                t.category_name,
+                t.account_name
+            ),
+            PendingWrite::AddTransfer(t) => format!(
+                "Transfer {} from {} to {}",
+                t.amount, t.from_account, t.to_account
+            ),
+            PendingWrite::CreateAccount(a) => {
+                format!("Create account {} ({})", a.account_name, a.account_type)
+            }
+            PendingWrite::DeleteAccount { account_name, .. } => {
+                format!("Delete account {}", account_name)
+            }
+            PendingWrite::UpdateAccount {
+                account_id,
+                account: a,
+            } => format!(
+                "Edit account #{} to {} ({})",
+                account_id, a.account_name, a.account_type
+            ),
+            PendingWrite::CreateCategory(c) => {
+                format!("Create category {} ({})", c.nickname, c.category_type)
+            }
+            PendingWrite::DeleteCategory { nickname, .. } => {
+                format!("Delete category {}", nickname)
+            }
+            PendingWrite::UpdateCategory { nickname, category } => format!(
+                "Edit category {} to {} ({}, budget {} {})",
+                nickname,
+                category.nickname,
+                category.category_type,
+                category.budget,
+                category.budget_freq
+            ),
+            PendingWrite::DeleteTransaction { trans_id, .. } => {
+                format!("Delete transaction #{}", trans_id)
+            }
+            PendingWrite::UpdateTransaction {
+                trans_id,
+                transaction: t,
+            } => format!(
+                "Edit transaction #{} to {:+} in {} ({})",
+                trans_id,
+                t.kind.signed(t.amount),
+                t.category_name,
+                t.account_name
+            ),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct JournalEntry {
+    pub id: u64,
+    pub write: PendingWrite,
+}
+
+/// A queued write the server turned down once it was reachable again.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Conflict {
+    pub entry: JournalEntry,
+    pub reason: String,
+}
+
+/// Outcome of one pass over the journal, see `FinTrakClient::replay_journal`.
+#[derive(Debug, Default)]
+pub struct SyncReport {
+    pub replayed: usize,
+    pub rejected: usize,
+}
+
+fn offline_dir() -> Option<PathBuf> {
+    session::data_dir().map(|dir| dir.join("offline"))
+}
+
+fn cache_path(email: &str, key: &str) -> Option<PathBuf> {
+    // Keep the email usable as a directory name on every platform
+    let user: String = email
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '.' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    offline_dir().map(|dir| dir.join("cache").join(user).join(format!("{}.json", key)))
+}
+
+fn read_json<T: DeserializeOwned>(path: Option<PathBuf>) -> Option<T> {
+    let raw = fs::read_to_string(path?).ok()?;
+    serde_json::from_str(&raw).ok()
+}
+
+fn write_json<T: Serialize + ?Sized>(path: Option<PathBuf>, value: &T) -> io::Result<()> {
+    let path = path.ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no data dir"))?;
+    let body = serde_json::to_vec(value).map_err(io::Error::other)?;
+    session::write_private(&path, &body)
+}
+
+fn lock() -> std::sync::MutexGuard<'static, ()> {
+    // A panic while holding the lock leaves the files themselves intact
+    LOCK.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// The last answer the server gave for `key`, if we ever got one.
+pub fn load_cached<T: DeserializeOwned>(email: &str, key: &str) -> Option<T> {
+    let _guard = lock();
+    read_json(cache_path(email, key))
+}
+
+/// Remember a fresh answer for when the server is out of reach.
+pub fn store_cached<T: Serialize>(email: &str, key: &str, value: &T) -> io::Result<()> {
+    let _guard = lock();
+    write_json(cache_path(email, key), value)
+}
+
+fn journal() -> Vec<JournalEntry> {
+    read_json(offline_dir().map(|dir| dir.join(JOURNAL))).unwrap_or_default()
+}
+
+fn conflicts_all() -> Vec<Conflict> {
+    read_json(offline_dir().map(|dir| dir.join(CONFLICTS))).unwrap_or_default()
+}
+
+/// Append `write` to the journal and reflect it in the cached views right away.
+pub fn enqueue(write: PendingWrite) -> io::Result<()> {
+    let _guard = lock();
+    let mut entries = journal();
+    let id = entries.iter().map(|entry| entry.id).max().unwrap_or(0) + 1;
+    apply_to_cache(&write);
+    entries.push(JournalEntry { id, write });
+    write_json(offline_dir().map(|dir| dir.join(JOURNAL)), &entries)
+}
+
+/// Queued writes of `email`, oldest first.
+pub fn pending(email: &str) -> Vec<JournalEntry> {
+    let _guard = lock();
+    journal()
+        .into_iter()
+        .filter(|entry| entry.write.email() == email)
+        .collect()
+}
+
+/// Drop an entry the server accepted.
+pub fn resolve(id: u64) -> io::Result<()> {
+    let _guard = lock();
+    let mut entries = journal();
+    entries.retain(|entry| entry.id != id);
+    write_json(offline_dir().map(|dir| dir.join(JOURNAL)), &entries)
+}
+
+/// Move an entry the server rejected from the journal to the conflict report.
+pub fn reject(id: u64, reason: String) -> io::Result<()> {
+    let _guard = lock();
+    let mut entries = journal();
+    let Some(index) = entries.iter().position(|entry| entry.id == id) else {
+        return Ok(());
+    };
+    let entry = entries.remove(index);
+    let mut conflicts = conflicts_all();
+    conflicts.push(Conflict { entry, reason });
+    // Conflicts first: losing the journal entry is worse than reporting it twice
+    write_json(offline_dir().map(|dir| dir.join(CONFLICTS)), &conflicts)?;
+    write_json(offline_dir().map(|dir| dir.join(JOURNAL)), &entries)
+}
+
+/// Rejected writes of `email`, in the order they were replayed.
+pub fn conflicts(email: &str) -> Vec<Conflict> {
+    let _guard = lock();
+    conflicts_all()
+        .into_iter()
+        .filter(|conflict| conflict.entry.write.email() == email)
+        .collect()
+}
+
+/// Dismiss the conflict report of `email`.
+pub fn clear_conflicts(email: &str) -> io::Result<()> {
+    let _guard = lock();
+    let mut conflicts = conflicts_all();
+    conflicts.retain(|conflict| conflict.entry.write.email() != email);
+    write_json(offline_dir().map(|dir| dir.join(CONFLICTS)), &conflicts)
+}
+
+/// Make the cached lists look like `write` already went through, so an offline
+/// refresh shows what the user just did. The next online fetch overwrites it all.
+fn apply_to_cache(write: &PendingWrite) {
+    let email = write.email();
+    match write {
+        PendingWrite::CreateAccount(new_account) => {
+            let path = cache_path(email, ACCOUNTS);
+            let mut accounts: Vec<Account> = read_json(path.clone()).unwrap_or_default();
+            accounts.push(Account {
+                account_id: 0, // Assigned by the server once replayed
+                email: new_account.email.clone(),
+                account_type: new_account.account_type.clone(),
+                account_name: new_account.account_name.clone(),
+                opening_balance: new_account.opening_balance,
+            });
+            let _ = write_json(path, &accounts);
+        }
+        PendingWrite::DeleteAccount { account_name, .. } => {
+            let path = cache_path(email, ACCOUNTS);
+            if let Some(mut accounts) = read_json::<Vec<Account>>(path.clone()) {
+                accounts.retain(|account| &account.account_name != account_name);
+                let _ = write_json(path, &accounts);
+            }
+        }
+        // Transactions point at the account by name, so they follow a rename
+        PendingWrite::UpdateAccount {
+            account_id,
+            account,
+        } => {
+            let path = cache_path(email, ACCOUNTS);
+            let Some(mut accounts) = read_json::<Vec<Account>>(path.clone()) else {
+                return;
+            };
+            let Some(cached) = accounts.iter_mut().find(|a| a.account_id == *account_id) else {
+                return;
+            };
+            let old_name =
+                std::mem::replace(&mut cached.account_name, account.account_name.clone());
+            cached.account_type = account.account_type.clone();
+            cached.opening_balance = account.opening_balance;
+            let _ = write_json(path, &accounts);
+
+            let path = cache_path(email, TRANSACTIONS);
+            if let Some(mut transactions) = read_json::<Vec<Transaction>>(path.clone()) {
+                for transaction in transactions.iter_mut() {
+                    if transaction.account_name == old_name {
+                        transaction.account_name = account.account_name.clone();
+                    }
+                }
+                let _ = write_json(path, &transactions);
+            }
+        }
+        PendingWrite::CreateCategory(new_category) => {
+            let path = cache_path(email, CATEGORIES);
+            let mut categories: Vec<Category> = read_json(path.clone()).unwrap_or_default();
+            categories.push(Category {
+                email: new_category.email.clone(),
+                nickname: new_category.nickname.clone(),
+                category_type: new_category.category_type.clone(),
+                budget: new_category.budget,
+                budget_freq: new_category.budget_freq.clone(),
+            });
+            let _ = write_json(path, &categories);
+        }
+        PendingWrite::DeleteCategory { nickname, .. } => {
+            let path = cache_path(email, CATEGORIES);
+            if let Some(mut categories) = read_json::<Vec<Category>>(path.clone()) {
+                categories.retain(|category| &category.nickname != nickname);
+                let _ = write_json(path, &categories);
+            }
+        }
+        // The ledger follows a rename, so the transactions stay in their category
+        PendingWrite::UpdateCategory { nickname, category } => {
+            let path = cache_path(email, CATEGORIES);
+            if let Some(mut categories) = read_json::<Vec<Category>>(path.clone()) {
+                if let Some(cached) = categories.iter_mut().find(|c| &c.nickname == nickname) {
+                    cached.nickname = category.nickname.clone();
+                    cached.category_type = category.category_type.clone();
+                    cached.budget = category.budget;
+                    cached.budget_freq = category.budget_freq.clone();
+                }
+                let _ = write_json(path, &categories);
+            }
+            let path = cache_path(email, TRANSACTIONS);
+            if let Some(mut transactions) = read_json::<Vec<Transaction>>(path.clone()) {
+                for transaction in transactions.iter_mut() {
+                    if &transaction.category_name == nickname && !transaction.is_transfer() {
+                        transaction.category_name = category.nickname.clone();
+                    }
+                }
+                let _ = write_json(path, &transactions);
+            }
+        }
+        PendingWrite::DeleteTransaction { trans_id, .. } => {
+            let path = cache_path(email, REPORT_DETAILS);
+            if let Some(mut summaries) = read_json::<Vec<CategorySummary>>(path.clone()) {
+                for summary in summaries.iter_mut() {
+                    if let Some(index) =
+                        summary.transaction_idz.iter().position(|id| id == trans_id)
+                    {
+                        summary.transaction_idz.remove(index);
+                        if index < summary.cat_trans.len() {
+                            summary.cat_trans.remove(index);
+                        }
+                    }
+                }
+                let _ = write_json(path, &summaries);
+            }
+            let path = cache_path(email, TRANSACTIONS);
+            if let Some(mut transactions) = read_json::<Vec<Transaction>>(path.clone()) {
+                remove_transaction(&mut transactions, *trans_id);
+                let _ = write_json(path, &transactions);
+            }
+        }
+        // Report totals and budgets are computed by the server, only the ledger
+        // shows the new transaction before the replay
+        PendingWrite::AddTransaction(data) => {
+            let path = cache_path(email, TRANSACTIONS);
+            let mut transactions: Vec<Transaction> = read_json(path.clone()).unwrap_or_default();
+            transactions.push(Transaction {
+                trans_id: 0, // Assigned by the server once replayed
+                email: data.email.clone(),
+                category_name: data.category_name.clone(),
+                account_name: data.account_name.clone(),
+                amount: data.amount,
+                kind: data.kind,
+                notes: data.notes.clone(),
+                date: data.date,
+                transfer_id: None,
+            });
+            let _ = write_json(path, &transactions);
+        }
+        // Transfers never touch the report, only the ledger gets the two legs
+        PendingWrite::AddTransfer(data) => {
+            let path = cache_path(email, TRANSACTIONS);
+            let mut transactions: Vec<Transaction> = read_json(path.clone()).unwrap_or_default();
+            let legs = queued_transfer(&transactions, data);
+            transactions.extend(legs);
+            let _ = write_json(path, &transactions);
+        }
+        // Same as adding: the report waits for the server, the ledger shows the edit
+        PendingWrite::UpdateTransaction {
+            trans_id,
+            transaction: data,
+        } => {
+            let path = cache_path(email, TRANSACTIONS);
+            if let Some(mut transactions) = read_json::<Vec<Transaction>>(path.clone()) {
+                if let Some(transaction) = transactions.iter_mut().find(|t| t.trans_id == *trans_id)
+                {
+                    transaction.category_name = data.category_name.clone();
+                    transaction.account_name = data.account_name.clone();
+                    transaction.amount = data.amount;
+                    transaction.kind = data.kind;
+                    transaction.notes = data.notes.clone();
+                    transaction.date = data.date;
+                }
+                let _ = write_json(path, &transactions);
+            }
+        }
+    }
+}
+
+/// Drop `trans_id` from the ledger, and the other leg too if it is a transfer.
+fn remove_transaction(transactions: &mut Vec<Transaction>, trans_id: i32) {
+    let transfer_id = transactions
+        .iter()
+        .find(|transaction| transaction.trans_id == trans_id)
+        .and_then(|transaction| transaction.transfer_id);
+    transactions.retain(|transaction| {
+        !(transaction.trans_id == trans_id
+            || (transfer_id.is_some() && transaction.transfer_id == transfer_id))
+    });
+}
+
+/// The two legs of a queued transfer. Until the server assigns real ids they get
+/// negative ones below every id in the ledger, so each queued pair stays its own.
+fn queued_transfer(transactions: &[Transaction], data: &TransferData) -> [Transaction; 2] {
+    let lowest = transactions
+        .iter()
+        .flat_map(|transaction| [transaction.trans_id, transaction.transfer_id.unwrap_or(0)])
+        .min()
+        .unwrap_or(0)
+        .min(0);
+    let leg = |trans_id: i32, account_name: &String, kind| Transaction {
+        trans_id,
+        email: data.email.clone(),
+        category_name: String::new(),
+        account_name: account_name.clone(),
+        amount: data.amount,
+        kind,
+        notes: data.notes.clone(),
+        date: data.date,
+        transfer_id: Some(lowest - 1),
+    };
+    [
+        leg(lowest - 1, &data.from_account, TransactionKind::Expense),
+        leg(lowest - 2, &data.to_account, TransactionKind::Income),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+
+    use super::*;
+    use crate::money::Money;
+
+    fn date() -> NaiveDate {
+        NaiveDate::from_ymd_opt(2024, 3, 1).unwrap()
+    }
+
+    fn transaction(trans_id: i32, transfer_id: Option<i32>) -> Transaction {
+        Transaction {
+            trans_id,
+            email: "a@b.c".to_string(),
+            category_name: String::new(),
+            account_name: "Checking".to_string(),
+            amount: Money::from_cents(1000),
+            kind: TransactionKind::Expense,
+            notes: String::new(),
+            date: date(),
+            transfer_id,
+        }
+    }
+
+    fn transfer() -> TransferData {
+        TransferData {
+            email: "a@b.c".to_string(),
+            from_account: "Checking".to_string(),
+            to_account: "Savings".to_string(),
+            amount: Money::from_cents(500),
+            notes: String::new(),
+            date: date(),
+        }
+    }
+
+    fn ids(transactions: &[Transaction]) -> Vec<i32> {
+        transactions.iter().map(|t| t.trans_id).collect()
+    }
+
+    #[test]
+    fn removing_a_transfer_leg_removes_both() {
+        let mut transactions = vec![
+            transaction(1, None),
+            transaction(2, Some(7)),
+            transaction(3, Some(7)),
+            transaction(4, Some(8)),
+            transaction(5, Some(8)),
+        ];
+        remove_transaction(&mut transactions, 3);
+        assert_eq!(ids(&transactions), [1, 4, 5]);
+    }
+
+    #[test]
+    fn removing_a_plain_transaction_keeps_the_transfers() {
+        let mut transactions = vec![
+            transaction(1, None),
+            transaction(2, None),
+            transaction(3, Some(7)),
+        ];
+        remove_transaction(&mut transactions, 2);
+        assert_eq!(ids(&transactions), [1, 3]);
+        remove_transaction(&mut transactions, 42);
+        assert_eq!(ids(&transactions), [1, 3]);
+    }
+
+    #[test]
+    fn queued_transfers_get_ids_of_their_own() {
+        let mut transactions = vec![transaction(1, None), transaction(2, Some(3))];
+        let first = queued_transfer(&transactions, &transfer());
+        transactions.extend(first.clone());
+        let second = queued_transfer(&transactions, &transfer());
+
+        assert_eq!(ids(&first), [-1, -2]);
+        assert_eq!(ids(&second), [-3, -4]);
+        assert!(first.iter().all(|t| t.transfer_id == Some(-1)));
+        assert!(second.iter().all(|t| t.transfer_id == Some(-3)));
+        assert_eq!(first[0].account_name, "Checking");
+        assert_eq!(first[0].kind, TransactionKind::Expense);
+        assert_eq!(first[1].account_name, "Savings");
+        assert_eq!(first[1].kind, TransactionKind::Income);
+
+        transactions.extend(second);
+        remove_transaction(&mut transactions, -2);
+        assert_eq!(ids(&transactions), [1, 2, -3, -4]);
+    }
+}
@@ -0,0 +1,77 @@
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+/// What "remember me" keeps on disk between launches. Never the password.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct StoredSession {
+    pub email: String,
+    pub username: String,
+    pub token: String,
+}
+
+/// `<data dir>/fintrak`, e.g. `~/.local/share/fintrak` on Linux.
+pub fn data_dir() -> Option<PathBuf> {
+    dirs::data_dir().map(|dir| dir.join("fintrak"))
+}
+
+fn session_path() -> Option<PathBuf> {
+    data_dir().map(|dir| dir.join("session.json"))
+}
+
+/// Read the stored session, if there is one and it is readable.
+pub fn load() -> Option<StoredSession> {
+    let raw = fs::read_to_string(session_path()?).ok()?;
+    serde_json::from_str(&raw).ok()
+}
+
+/// Write the session so that only the current user can read it.
+pub fn save(session: &StoredSession) -> io::Result<()> {
+    let dir = data_dir().ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no data dir"))?;
+    let body = serde_json::to_vec(session).map_err(io::Error::other)?;
+    write_private(&dir.join("session.json"), &body)
+}
+
+/// Replace `path` with `body`, readable by the current user only.
+///
+/// The body goes to a temporary file that is renamed over `path`, so a crash halfway
+/// through never leaves a truncated file behind.
+pub fn write_private(path: &Path, body: &[u8]) -> io::Result<()> {
+    let dir = path
+        .parent()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "no parent dir"))?;
+    fs::create_dir_all(dir)?;
+    let tmp = path.with_extension("tmp");
+
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+        options.mode(0o600);
+        fs::set_permissions(dir, fs::Permissions::from_mode(0o700))?;
+    }
+    let mut file = options.open(&tmp)?;
+    // `mode` only applies on creation, tighten a leftover file as well
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        file.set_permissions(fs::Permissions::from_mode(0o600))?;
+    }
+    file.write_all(body)?;
+    file.sync_all()?;
+    fs::rename(tmp, path)
+}
+
+/// Forget the stored session. Missing files are not an error.
+pub fn clear() -> io::Result<()> {
+    match session_path() {
+        Some(path) => match fs::remove_file(path) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+            _ => Ok(()),
+        },
+        None => Ok(()),
+    }
+}
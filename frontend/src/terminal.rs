@@ -0,0 +1,141 @@
+use std::backtrace::Backtrace;
+use std::fs;
+use std::io::{self, Write};
+use std::panic::{self, PanicHookInfo};
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crossterm::{
+    cursor,
+    event::{DisableBracketedPaste, EnableBracketedPaste},
+    execute,
+    terminal::{self, EnterAlternateScreen, LeaveAlternateScreen},
+};
+
+use crate::session;
+
+/// Raw mode and the alternate screen for as long as it lives.
+///
+/// Dropping it puts the terminal back, so every return path out of `main` restores
+/// it. Panics are covered by `install_panic_hook` and signals by `shutdown_signal`.
+pub struct TerminalGuard;
+
+impl TerminalGuard {
+    pub fn enter() -> io::Result<Self> {
+        terminal::enable_raw_mode()?;
+        // From here on Drop undoes whatever part of the setup went through
+        let guard = TerminalGuard;
+        // Pasted text arrives as one event instead of a burst of key presses
+        execute!(io::stdout(), EnterAlternateScreen, EnableBracketedPaste)?;
+        Ok(guard)
+    }
+}
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        restore();
+    }
+}
+
+/// Leave the alternate screen and raw mode. Safe to call more than once.
+pub fn restore() {
+    // Nothing useful to do if the terminal is gone, keep going with the rest
+    let _ = execute!(
+        io::stdout(),
+        DisableBracketedPaste,
+        LeaveAlternateScreen,
+        cursor::Show
+    );
+    let _ = terminal::disable_raw_mode();
+}
+
+/// Restore the terminal before a panic message is printed and keep a crash report.
+///
+/// Panics in request tasks only lose that request (tokio catches them), so for those
+/// the report is written but the UI keeps running untouched.
+pub fn install_panic_hook() {
+    let default_hook = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        let report = write_crash_report(info);
+        let on_ui_thread = std::thread::current().name() == Some("main");
+        if !on_ui_thread {
+            return;
+        }
+        restore();
+        default_hook(info);
+        match report {
+            Ok(path) => eprintln!("FinTrak crashed, a report was saved to {}", path.display()),
+            Err(e) => eprintln!(
+                "FinTrak crashed, and the crash report could not be saved: {}",
+                e
+            ),
+        }
+    }));
+}
+
+/// `<data dir>/fintrak/crash-reports/crash-<unix time>.txt`
+fn write_crash_report(info: &PanicHookInfo) -> io::Result<PathBuf> {
+    let dir = session::data_dir()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no data dir"))?
+        .join("crash-reports");
+    fs::create_dir_all(&dir)?;
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or_default();
+    let path = dir.join(format!("crash-{}.txt", now));
+
+    let message = if let Some(message) = info.payload().downcast_ref::<&str>() {
+        message.to_string()
+    } else if let Some(message) = info.payload().downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "non-string panic payload".to_string()
+    };
+    let location = info
+        .location()
+        .map(|location| location.to_string())
+        .unwrap_or_else(|| "unknown".to_string());
+
+    let mut file = fs::File::create(&path)?;
+    writeln!(file, "FinTrak {} crash report", env!("CARGO_PKG_VERSION"))?;
+    writeln!(file, "time (unix): {}", now)?;
+    writeln!(
+        file,
+        "thread: {}",
+        std::thread::current().name().unwrap_or("unnamed")
+    )?;
+    writeln!(file, "location: {}", location)?;
+    writeln!(file, "message: {}", message)?;
+    writeln!(file)?;
+    writeln!(file, "{}", Backtrace::force_capture())?;
+    Ok(path)
+}
+
+/// Resolves when the process is asked to stop (SIGTERM, SIGHUP), so the event loop
+/// can return and the `TerminalGuard` restore the terminal.
+///
+/// Ctrl-C never gets here: in raw mode it arrives as a key press.
+pub async fn shutdown_signal() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+        match (
+            signal(SignalKind::terminate()),
+            signal(SignalKind::hangup()),
+        ) {
+            (Ok(mut terminate), Ok(mut hangup)) => {
+                tokio::select! {
+                    _ = terminate.recv() => {}
+                    _ = hangup.recv() => {}
+                }
+            }
+            // Without handlers the default action (exit) still applies
+            _ => std::future::pending().await,
+        }
+    }
+    #[cfg(not(unix))]
+    {
+        std::future::pending::<()>().await
+    }
+}
@@ -0,0 +1,256 @@
+use crossterm::event::{KeyCode, KeyModifiers};
+use ratatui::{
+    layout::{Alignment, Constraint, Direction, Layout},
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Cell, Paragraph, Row, Table, TableState},
+    Frame,
+};
+
+use crate::action::{Action, Dispatcher};
+use crate::api::models::{Account, Transaction};
+use crate::error::FinTrakError;
+use crate::money::Money;
+use crate::ui::components::{ErrorPopup, Loading, PopupOutcome};
+use crate::ui::report::{amount_style, signed_span};
+
+/// How many rows PageUp/PageDown move.
+const PAGE: usize = 10;
+
+/// One account's transaction history with the balance after every transaction.
+pub struct AccountDetail {
+    account: Account,
+    rows: Vec<(Transaction, Money)>, // Newest first, with the balance right after it
+    table_state: TableState,
+    email: String,
+    message: String,
+    dispatcher: Dispatcher,
+    loading: Loading,
+    error: Option<ErrorPopup<()>>, // Retrying fetches the history again
+}
+
+impl AccountDetail {
+    pub fn new(dispatcher: Dispatcher, account: Account) -> Self {
+        Self {
+            email: account.email.clone(),
+            account,
+            rows: Vec::new(),
+            table_state: TableState::default(),
+            message: "Loading history...".to_string(),
+            dispatcher,
+            loading: Loading::default(),
+            error: None,
+        }
+    }
+
+    pub fn initialize(&mut self) {
+        self.fetch_history();
+    }
+
+    fn fetch_history(&mut self) {
+        let email = self.email.clone();
+        let handle = self.dispatcher.spawn(move |client| async move {
+            Action::AccountHistory(client.transactions(&email).await)
+        });
+        self.loading.start("Loading history...", handle);
+    }
+
+    /// Advance the loading spinner.
+    pub fn tick(&mut self) {
+        self.loading.tick();
+    }
+
+    pub fn history_loaded(&mut self, result: Result<Vec<Transaction>, FinTrakError>) {
+        self.loading.finish();
+        match result {
+            Ok(transactions) => {
+                let mut history: Vec<Transaction> = transactions
+                    .into_iter()
+                    .filter(|t| t.account_name == self.account.account_name)
+                    .collect();
+                // The balance runs oldest to newest, same-day entries in the order they were made
+                history.sort_by_key(|t| (t.date, t.trans_id));
+                let mut balance = self.account.opening_balance;
+                let mut rows: Vec<(Transaction, Money)> = history
+                    .into_iter()
+                    .map(|t| {
+                        balance += t.signed_amount();
+                        (t, balance)
+                    })
+                    .collect();
+                rows.reverse();
+                self.rows = rows;
+                self.table_state
+                    .select(if self.rows.is_empty() { None } else { Some(0) });
+                self.message = format!("{} transactions", self.rows.len());
+                if self.dispatcher.client().is_offline() {
+                    self.message.push_str(" (offline copy)");
+                }
+            }
+            Err(e) => {
+                self.message = "Failed to fetch the history".to_string();
+                self.error = Some(ErrorPopup::new(e, ()));
+            }
+        }
+    }
+
+    /// Where the account stands after its newest transaction.
+    fn current_balance(&self) -> Money {
+        self.rows
+            .first()
+            .map(|(_, balance)| *balance)
+            .unwrap_or(self.account.opening_balance)
+    }
+
+    fn move_selection(&mut self, delta: isize) {
+        if self.rows.is_empty() {
+            return;
+        }
+        let last = self.rows.len() - 1;
+        let selected = self.table_state.selected().unwrap_or(0);
+        self.table_state
+            .select(Some(selected.saturating_add_signed(delta).min(last)));
+    }
+
+    pub fn render(&mut self, f: &mut Frame) {
+        let background = Block::default().style(Style::default().bg(Color::White));
+        f.render_widget(background, f.area());
+
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .margin(1)
+            .constraints(
+                [
+                    Constraint::Length(2), // Title
+                    Constraint::Length(1), // Balances
+                    Constraint::Min(5),    // History
+                    Constraint::Length(1), // Message
+                    Constraint::Length(2), // Navigation help
+                ]
+                .as_ref(),
+            )
+            .split(f.area());
+
+        let title = Paragraph::new(format!(
+            "ACCOUNT: {} ({})",
+            self.account.account_name, self.account.account_type
+        ))
+        .style(
+            Style::default()
+                .fg(Color::Black)
+                .bg(Color::White)
+                .add_modifier(Modifier::BOLD),
+        )
+        .alignment(Alignment::Center);
+        f.render_widget(title, chunks[0]);
+
+        let current = self.current_balance();
+        let balances = Paragraph::new(Line::from(vec![
+            Span::raw("Opening balance "),
+            Span::styled(
+                self.account.opening_balance.to_string(),
+                amount_style(self.account.opening_balance),
+            ),
+            Span::raw(" | Current balance "),
+            Span::styled(
+                current.to_string(),
+                amount_style(current).add_modifier(Modifier::BOLD),
+            ),
+        ]))
+        .style(Style::default().fg(Color::Black))
+        .alignment(Alignment::Center);
+        f.render_widget(balances, chunks[1]);
+
+        let header = Row::new(["Date", "Category", "Notes", "Amount", "Balance"]).style(
+            Style::default()
+                .fg(Color::Black)
+                .add_modifier(Modifier::BOLD),
+        );
+        let rows = self.rows.iter().map(|(t, balance)| {
+            Row::new(vec![
+                Cell::from(t.date.to_string()),
+                Cell::from(t.category_label().to_string()),
+                Cell::from(t.notes.clone()),
+                Cell::from(Line::from(signed_span(t.signed_amount())).alignment(Alignment::Right)),
+                Cell::from(
+                    Line::styled(balance.to_string(), amount_style(*balance))
+                        .alignment(Alignment::Right),
+                ),
+            ])
+        });
+        let table = Table::new(
+            rows,
+            [
+                Constraint::Length(10),
+                Constraint::Length(16),
+                Constraint::Min(10),
+                Constraint::Length(12),
+                Constraint::Length(12),
+            ],
+        )
+        .header(header)
+        .column_spacing(2)
+        .style(Style::default().fg(Color::Black))
+        .row_highlight_style(
+            Style::default()
+                .add_modifier(Modifier::BOLD)
+                .fg(Color::Yellow),
+        )
+        .block(Block::default().borders(Borders::ALL));
+        f.render_stateful_widget(table, chunks[2], &mut self.table_state);
+
+        let message_style = if self.message.contains("Failed") {
+            Style::default().fg(Color::Red)
+        } else {
+            Style::default().fg(Color::Green)
+        };
+        let message = Paragraph::new(self.message.clone())
+            .style(message_style)
+            .alignment(Alignment::Center);
+        f.render_widget(message, chunks[3]);
+
+        let help = Paragraph::new(self.loading.footer("ESC: Back | ↑↓ PgUp PgDn: Navigate"))
+            .style(Style::default().fg(Color::DarkGray))
+            .alignment(Alignment::Center);
+        f.render_widget(help, chunks[4]);
+
+        if let Some(ref popup) = self.error {
+            popup.render(f);
+        }
+    }
+
+    /// Handle one key press. Returns `true` to go back to the accounts page.
+    pub fn handle_input(&mut self, key: KeyCode, _modifiers: KeyModifiers) -> bool {
+        // While a request runs, Esc cancels it and everything else waits
+        if self.loading.is_loading() {
+            if key == KeyCode::Esc {
+                if let Some(label) = self.loading.cancel() {
+                    self.message = format!("Cancelled: {}", label);
+                }
+            }
+            return false;
+        }
+
+        // An error popup takes every key until it is closed
+        if let Some(popup) = self.error.take() {
+            match popup.handle_input(key) {
+                PopupOutcome::Open(popup) => self.error = Some(popup),
+                PopupOutcome::Dismissed => {}
+                PopupOutcome::Retry(()) => self.fetch_history(),
+            }
+            return false;
+        }
+
+        match key {
+            KeyCode::Esc => return true,
+            KeyCode::Up => self.move_selection(-1),
+            KeyCode::Down => self.move_selection(1),
+            KeyCode::PageUp => self.move_selection(-(PAGE as isize)),
+            KeyCode::PageDown => self.move_selection(PAGE as isize),
+            KeyCode::Home => self.move_selection(isize::MIN),
+            KeyCode::End => self.move_selection(isize::MAX),
+            _ => {}
+        }
+        false
+    }
+}
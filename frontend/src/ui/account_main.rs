@@ -1,55 +1,70 @@
+use std::collections::HashMap;
+
 use ratatui::{
     layout::{Alignment, Constraint, Direction, Layout, Rect},
     style::{Color, Modifier, Style},
+    text::{Line, Span},
     widgets::{Block, Borders, List, ListItem, Paragraph, ListState},
     Frame,
 };
 use crossterm::event::{KeyCode, KeyModifiers};
-use reqwest::Client;
-use serde::{Deserialize, Serialize};
-
-#[allow(dead_code)]
-#[derive(Deserialize, Debug)]
-pub struct Account {
-    pub account_id: i32,
-    pub email: String,
-    pub account_type: String,
-    pub account_name: String,
-}
 
-#[derive(Serialize)]
-pub struct NewAccount {
-    pub email: String,
-    pub account_type: String,
-    pub account_name: String,
+use crate::action::{Action, Dispatcher};
+use crate::api::models::{Account, NewAccount, Transaction};
+use crate::api::Sent;
+use crate::error::FinTrakError;
+use crate::history::{Mutation, UNDO_HINT};
+use crate::money::Money;
+use crate::ui::components::{
+    ConfirmDialog, ConfirmOutcome, ErrorPopup, Field, Form, FormOutcome, Loading, PopupOutcome,
+};
+use crate::ui::report::amount_style;
+
+/// The request behind an error popup, repeated when the user retries.
+enum Retry {
+    Fetch,
+    Submit,
+    Delete(String),
 }
 
 pub struct AccountMain {
     accounts: Vec<Account>,
+    balances: HashMap<String, Money>, // By account name, empty if the ledger failed to load
+    transaction_counts: HashMap<String, usize>, // Same, for the delete confirmation
+    ledger: Vec<Transaction>, // What undoing a deletion files under the account again
     list_state: ListState,
     email: String,
     message: String,
     creating_account: bool,
-    new_account: NewAccount,
-    active_field: usize,
-    client: Client,
+    editing: Option<Account>, // The account the form updates, `None` creates
+    form: Form,
+    dispatcher: Dispatcher,
+    loading: Loading,
+    error: Option<ErrorPopup<Retry>>,
+    confirm: Option<ConfirmDialog<String>>, // The account to delete once confirmed
 }
 
 impl AccountMain {
-    pub fn new(email: String) -> Self {
+    pub fn new(dispatcher: Dispatcher, email: String) -> Self {
         let mut instance = Self {
             accounts: Vec::new(),
+            balances: HashMap::new(),
+            transaction_counts: HashMap::new(),
+            ledger: Vec::new(),
             list_state: ListState::default(),
-            email: email.clone(),
+            email,
             message: String::new(),
             creating_account: false,
-            new_account: NewAccount {
-                email,
-                account_type: String::new(),
-                account_name: String::new(),
-            },
-            active_field: 0,
-            client: Client::new(),
+            editing: None,
+            form: Form::new(vec![
+                Field::text("account_name", "Account Name").required(),
+                Field::text("account_type", "Account Type (credit/debit)").required(),
+                Field::money("opening_balance", "Opening Balance (optional, negative for money owed)"),
+            ]),
+            dispatcher,
+            loading: Loading::default(),
+            error: None,
+            confirm: None,
         };
 
         // Manually call fetch_accounts since we can't use async in new
@@ -93,26 +108,40 @@ impl AccountMain {
             .alignment(Alignment::Center);
         f.render_widget(message, chunks[2]);
 
-        let help_text = if self.creating_account {
+        let help_text = self.loading.footer(if self.editing.is_some() {
+            "ESC: Back | TAB: Next Field | ENTER: Save"
+        } else if self.creating_account {
             "ESC: Back | TAB: Next Field | ENTER: Submit"
         } else {
-            "ESC: Back | N: New Account | D: Delete Account | ↑↓: Navigate"
-        };
+            "ESC: Back | ENTER: Details | N: New Account | E: Edit Account | D: Delete Account | T: Transfer | ↑↓: Navigate"
+        });
         let help = Paragraph::new(help_text)
             .style(Style::default().fg(Color::DarkGray))
             .alignment(Alignment::Center);
         f.render_widget(help, chunks[3]);
+
+        if let Some(ref dialog) = self.confirm {
+            dialog.render(f);
+        }
+        if let Some(ref popup) = self.error {
+            popup.render(f);
+        }
     }
 
     fn render_account_list(&mut self, f: &mut Frame, area: Rect) {
         let items: Vec<ListItem> = self.accounts
             .iter()
             .map(|account| {
-                ListItem::new(format!(
+                let mut spans = vec![Span::raw(format!(
                     "{}: {}",
                     account.account_name,
                     account.account_type
-                ))
+                ))];
+                if let Some(balance) = self.balances.get(&account.account_name) {
+                    spans.push(Span::raw(" | Balance "));
+                    spans.push(Span::styled(balance.to_string(), amount_style(*balance)));
+                }
+                ListItem::new(Line::from(spans))
             })
             .collect();
 
@@ -125,92 +154,108 @@ impl AccountMain {
     }
 
     fn render_create_account(&self, f: &mut Frame, area: Rect) {
-        let create_chunks = Layout::default()
-            .direction(Direction::Vertical)
-            .margin(1)
-            .constraints([
-                Constraint::Length(3),  // Account Name
-                Constraint::Length(3),  // Account Type
-            ].as_ref())
-            .split(area);
-
-        let name_block = Block::default()
-            .title("Account Name")
-            .borders(Borders::ALL)
-            .border_style(Style::default().fg(if self.active_field == 0 { Color::Yellow } else { Color::Black }));
-        let name_text = Paragraph::new(self.new_account.account_name.clone())
-            .style(Style::default().fg(Color::Black));
-        f.render_widget(name_text.block(name_block), create_chunks[0]);
-
-        let type_block = Block::default()
-            .title("Account Type (credit/debit)")
-            .borders(Borders::ALL)
-            .border_style(Style::default().fg(if self.active_field == 1 { Color::Yellow } else { Color::Black }));
-        let type_text = Paragraph::new(self.new_account.account_type.clone())
-            .style(Style::default().fg(Color::Black));
-        f.render_widget(type_text.block(type_block), create_chunks[1]);
+        let form_title = match self.editing {
+            Some(ref account) => format!("Edit account {}", account.account_name),
+            None => "New account".to_string(),
+        };
+        let form = Block::default().title(form_title).borders(Borders::ALL);
+        let inner = form.inner(area);
+        f.render_widget(form.style(Style::default().fg(Color::Black)), area);
+
+        self.form.render(f, inner);
     }
 
-    pub async fn handle_input(&mut self, key: KeyCode, _modifiers: KeyModifiers) -> bool {
+    pub fn handle_input(&mut self, key: KeyCode, modifiers: KeyModifiers) -> bool {
+        // While a request runs, Esc cancels it and everything else waits
+        if self.loading.is_loading() {
+            if key == KeyCode::Esc {
+                if let Some(label) = self.loading.cancel() {
+                    self.message = format!("Cancelled: {}", label);
+                }
+            }
+            return false;
+        }
+
+        // An error popup takes every key until it is closed
+        if let Some(popup) = self.error.take() {
+            match popup.handle_input(key) {
+                PopupOutcome::Open(popup) => self.error = Some(popup),
+                PopupOutcome::Dismissed => {}
+                PopupOutcome::Retry(retry) => self.retry(retry),
+            }
+            return false;
+        }
+
+        // So does the delete confirmation
+        if let Some(dialog) = self.confirm.take() {
+            match dialog.handle_input(key) {
+                ConfirmOutcome::Open(dialog) => self.confirm = Some(dialog),
+                ConfirmOutcome::Cancelled => self.message = "Deletion cancelled".to_string(),
+                ConfirmOutcome::Confirmed(account_name) => self.delete_account(account_name),
+            }
+            return false;
+        }
+
         if key == KeyCode::Esc {
             if self.creating_account {
                 self.creating_account = false;
+                self.editing = None;
                 return false;
             }
             return true;
         }
 
         if self.creating_account {
-            self.handle_create_input(key).await;
+            self.handle_create_input(key, modifiers);
         } else {
-            self.handle_list_input(key).await;
+            self.handle_list_input(key);
         }
         false
     }
 
 
-    async fn handle_create_input(&mut self, key: KeyCode) {
-        match key {
-            KeyCode::Tab => {
-                self.active_field = (self.active_field + 1) % 2;
-            }
-            KeyCode::Enter => {
-                self.submit_new_account().await;
-            }
-            KeyCode::Char(c) => {
-                match self.active_field {
-                    0 => self.new_account.account_name.push(c),
-                    1 => self.new_account.account_type.push(c),
-                    _ => {}
-                }
-            }
-            KeyCode::Backspace => {
-                match self.active_field {
-                    0 => { self.new_account.account_name.pop(); }
-                    1 => { self.new_account.account_type.pop(); }
-                    _ => {}
-                }
-            }
-            _ => {}
+    /// Insert pasted text into the focused field of the form, the list ignores it.
+    pub fn handle_paste(&mut self, text: &str) {
+        let idle = !self.loading.is_loading() && self.error.is_none() && self.confirm.is_none();
+        if idle && self.creating_account {
+            self.form.paste(text);
         }
     }
 
-    async fn handle_list_input(&mut self, key: KeyCode) {
+    fn handle_create_input(&mut self, key: KeyCode, modifiers: KeyModifiers) {
+        if let FormOutcome::Submit = self.form.handle_input(key, modifiers) {
+            self.submit_account();
+        }
+    }
+
+    fn handle_list_input(&mut self, key: KeyCode) {
         match key {
             KeyCode::Char('n') => {
                 self.creating_account = true;
-                self.new_account.account_name.clear();
-                self.new_account.account_type.clear();
-                self.active_field = 0;
+                self.editing = None;
+                self.form.clear();
             }
             KeyCode::Char('d') => {
-                if let Some(selected) = self.list_state.selected() {
-                    if selected < self.accounts.len() {
-                        let account_name = self.accounts[selected].account_name.clone();
-                        self.delete_account(&account_name).await;
-                    }
+                if let Some(account) = self.selected_account() {
+                    self.confirm = Some(self.confirm_delete(account));
                 }
             }
+            KeyCode::Char('e') => {
+                let Some(account) = self.selected_account().cloned() else {
+                    return;
+                };
+                // Created offline, the server has not given it an id to update by yet
+                if account.account_id == 0 {
+                    self.message = "This account is not synced yet, edit it once it is".to_string();
+                    return;
+                }
+                self.form.clear();
+                self.form.set_text("account_name", account.account_name.clone());
+                self.form.set_text("account_type", account.account_type.clone());
+                self.form.set_text("opening_balance", account.opening_balance.to_string());
+                self.editing = Some(account);
+                self.creating_account = true;
+            }
             KeyCode::Up => {
                 let selected = self.list_state.selected().unwrap_or(0);
                 if !self.accounts.is_empty() {
@@ -231,97 +276,232 @@ impl AccountMain {
         }
     }
 
-    pub async fn initialize(&mut self) {
-        self.fetch_accounts().await;
+    /// Ask before deleting `account`, with what it holds.
+    fn confirm_delete(&self, account: &Account) -> ConfirmDialog<String> {
+        let mut lines = vec![format!(
+            "Delete account {} ({})?",
+            account.account_name, account.account_type
+        )];
+        match self.transaction_counts.get(&account.account_name) {
+            Some(0) => lines.push("No transactions are recorded in it.".to_string()),
+            Some(count) => lines.push(format!(
+                "{} transaction(s) are recorded in it, balance {}.",
+                count,
+                self.balances.get(&account.account_name).copied().unwrap_or_default()
+            )),
+            None => lines.push("Its transactions could not be counted.".to_string()),
+        }
+        ConfirmDialog::new("Delete account", lines, account.account_name.clone()).hint(UNDO_HINT)
     }
 
-    async fn fetch_accounts(&mut self) {
-        let url = format!("http://localhost:8000/account_summary?email={}", self.email);
-        match self.client.get(&url).send().await {
-            Ok(response) => {
-                match response.status() {
-                    reqwest::StatusCode::OK => {
-                        if let Ok(accounts) = response.json::<Vec<Account>>().await {
-                            self.accounts = accounts;
-                            if !self.accounts.is_empty() && self.list_state.selected().is_none() {
-                                self.list_state.select(Some(0));
-                            }
-                            self.message = format!("Loaded {} accounts", self.accounts.len());
-                        } else {
-                            self.message = "Failed to parse account data".to_string();
-                        }
+    pub fn initialize(&mut self) {
+        self.fetch_accounts();
+    }
+
+    /// Showing the list with nothing in the way, so a page-level key can act on it.
+    pub fn is_browsing(&self) -> bool {
+        !self.creating_account
+            && !self.loading.is_loading()
+            && self.error.is_none()
+            && self.confirm.is_none()
+    }
+
+    pub fn selected_account(&self) -> Option<&Account> {
+        self.list_state
+            .selected()
+            .and_then(|selected| self.accounts.get(selected))
+    }
+
+    fn fetch_accounts(&mut self) {
+        let email = self.email.clone();
+        let handle = self.dispatcher.spawn(move |client| async move {
+            let (accounts, transactions) =
+                tokio::join!(client.account_summary(&email), client.transactions(&email));
+            Action::Accounts {
+                accounts,
+                transactions,
+            }
+        });
+        self.loading.start("Loading accounts...", handle);
+    }
+
+    /// Advance the loading spinner.
+    pub fn tick(&mut self) {
+        self.loading.tick();
+    }
+
+    fn retry(&mut self, retry: Retry) {
+        match retry {
+            Retry::Fetch => self.fetch_accounts(),
+            Retry::Submit => self.submit_account(),
+            Retry::Delete(account_name) => self.delete_account(account_name),
+        }
+    }
+
+    pub fn accounts_loaded(
+        &mut self,
+        result: Result<Vec<Account>, FinTrakError>,
+        transactions: Result<Vec<Transaction>, FinTrakError>,
+    ) {
+        self.loading.finish();
+        match result {
+            Ok(accounts) => {
+                // Without the ledger the list just leaves the balances out
+                let transactions = transactions.ok();
+                self.balances = accounts
+                    .iter()
+                    .filter_map(|account| {
+                        let balance = account.balance(transactions.as_ref()?);
+                        Some((account.account_name.clone(), balance))
+                    })
+                    .collect();
+                self.transaction_counts.clear();
+                if let Some(ref transactions) = transactions {
+                    for account in &accounts {
+                        self.transaction_counts.insert(account.account_name.clone(), 0);
                     }
-                    _ => {
-                        self.message = "Failed to fetch accounts".to_string();
+                    for transaction in transactions {
+                        if let Some(count) = self.transaction_counts.get_mut(&transaction.account_name) {
+                            *count += 1;
+                        }
                     }
                 }
+                self.ledger = transactions.unwrap_or_default();
+                self.accounts = accounts;
+                if !self.accounts.is_empty() && self.list_state.selected().is_none() {
+                    self.list_state.select(Some(0));
+                }
+                self.message = format!("Loaded {} accounts", self.accounts.len());
+                if self.dispatcher.client().is_offline() {
+                    self.message.push_str(" (offline copy)");
+                }
             }
             Err(e) => {
-                self.message = format!("Error fetching accounts: {}", e);
+                self.message = "Failed to fetch accounts".to_string();
+                self.error = Some(ErrorPopup::new(e, Retry::Fetch));
             }
         }
     }
 
-    async fn submit_new_account(&mut self) {
-        if self.new_account.account_name.is_empty() || self.new_account.account_type.is_empty() {
-            self.message = "Please fill in all fields".to_string();
+    /// Create an account, or update the one being edited.
+    fn submit_account(&mut self) {
+        if let Err(e) = self.form.check() {
+            self.message = e;
             return;
         }
+        let new_account = NewAccount {
+            email: self.email.clone(),
+            account_type: self.form.text("account_type").trim().to_string(),
+            account_name: self.form.text("account_name").trim().to_string(),
+            opening_balance: self.form.money("opening_balance").unwrap_or(Money::ZERO),
+        };
+        match self.editing {
+            Some(ref account) => {
+                let account_id = account.account_id;
+                let mutation = Mutation::UpdateAccount {
+                    account_id,
+                    before: NewAccount {
+                        email: account.email.clone(),
+                        account_type: account.account_type.clone(),
+                        account_name: account.account_name.clone(),
+                        opening_balance: account.opening_balance,
+                    },
+                    after: new_account.clone(),
+                };
+                let handle = self.dispatcher.spawn(move |client| async move {
+                    let result = client.update_account(account_id, &new_account).await;
+                    Action::AccountUpdated { mutation, result }
+                });
+                self.loading.start("Updating account...", handle);
+            }
+            None => {
+                let mutation = Mutation::CreateAccount {
+                    account: new_account.clone(),
+                };
+                let handle = self.dispatcher.spawn(move |client| async move {
+                    let result = client.create_account(&new_account).await;
+                    Action::AccountCreated { mutation, result }
+                });
+                self.loading.start("Creating account...", handle);
+            }
+        }
+    }
 
-        match self.client
-            .post("http://localhost:8000/account_create")
-            .json(&self.new_account)
-            .send()
-            .await
-        {
-            Ok(response) => {
-                let status = response.status();
-                let message = response.text().await.unwrap_or_default();
-
-                match status {
-                    reqwest::StatusCode::CREATED => {
-                        self.message = "Account created successfully".to_string();
-                        self.creating_account = false;
-                        self.fetch_accounts().await;
-                    }
-                    reqwest::StatusCode::BAD_REQUEST => {
-                        self.message = message;
-                    }
-                    _ => {
-                        self.message = format!("Failed to create account: {}", message);
-                    }
-                }
+    /// Handle a create or update answer. A failed update leaves the account as it was
+    /// and the form open.
+    pub fn account_saved(&mut self, result: Result<Sent, FinTrakError>) {
+        self.loading.finish();
+        let verb = if self.editing.is_some() { "updated" } else { "created" };
+        match result {
+            Ok(sent) => {
+                self.message = match sent {
+                    Sent::Delivered => format!("Account {} successfully", verb),
+                    Sent::Queued => "Offline: account saved, it will sync when the server is back".to_string(),
+                };
+                self.creating_account = false;
+                self.editing = None;
+                self.fetch_accounts();
             }
             Err(e) => {
-                self.message = format!("Error creating account: {}", e);
+                self.message = if self.editing.is_some() {
+                    "Failed to update account".to_string()
+                } else {
+                    "Failed to create account".to_string()
+                };
+                self.error = Some(ErrorPopup::new(e, Retry::Submit));
             }
         }
     }
 
-    async fn delete_account(&mut self, account_name: &str) {
-        let url = format!(
-            "http://localhost:8000/delete_account?email={}&account_name={}",
-            self.email, account_name
-        );
-
-        match self.client.delete(&url).send().await {
-            Ok(response) => {
-                let status = response.status();
-                let message = response.text().await.unwrap_or_default();
-
-                match status {
-                    reqwest::StatusCode::OK => {
-                        self.message = "Account deleted successfully".to_string();
-                        self.fetch_accounts().await;
-                    }
-                    _ => {
-                        self.message = format!("Failed to delete account: {}", message);
-                    }
-                }
+    fn delete_account(&mut self, account_name: String) {
+        let email = self.email.clone();
+        let handle = self.dispatcher.spawn(move |client| async move {
+            let result = client.delete_account(&email, &account_name).await;
+            Action::AccountDeleted { account_name, result }
+        });
+        self.loading.start("Deleting account...", handle);
+    }
+
+    /// The deletion to keep for undo, `None` if it failed.
+    pub fn account_deleted(
+        &mut self,
+        account_name: String,
+        result: Result<Sent, FinTrakError>,
+    ) -> Option<Mutation> {
+        self.loading.finish();
+        match result {
+            Ok(sent) => {
+                // The list still has it until the refresh comes back
+                let mutation = self
+                    .accounts
+                    .iter()
+                    .find(|a| a.account_name == account_name)
+                    .map(|a| Mutation::DeleteAccount {
+                        account: NewAccount {
+                            email: self.email.clone(),
+                            account_type: a.account_type.clone(),
+                            account_name: a.account_name.clone(),
+                            opening_balance: a.opening_balance,
+                        },
+                        transactions: self
+                            .ledger
+                            .iter()
+                            .filter(|t| t.account_name == a.account_name)
+                            .cloned()
+                            .collect(),
+                    });
+                self.message = match sent {
+                    Sent::Delivered => "Account deleted successfully".to_string(),
+                    Sent::Queued => "Offline: deletion queued, it will sync when the server is back".to_string(),
+                };
+                self.fetch_accounts();
+                mutation
             }
             Err(e) => {
-                self.message = format!("Error deleting account: {}", e);
+                self.message = "Failed to delete account".to_string();
+                self.error = Some(ErrorPopup::new(e, Retry::Delete(account_name)));
+                None
             }
         }
     }
-}
\ No newline at end of file
+}
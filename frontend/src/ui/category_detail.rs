@@ -0,0 +1,528 @@
+use chrono::{Datelike, Days, Local, Months, NaiveDate};
+use crossterm::event::{KeyCode, KeyModifiers};
+use ratatui::{
+    layout::{Alignment, Constraint, Direction, Layout, Rect},
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Cell, Paragraph, Row, Table, TableState},
+    Frame,
+};
+
+use crate::action::{Action, Dispatcher};
+use crate::api::models::{Category, Transaction};
+use crate::error::FinTrakError;
+use crate::money::Money;
+use crate::ui::components::{ErrorPopup, Loading, PopupOutcome};
+use crate::ui::report::amount_style;
+
+/// How many rows PageUp/PageDown move.
+const PAGE: usize = 10;
+
+/// How long one budget period lasts, from `Category::budget_freq`.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Frequency {
+    Daily,
+    Weekly,
+    Monthly,
+}
+
+impl Frequency {
+    /// Free text on the server, anything unknown is treated as monthly.
+    fn parse(budget_freq: &str) -> Self {
+        match budget_freq.trim().to_lowercase().as_str() {
+            "daily" | "day" => Frequency::Daily,
+            "weekly" | "week" => Frequency::Weekly,
+            _ => Frequency::Monthly,
+        }
+    }
+
+    /// First day of the period `date` falls in. Weeks start on Monday.
+    fn start_of(self, date: NaiveDate) -> NaiveDate {
+        match self {
+            Frequency::Daily => date,
+            Frequency::Weekly => date
+                .checked_sub_days(Days::new(date.weekday().num_days_from_monday() as u64))
+                .unwrap_or(date),
+            Frequency::Monthly => date.with_day(1).unwrap_or(date),
+        }
+    }
+
+    /// `None` at the end of the calendar.
+    fn next_start(self, start: NaiveDate) -> Option<NaiveDate> {
+        match self {
+            Frequency::Daily => start.checked_add_days(Days::new(1)),
+            Frequency::Weekly => start.checked_add_days(Days::new(7)),
+            Frequency::Monthly => start.checked_add_months(Months::new(1)),
+        }
+    }
+
+    fn label(self, start: NaiveDate) -> String {
+        match self {
+            Frequency::Daily => start.format("%a %Y-%m-%d").to_string(),
+            Frequency::Weekly => format!("Week of {}", start),
+            Frequency::Monthly => start.format("%B %Y").to_string(),
+        }
+    }
+}
+
+/// One budget period and what was spent in it.
+struct BudgetPeriod {
+    start: NaiveDate,
+    spent: Money,                   // Expenses minus refunds, income counts against it
+    transactions: Vec<Transaction>, // Newest first
+}
+
+/// Group `history` (newest first) into periods, empty ones included, up to today's.
+///
+/// Transactions dated after the period following today's have no period, a date
+/// typed wrong would otherwise fill the table with empty days up to year 262143.
+fn split_into_periods(
+    frequency: Frequency,
+    history: Vec<Transaction>,
+    today: NaiveDate,
+) -> Vec<BudgetPeriod> {
+    let this_period = frequency.start_of(today);
+    let last_period = frequency.next_start(this_period).unwrap_or(this_period);
+    let newest = history
+        .first()
+        .map_or(today, |t| t.date.max(today))
+        .min(last_period);
+    let oldest = history.last().map_or(today, |t| t.date.min(today));
+
+    let mut periods = Vec::new();
+    let mut start = frequency.start_of(oldest);
+    while start <= newest {
+        periods.push(BudgetPeriod {
+            start,
+            spent: Money::ZERO,
+            transactions: Vec::new(),
+        });
+        let Some(next) = frequency.next_start(start) else {
+            break;
+        };
+        start = next;
+    }
+    periods.reverse();
+
+    for transaction in history {
+        let start = frequency.start_of(transaction.date);
+        if let Some(period) = periods.iter_mut().find(|p| p.start == start) {
+            period.spent = period.spent - transaction.signed_amount();
+            period.transactions.push(transaction);
+        }
+    }
+    periods
+}
+
+/// Every budget period of one category, from its first transaction until today.
+pub struct CategoryDetail {
+    category: Category,
+    frequency: Frequency,
+    periods: Vec<BudgetPeriod>, // Newest first
+    table_state: TableState,
+    email: String,
+    message: String,
+    dispatcher: Dispatcher,
+    loading: Loading,
+    error: Option<ErrorPopup<()>>, // Retrying fetches the history again
+}
+
+impl CategoryDetail {
+    pub fn new(dispatcher: Dispatcher, category: Category) -> Self {
+        Self {
+            email: category.email.clone(),
+            frequency: Frequency::parse(&category.budget_freq),
+            category,
+            periods: Vec::new(),
+            table_state: TableState::default(),
+            message: "Loading history...".to_string(),
+            dispatcher,
+            loading: Loading::default(),
+            error: None,
+        }
+    }
+
+    pub fn initialize(&mut self) {
+        self.fetch_history();
+    }
+
+    fn fetch_history(&mut self) {
+        let email = self.email.clone();
+        let handle = self.dispatcher.spawn(move |client| async move {
+            Action::CategoryHistory(client.transactions(&email).await)
+        });
+        self.loading.start("Loading history...", handle);
+    }
+
+    /// Advance the loading spinner.
+    pub fn tick(&mut self) {
+        self.loading.tick();
+    }
+
+    /// Income categories have nothing to go over, they show what came in instead.
+    fn is_income(&self) -> bool {
+        self.category.category_type.eq_ignore_ascii_case("income")
+    }
+
+    fn is_over(&self, period: &BudgetPeriod) -> bool {
+        !self.is_income() && period.spent > self.category.budget
+    }
+
+    pub fn history_loaded(&mut self, result: Result<Vec<Transaction>, FinTrakError>) {
+        self.loading.finish();
+        match result {
+            Ok(transactions) => {
+                let mut history: Vec<Transaction> = transactions
+                    .into_iter()
+                    .filter(|t| t.category_name == self.category.nickname && !t.is_transfer())
+                    .collect();
+                history.sort_by_key(|t| std::cmp::Reverse((t.date, t.trans_id)));
+                self.periods =
+                    split_into_periods(self.frequency, history, Local::now().date_naive());
+                self.table_state.select(if self.periods.is_empty() {
+                    None
+                } else {
+                    Some(0)
+                });
+                let over = self.periods.iter().filter(|p| self.is_over(p)).count();
+                self.message = if self.is_income() {
+                    format!("{} budget period(s)", self.periods.len())
+                } else {
+                    format!(
+                        "{} budget period(s), {} over budget",
+                        self.periods.len(),
+                        over
+                    )
+                };
+                if self.dispatcher.client().is_offline() {
+                    self.message.push_str(" (offline copy)");
+                }
+            }
+            Err(e) => {
+                self.message = "Failed to fetch the history".to_string();
+                self.error = Some(ErrorPopup::new(e, ()));
+            }
+        }
+    }
+
+    fn move_selection(&mut self, delta: isize) {
+        if self.periods.is_empty() {
+            return;
+        }
+        let last = self.periods.len() - 1;
+        let selected = self.table_state.selected().unwrap_or(0);
+        self.table_state
+            .select(Some(selected.saturating_add_signed(delta).min(last)));
+    }
+
+    pub fn render(&mut self, f: &mut Frame) {
+        let background = Block::default().style(Style::default().bg(Color::White));
+        f.render_widget(background, f.area());
+
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .margin(1)
+            .constraints(
+                [
+                    Constraint::Length(2),      // Title
+                    Constraint::Length(1),      // Budget
+                    Constraint::Percentage(50), // Periods
+                    Constraint::Min(5),         // Transactions of the selected period
+                    Constraint::Length(1),      // Message
+                    Constraint::Length(2),      // Navigation help
+                ]
+                .as_ref(),
+            )
+            .split(f.area());
+
+        let title = Paragraph::new(format!(
+            "CATEGORY: {} ({})",
+            self.category.nickname, self.category.category_type
+        ))
+        .style(
+            Style::default()
+                .fg(Color::Black)
+                .bg(Color::White)
+                .add_modifier(Modifier::BOLD),
+        )
+        .alignment(Alignment::Center);
+        f.render_widget(title, chunks[0]);
+
+        let budget = Paragraph::new(format!(
+            "Budget ${} {}",
+            self.category.budget, self.category.budget_freq
+        ))
+        .style(Style::default().fg(Color::Black))
+        .alignment(Alignment::Center);
+        f.render_widget(budget, chunks[1]);
+
+        self.render_periods(f, chunks[2]);
+        self.render_transactions(f, chunks[3]);
+
+        let message_style = if self.message.contains("Failed") {
+            Style::default().fg(Color::Red)
+        } else {
+            Style::default().fg(Color::Green)
+        };
+        let message = Paragraph::new(self.message.clone())
+            .style(message_style)
+            .alignment(Alignment::Center);
+        f.render_widget(message, chunks[4]);
+
+        let help = Paragraph::new(
+            self.loading
+                .footer("ESC: Back | ↑↓ PgUp PgDn: Switch between Periods"),
+        )
+        .style(Style::default().fg(Color::DarkGray))
+        .alignment(Alignment::Center);
+        f.render_widget(help, chunks[5]);
+
+        if let Some(ref popup) = self.error {
+            popup.render(f);
+        }
+    }
+
+    fn render_periods(&mut self, f: &mut Frame, area: Rect) {
+        let spent_title = if self.is_income() {
+            "Received"
+        } else {
+            "Spent"
+        };
+        let header = Row::new(["Period", spent_title, "Budget", "Status"]).style(
+            Style::default()
+                .fg(Color::Black)
+                .add_modifier(Modifier::BOLD),
+        );
+        let rows: Vec<Row> = self
+            .periods
+            .iter()
+            .map(|period| {
+                let (amount, status) = if self.is_income() {
+                    (-period.spent, Span::raw(""))
+                } else if self.is_over(period) {
+                    let over = period.spent - self.category.budget;
+                    (
+                        period.spent,
+                        Span::styled(format!("Over by {}", over), Style::default().fg(Color::Red)),
+                    )
+                } else {
+                    let left = self.category.budget - period.spent;
+                    (
+                        period.spent,
+                        Span::styled(format!("{} left", left), Style::default().fg(Color::Green)),
+                    )
+                };
+                Row::new(vec![
+                    Cell::from(self.frequency.label(period.start)),
+                    Cell::from(Line::from(amount.to_string()).alignment(Alignment::Right)),
+                    Cell::from(
+                        Line::from(self.category.budget.to_string()).alignment(Alignment::Right),
+                    ),
+                    Cell::from(status),
+                ])
+            })
+            .collect();
+        let table = Table::new(
+            rows,
+            [
+                Constraint::Length(22),
+                Constraint::Length(12),
+                Constraint::Length(12),
+                Constraint::Min(10),
+            ],
+        )
+        .header(header)
+        .column_spacing(2)
+        .style(Style::default().fg(Color::Black))
+        .row_highlight_style(
+            Style::default()
+                .add_modifier(Modifier::BOLD)
+                .fg(Color::Yellow),
+        )
+        .block(
+            Block::default()
+                .title("Budget Periods")
+                .borders(Borders::ALL),
+        );
+        f.render_stateful_widget(table, area, &mut self.table_state);
+    }
+
+    fn render_transactions(&self, f: &mut Frame, area: Rect) {
+        let period = self
+            .table_state
+            .selected()
+            .and_then(|selected| self.periods.get(selected));
+        let lines: Vec<Line> = match period {
+            Some(period) if !period.transactions.is_empty() => period
+                .transactions
+                .iter()
+                .map(|t| {
+                    Line::from(vec![
+                        Span::raw(format!("{} ", t.date)),
+                        Span::styled(
+                            format!("{:>+10}", t.signed_amount()),
+                            amount_style(t.signed_amount()),
+                        ),
+                        Span::raw(format!(" | {} | {}", t.account_name, t.notes)),
+                    ])
+                })
+                .collect(),
+            Some(_) => vec![Line::raw("No transactions in this period")],
+            None => Vec::new(),
+        };
+        let title = match period {
+            Some(period) => format!("Transactions: {}", self.frequency.label(period.start)),
+            None => "Transactions".to_string(),
+        };
+        let list = Paragraph::new(lines)
+            .style(Style::default().fg(Color::Black))
+            .block(Block::default().title(title).borders(Borders::ALL));
+        f.render_widget(list, area);
+    }
+
+    /// Handle one key press. Returns `true` to go back to the categories page.
+    pub fn handle_input(&mut self, key: KeyCode, _modifiers: KeyModifiers) -> bool {
+        // While a request runs, Esc cancels it and everything else waits
+        if self.loading.is_loading() {
+            if key == KeyCode::Esc {
+                if let Some(label) = self.loading.cancel() {
+                    self.message = format!("Cancelled: {}", label);
+                }
+            }
+            return false;
+        }
+
+        // An error popup takes every key until it is closed
+        if let Some(popup) = self.error.take() {
+            match popup.handle_input(key) {
+                PopupOutcome::Open(popup) => self.error = Some(popup),
+                PopupOutcome::Dismissed => {}
+                PopupOutcome::Retry(()) => self.fetch_history(),
+            }
+            return false;
+        }
+
+        match key {
+            KeyCode::Esc => return true,
+            KeyCode::Up => self.move_selection(-1),
+            KeyCode::Down => self.move_selection(1),
+            KeyCode::PageUp => self.move_selection(-(PAGE as isize)),
+            KeyCode::PageDown => self.move_selection(PAGE as isize),
+            KeyCode::Home => self.move_selection(isize::MIN),
+            KeyCode::End => self.move_selection(isize::MAX),
+            _ => {}
+        }
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::models::TransactionKind;
+
+    fn day(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    fn transaction(date: NaiveDate, kind: TransactionKind, cents: i64) -> Transaction {
+        Transaction {
+            trans_id: 1,
+            email: "a@b.c".to_string(),
+            category_name: "Groceries".to_string(),
+            account_name: "Checking".to_string(),
+            amount: Money::from_cents(cents),
+            kind,
+            notes: String::new(),
+            date,
+            transfer_id: None,
+        }
+    }
+
+    fn starts(periods: &[BudgetPeriod]) -> Vec<NaiveDate> {
+        periods.iter().map(|p| p.start).collect()
+    }
+
+    #[test]
+    fn unknown_frequencies_are_monthly() {
+        assert!(Frequency::parse(" Weekly ") == Frequency::Weekly);
+        assert!(Frequency::parse("day") == Frequency::Daily);
+        assert!(Frequency::parse("fortnightly") == Frequency::Monthly);
+    }
+
+    #[test]
+    fn weeks_start_on_monday() {
+        assert_eq!(
+            Frequency::Weekly.start_of(day(2024, 3, 13)),
+            day(2024, 3, 11)
+        );
+        assert_eq!(
+            Frequency::Weekly.start_of(day(2024, 3, 11)),
+            day(2024, 3, 11)
+        );
+        assert_eq!(
+            Frequency::Monthly.start_of(day(2024, 3, 13)),
+            day(2024, 3, 1)
+        );
+        // Before the first Monday of the calendar there is no earlier one to go to
+        assert_eq!(Frequency::Weekly.start_of(NaiveDate::MIN), NaiveDate::MIN);
+    }
+
+    #[test]
+    fn the_calendar_has_an_end() {
+        assert_eq!(Frequency::Daily.next_start(NaiveDate::MAX), None);
+        assert_eq!(Frequency::Weekly.next_start(NaiveDate::MAX), None);
+        assert_eq!(
+            Frequency::Monthly.next_start(Frequency::Monthly.start_of(NaiveDate::MAX)),
+            None
+        );
+    }
+
+    #[test]
+    fn periods_include_empty_ones_and_net_refunds() {
+        let history = vec![
+            transaction(day(2024, 3, 10), TransactionKind::Expense, 1_000),
+            transaction(day(2024, 3, 2), TransactionKind::Refund, 300),
+            transaction(day(2024, 1, 20), TransactionKind::Expense, 500),
+        ];
+        let periods = split_into_periods(Frequency::Monthly, history, day(2024, 3, 15));
+
+        assert_eq!(
+            starts(&periods),
+            [day(2024, 3, 1), day(2024, 2, 1), day(2024, 1, 1)]
+        );
+        assert_eq!(periods[0].spent, Money::from_cents(700));
+        assert_eq!(periods[0].transactions.len(), 2);
+        assert_eq!(periods[1].spent, Money::ZERO);
+        assert_eq!(periods[2].spent, Money::from_cents(500));
+    }
+
+    #[test]
+    fn periods_run_until_today_without_history() {
+        let periods = split_into_periods(Frequency::Weekly, Vec::new(), day(2024, 3, 13));
+        assert_eq!(starts(&periods), [day(2024, 3, 11)]);
+    }
+
+    #[test]
+    fn periods_stop_one_past_today() {
+        let history = vec![
+            transaction(NaiveDate::MAX, TransactionKind::Expense, 100),
+            transaction(day(2024, 3, 18), TransactionKind::Expense, 200),
+            transaction(day(2024, 3, 13), TransactionKind::Expense, 400),
+        ];
+        let periods = split_into_periods(Frequency::Weekly, history, day(2024, 3, 13));
+
+        assert_eq!(starts(&periods), [day(2024, 3, 18), day(2024, 3, 11)]);
+        assert_eq!(periods[0].spent, Money::from_cents(200));
+        assert_eq!(periods[1].spent, Money::from_cents(400));
+    }
+
+    #[test]
+    fn periods_stop_at_the_end_of_the_calendar() {
+        let history = vec![transaction(NaiveDate::MAX, TransactionKind::Expense, 100)];
+        let periods = split_into_periods(Frequency::Daily, history, NaiveDate::MAX);
+
+        assert_eq!(starts(&periods), [NaiveDate::MAX]);
+        assert_eq!(periods[0].spent, Money::from_cents(100));
+    }
+}
@@ -1,3 +1,5 @@
+use std::collections::HashMap;
+
 use crossterm::event::{KeyCode, KeyModifiers};
 use ratatui::{
     layout::{Alignment, Constraint, Direction, Layout, Rect},
@@ -5,52 +7,63 @@ use ratatui::{
     widgets::{Block, Borders, List, ListItem, ListState, Paragraph},
     Frame,
 };
-use reqwest::Client;
-use serde::{Deserialize, Serialize};
-
-#[derive(Deserialize, Debug, Clone)]
-pub struct Category {
-    #[allow(dead_code)]
-    pub email: String,
-    pub nickname: String,
-    pub category_type: String,
-    pub budget: f64,
-    pub budget_freq: String,
-}
 
-#[derive(Serialize, Debug)]
-pub struct NewCategory {
-    pub email: String,
-    pub nickname: String,
-    pub category_type: String,
-    pub budget: f64,
-    pub budget_freq: String,
+use crate::action::{Action, Dispatcher};
+use crate::api::models::{Category, NewCategory, Transaction};
+use crate::api::Sent;
+use crate::error::FinTrakError;
+use crate::history::{Mutation, UNDO_HINT};
+use crate::money::Money;
+use crate::ui::components::{
+    ConfirmDialog, ConfirmOutcome, ErrorPopup, Field, Form, FormOutcome, Loading, PopupOutcome,
+};
+
+/// The request behind an error popup, repeated when the user retries.
+enum Retry {
+    Fetch,
+    Submit,
+    Delete(String),
 }
 
 pub struct CategoryMain {
     categories: Vec<Category>,
+    transaction_counts: HashMap<String, usize>, // By nickname, empty if the ledger failed to load
+    ledger: Vec<Transaction>, // What undoing a deletion files under the category again
     list_state: ListState,
     email: String,
     message: String,
     creating_category: bool,
-    active_field: usize,
-    client: Client,
-    input_strings: [String; 5],
+    editing: Option<Category>, // The category the form updates, `None` creates
+    form: Form,
+    dispatcher: Dispatcher,
     last_operation_nickname: Option<String>,
+    loading: Loading,
+    error: Option<ErrorPopup<Retry>>,
+    confirm: Option<ConfirmDialog<String>>, // The category to delete once confirmed
 }
 
 impl CategoryMain {
-    pub fn new(email: String) -> Self {
+    pub fn new(dispatcher: Dispatcher, email: String) -> Self {
         let mut instance = Self {
             categories: Vec::new(),
+            transaction_counts: HashMap::new(),
+            ledger: Vec::new(),
             list_state: ListState::default(),
-            email: email.clone(),
+            email,
             message: String::new(),
             creating_category: false,
-            active_field: 0,
-            client: Client::new(),
-            input_strings: Default::default(),
+            editing: None,
+            form: Form::new(vec![
+                Field::text("nickname", "Nickname").required(),
+                Field::text("category_type", "Category Type").required(),
+                Field::money("budget", "Budget").required().validate(check_budget),
+                Field::text("budget_freq", "Budget Frequency (daily/weekly/monthly)").required(),
+            ]),
+            dispatcher,
             last_operation_nickname: None,
+            loading: Loading::default(),
+            error: None,
+            confirm: None,
         };
 
         instance.message = "Loading categories...".to_string();
@@ -101,15 +114,24 @@ impl CategoryMain {
             .alignment(Alignment::Center);
         f.render_widget(message, chunks[2]);
 
-        let help_text = if self.creating_category {
+        let help_text = self.loading.footer(if self.editing.is_some() {
+            "ESC: Back | TAB: Next Field | ENTER: Save"
+        } else if self.creating_category {
             "ESC: Back | TAB: Next Field | ENTER: Submit"
         } else {
-            "ESC: Back | N: New Category | D: Delete Category | U: Update Category | ↑↓: Navigate"
-        };
+            "ESC: Back | ENTER: Details | N: New Category | D: Delete Category | E: Edit Category | ↑↓: Navigate"
+        });
         let help = Paragraph::new(help_text)
             .style(Style::default().fg(Color::DarkGray))
             .alignment(Alignment::Center);
         f.render_widget(help, chunks[3]);
+
+        if let Some(ref dialog) = self.confirm {
+            dialog.render(f);
+        }
+        if let Some(ref popup) = self.error {
+            popup.render(f);
+        }
     }
 
     fn render_category_list(&mut self, f: &mut Frame, area: Rect) {
@@ -140,112 +162,102 @@ impl CategoryMain {
     }
 
     fn render_create_category(&self, f: &mut Frame, area: Rect) {
-        let create_chunks = Layout::default()
-            .direction(Direction::Vertical)
-            .margin(1)
-            .constraints(
-                [
-                    Constraint::Length(3), // Nickname
-                    Constraint::Length(3), // Category Type
-                    Constraint::Length(3), // Budget
-                    Constraint::Length(3), // Budget Frequency
-                ]
-                .as_ref(),
-            )
-            .split(area);
-
-        let fields = [
-            ("Nickname", &self.input_strings[0]),
-            ("Category Type", &self.input_strings[1]),
-            ("Budget", &self.input_strings[2]),
-            (
-                "Budget Frequency (daily/weekly/monthly)",
-                &self.input_strings[3],
-            ),
-        ];
-
-        for (i, (title, content)) in fields.iter().enumerate() {
-            let block = Block::default()
-                .title(*title)
-                .borders(Borders::ALL)
-                .border_style(Style::default().fg(if self.active_field == i {
-                    Color::Yellow
-                } else {
-                    Color::Black
-                }));
-            let text = Paragraph::new(content.to_string()).style(Style::default().fg(Color::Black));
-            f.render_widget(text.block(block), create_chunks[i]);
-        }
+        let form_title = match self.editing {
+            Some(ref category) => format!("Edit category {}", category.nickname),
+            None => "New category".to_string(),
+        };
+        let form = Block::default().title(form_title).borders(Borders::ALL);
+        let inner = form.inner(area);
+        f.render_widget(form.style(Style::default().fg(Color::Black)), area);
+
+        self.form.render(f, inner);
     }
 
-    pub async fn handle_input(&mut self, key: KeyCode, _modifiers: KeyModifiers) -> bool {
+    pub fn handle_input(&mut self, key: KeyCode, modifiers: KeyModifiers) -> bool {
+        // While a request runs, Esc cancels it and everything else waits
+        if self.loading.is_loading() {
+            if key == KeyCode::Esc {
+                if let Some(label) = self.loading.cancel() {
+                    self.message = format!("Cancelled: {}", label);
+                }
+            }
+            return false;
+        }
+
+        // An error popup takes every key until it is closed
+        if let Some(popup) = self.error.take() {
+            match popup.handle_input(key) {
+                PopupOutcome::Open(popup) => self.error = Some(popup),
+                PopupOutcome::Dismissed => {}
+                PopupOutcome::Retry(retry) => self.retry(retry),
+            }
+            return false;
+        }
+
+        // So does the delete confirmation
+        if let Some(dialog) = self.confirm.take() {
+            match dialog.handle_input(key) {
+                ConfirmOutcome::Open(dialog) => self.confirm = Some(dialog),
+                ConfirmOutcome::Cancelled => self.message = "Deletion cancelled".to_string(),
+                ConfirmOutcome::Confirmed(nickname) => self.delete_category(nickname),
+            }
+            return false;
+        }
+
         if key == KeyCode::Esc {
             if self.creating_category {
                 self.creating_category = false;
+                self.editing = None;
                 return false;
             }
             return true;
         }
 
         if self.creating_category {
-            self.handle_create_input(key).await;
+            self.handle_create_input(key, modifiers);
         } else {
-            self.handle_list_input(key).await;
+            self.handle_list_input(key);
         }
         false
     }
 
-    async fn handle_create_input(&mut self, key: KeyCode) {
-        match key {
-            KeyCode::Tab => {
-                self.active_field = (self.active_field + 1) % 4; // Only cycle through 4 fields
-            }
-            KeyCode::Enter => {
-                self.submit_new_category().await;
-            }
-            KeyCode::Char(c) => {
-                if self.active_field < 4 {
-                    // Only allow input for first 4 fields
-                    self.input_strings[self.active_field].push(c);
-                }
-            }
-            KeyCode::Backspace => {
-                if self.active_field < 4 {
-                    // Only allow deletion for first 4 fields
-                    self.input_strings[self.active_field].pop();
-                }
-            }
-            _ => {}
+    /// Insert pasted text into the focused field of the form, the list ignores it.
+    pub fn handle_paste(&mut self, text: &str) {
+        let idle = !self.loading.is_loading() && self.error.is_none() && self.confirm.is_none();
+        if idle && self.creating_category {
+            self.form.paste(text);
+        }
+    }
+
+    fn handle_create_input(&mut self, key: KeyCode, modifiers: KeyModifiers) {
+        if let FormOutcome::Submit = self.form.handle_input(key, modifiers) {
+            self.submit_category();
         }
     }
 
-    async fn handle_list_input(&mut self, key: KeyCode) {
+    fn handle_list_input(&mut self, key: KeyCode) {
         match key {
             KeyCode::Char('n') => {
                 self.creating_category = true;
-                self.input_strings = Default::default();
-                self.active_field = 0;
+                self.editing = None;
+                self.form.clear();
             }
             KeyCode::Char('d') => {
-                if let Some(selected) = self.list_state.selected() {
-                    if selected < self.categories.len() {
-                        let nickname = self.categories[selected].nickname.clone();
-                        self.delete_category(&nickname).await;
-                    }
+                if let Some(category) = self.selected_category() {
+                    self.confirm = Some(self.confirm_delete(category));
                 }
             }
-            KeyCode::Char('u') => {
-                // delete first
-                if let Some(selected) = self.list_state.selected() {
-                    if selected < self.categories.len() {
-                        let nickname = self.categories[selected].nickname.clone();
-                        self.delete_category(&nickname).await;
-                    }
+            KeyCode::Char('e') => {
+                // The form starts from the category as it is, nothing changes until it is saved
+                if let Some(category) = self.selected_category().cloned() {
+                    self.editing = Some(category.clone());
+                    self.form.clear();
+                    self.form.set_text("nickname", category.nickname.clone());
+                    self.form.set_text("category_type", category.category_type);
+                    self.form.set_text("budget", category.budget.to_string());
+                    self.form.set_text("budget_freq", category.budget_freq);
+                    self.creating_category = true;
                 }
-                // then create new lol
-                self.creating_category = true;
-                self.input_strings = Default::default();
-                // self.active_field = 0;
             }
             KeyCode::Up => {
                 let selected = self.list_state.selected().unwrap_or(0);
@@ -272,133 +284,247 @@ impl CategoryMain {
         }
     }
 
-    pub async fn initialize(&mut self) {
-        self.fetch_categories().await;
+    /// Ask before deleting `category`, with how much of the ledger it covers.
+    fn confirm_delete(&self, category: &Category) -> ConfirmDialog<String> {
+        let mut lines = vec![format!(
+            "Delete category {} ({}, budget ${} {})?",
+            category.nickname, category.category_type, category.budget, category.budget_freq
+        )];
+        lines.push(match self.transaction_counts.get(&category.nickname) {
+            Some(0) => "No transactions are filed under it.".to_string(),
+            Some(count) => format!("{} transaction(s) are filed under it.", count),
+            None => "Its transactions could not be counted.".to_string(),
+        });
+        ConfirmDialog::new("Delete category", lines, category.nickname.clone()).hint(UNDO_HINT)
     }
 
-    async fn fetch_categories(&mut self) {
-        let url = format!(
-            "http://localhost:8000/category_summary?email={}",
-            self.email
-        );
-        match self.client.get(&url).send().await {
-            Ok(response) => match response.status() {
-                reqwest::StatusCode::OK => {
-                    if let Ok(categories) = response.json::<Vec<Category>>().await {
-                        self.categories = categories;
-
-                        // If we have a last operated category, find and select it
-                        if let Some(ref nickname) = self.last_operation_nickname {
-                            if let Some(index) = self.categories.iter().position(|c| &c.nickname == nickname) {
-                                self.list_state.select(Some(index));
-                            }
-                        } else if !self.categories.is_empty() && self.list_state.selected().is_none() {
-                            self.list_state.select(Some(0));
+    pub fn initialize(&mut self) {
+        self.fetch_categories();
+    }
+
+    /// Showing the list with nothing in the way, so a page-level key can act on it.
+    pub fn is_browsing(&self) -> bool {
+        !self.creating_category
+            && !self.loading.is_loading()
+            && self.error.is_none()
+            && self.confirm.is_none()
+    }
+
+    pub fn selected_category(&self) -> Option<&Category> {
+        self.list_state
+            .selected()
+            .and_then(|selected| self.categories.get(selected))
+    }
+
+    fn fetch_categories(&mut self) {
+        let email = self.email.clone();
+        let handle = self.dispatcher.spawn(move |client| async move {
+            let (categories, transactions) =
+                tokio::join!(client.category_summary(&email), client.transactions(&email));
+            Action::Categories {
+                categories,
+                transactions,
+            }
+        });
+        self.loading.start("Loading categories...", handle);
+    }
+
+    /// Advance the loading spinner.
+    pub fn tick(&mut self) {
+        self.loading.tick();
+    }
+
+    fn retry(&mut self, retry: Retry) {
+        match retry {
+            Retry::Fetch => self.fetch_categories(),
+            Retry::Submit => self.submit_category(),
+            Retry::Delete(nickname) => self.delete_category(nickname),
+        }
+    }
+
+    pub fn categories_loaded(
+        &mut self,
+        result: Result<Vec<Category>, FinTrakError>,
+        transactions: Result<Vec<Transaction>, FinTrakError>,
+    ) {
+        self.loading.finish();
+        match result {
+            Ok(categories) => {
+                // Without the ledger the delete confirmation just cannot count
+                self.transaction_counts.clear();
+                self.ledger.clear();
+                if let Ok(transactions) = transactions {
+                    for category in &categories {
+                        self.transaction_counts.insert(category.nickname.clone(), 0);
+                    }
+                    for transaction in transactions.iter().filter(|t| !t.is_transfer()) {
+                        if let Some(count) = self.transaction_counts.get_mut(&transaction.category_name) {
+                            *count += 1;
                         }
+                    }
+                    self.ledger = transactions;
+                }
+                self.categories = categories;
 
-                        self.message = format!("Loaded {} categories", self.categories.len());
-                        self.last_operation_nickname = None; // Clear the last operation
-                    } else {
-                        self.message = "Failed to parse category data".to_string();
+                // If we have a last operated category, find and select it
+                if let Some(ref nickname) = self.last_operation_nickname {
+                    if let Some(index) = self.categories.iter().position(|c| &c.nickname == nickname) {
+                        self.list_state.select(Some(index));
                     }
+                } else if !self.categories.is_empty() && self.list_state.selected().is_none() {
+                    self.list_state.select(Some(0));
                 }
-                _ => {
-                    self.message = "Failed to fetch categories".to_string();
+
+                self.message = format!("Loaded {} categories", self.categories.len());
+                if self.dispatcher.client().is_offline() {
+                    self.message.push_str(" (offline copy)");
                 }
-            },
+                self.last_operation_nickname = None; // Clear the last operation
+            }
             Err(e) => {
-                self.message = format!("Error fetching categories: {}", e);
+                self.message = "Failed to fetch categories".to_string();
+                self.error = Some(ErrorPopup::new(e, Retry::Fetch));
             }
         }
     }
 
-    async fn submit_new_category(&mut self) {
-        // Only check the first 4 fields that we actually use
-        if self.input_strings[..4].iter().any(|s| s.is_empty()) {
-            self.message = "Please fill in all fields".to_string();
+    /// Create a category, or update the one being edited.
+    fn submit_category(&mut self) {
+        if let Err(e) = self.form.check() {
+            self.message = e;
             return;
         }
 
-        // Parse budget value
-        let budget = match self.input_strings[2].parse::<f64>() {
-            Ok(value) => value,
-            Err(_) => {
-                self.message = "Invalid budget value".to_string();
-                return;
-            }
-        };
-
         // Store the nickname for later selection
-        let nickname = self.input_strings[0].clone();
+        let nickname = self.form.text("nickname").trim().to_string();
         self.last_operation_nickname = Some(nickname.clone());
 
-        // Create new category
         let new_category = NewCategory {
             email: self.email.clone(),
-            nickname: self.input_strings[0].clone(),
-            category_type: self.input_strings[1].clone(),
-            budget,
-            budget_freq: self.input_strings[3].clone(),
+            nickname,
+            category_type: self.form.text("category_type").trim().to_string(),
+            budget: self.form.money("budget").unwrap_or(Money::ZERO),
+            budget_freq: self.form.text("budget_freq").trim().to_string(),
         };
 
-        match self
-            .client
-            .post("http://localhost:8000/category_create")
-            .json(&new_category)
-            .send()
-            .await
-        {
-            Ok(response) => {
-                let status = response.status();
-
-                let message = response.text().await.unwrap_or_default();
-
-                match status {
-                    reqwest::StatusCode::CREATED => {
-                        self.message = "Category created successfully".to_string();
-                        self.creating_category = false;
-                        self.input_strings = Default::default();
-                        self.active_field = 0;
-                        self.fetch_categories().await;
-                    }
-                    reqwest::StatusCode::BAD_REQUEST => {
-                        self.message = message;
-                    }
-                    _ => {
-                        self.message = format!("Failed to create category: {}", message);
-                    }
-                }
+        match self.editing {
+            Some(ref category) => {
+                let old_nickname = category.nickname.clone();
+                let mutation = Mutation::UpdateCategory {
+                    before: NewCategory {
+                        email: category.email.clone(),
+                        nickname: category.nickname.clone(),
+                        category_type: category.category_type.clone(),
+                        budget: category.budget,
+                        budget_freq: category.budget_freq.clone(),
+                    },
+                    after: new_category.clone(),
+                };
+                let handle = self.dispatcher.spawn(move |client| async move {
+                    let result = client.update_category(&old_nickname, &new_category).await;
+                    Action::CategoryUpdated { mutation, result }
+                });
+                self.loading.start("Updating category...", handle);
+            }
+            None => {
+                let mutation = Mutation::CreateCategory {
+                    category: new_category.clone(),
+                };
+                let handle = self.dispatcher.spawn(move |client| async move {
+                    let result = client.create_category(&new_category).await;
+                    Action::CategoryCreated { mutation, result }
+                });
+                self.loading.start("Creating category...", handle);
+            }
+        }
+    }
+
+    /// Handle a create or update answer. A failed update leaves the category as it was
+    /// and the form open.
+    pub fn category_saved(&mut self, result: Result<Sent, FinTrakError>) {
+        self.loading.finish();
+        let verb = if self.editing.is_some() { "updated" } else { "created" };
+        match result {
+            Ok(sent) => {
+                self.message = match sent {
+                    Sent::Delivered => format!("Category {} successfully", verb),
+                    Sent::Queued => "Offline: category saved, it will sync when the server is back".to_string(),
+                };
+                self.creating_category = false;
+                self.editing = None;
+                self.form.clear();
+                self.fetch_categories();
             }
             Err(e) => {
-                self.message = format!("Error creating category: {}", e);
+                self.message = if self.editing.is_some() {
+                    "Failed to update category".to_string()
+                } else {
+                    "Failed to create category".to_string()
+                };
+                self.error = Some(ErrorPopup::new(e, Retry::Submit));
             }
         }
     }
 
-    async fn delete_category(&mut self, nickname: &str) {
-        let url = format!(
-            "http://localhost:8000/delete_category?email={}&category_nickname={}",
-            self.email, nickname
-        );
-
-        match self.client.delete(&url).send().await {
-            Ok(response) => {
-                let status = response.status();
-                let message = response.text().await.unwrap_or_default();
-
-                match status {
-                    reqwest::StatusCode::OK => {
-                        self.message = "Category deleted successfully".to_string();
-                        self.fetch_categories().await;
-                    }
-                    _ => {
-                        self.message = format!("Failed to delete category: {}", message);
-                    }
-                }
+    fn delete_category(&mut self, nickname: String) {
+        let email = self.email.clone();
+        let handle = self.dispatcher.spawn(move |client| async move {
+            let result = client.delete_category(&email, &nickname).await;
+            Action::CategoryDeleted { nickname, result }
+        });
+        self.loading.start("Deleting category...", handle);
+    }
+
+    /// The deletion to keep for undo, `None` if it failed.
+    pub fn category_deleted(
+        &mut self,
+        nickname: String,
+        result: Result<Sent, FinTrakError>,
+    ) -> Option<Mutation> {
+        self.loading.finish();
+        match result {
+            Ok(sent) => {
+                // The list still has it until the refresh comes back
+                let mutation = self
+                    .categories
+                    .iter()
+                    .find(|c| c.nickname == nickname)
+                    .map(|c| Mutation::DeleteCategory {
+                        category: NewCategory {
+                            email: self.email.clone(),
+                            nickname: c.nickname.clone(),
+                            category_type: c.category_type.clone(),
+                            budget: c.budget,
+                            budget_freq: c.budget_freq.clone(),
+                        },
+                        transactions: self
+                            .ledger
+                            .iter()
+                            .filter(|t| !t.is_transfer() && t.category_name == c.nickname)
+                            .cloned()
+                            .collect(),
+                    });
+                self.message = match sent {
+                    Sent::Delivered => "Category deleted successfully".to_string(),
+                    Sent::Queued => "Offline: deletion queued, it will sync when the server is back".to_string(),
+                };
+                self.fetch_categories();
+                mutation
             }
             Err(e) => {
-                self.message = format!("Error deleting category: {}", e);
+                self.message = "Failed to delete category".to_string();
+                self.error = Some(ErrorPopup::new(e, Retry::Delete(nickname)));
+                None
             }
         }
     }
 }
+
+/// What may be spent per period. Zero is fine, income categories have no budget.
+pub fn check_budget(text: &str) -> Result<(), String> {
+    match Money::parse(text) {
+        Ok(budget) if budget.is_negative() => Err("must not be negative".to_string()),
+        Ok(_) => Ok(()),
+        Err(e) => Err(e),
+    }
+}
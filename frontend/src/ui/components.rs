@@ -1,15 +1,25 @@
-use crossterm::event::KeyCode;
+use chrono::{Datelike, Days, Local, Months, NaiveDate};
+use crossterm::event::{KeyCode, KeyModifiers};
 use ratatui::{
-    widgets::{Block, Borders, Paragraph},
-    style::{Color, Style},
-    layout::Rect,
+    widgets::{Block, Borders, Clear, Paragraph, Wrap},
+    style::{Color, Modifier, Style},
+    layout::{Alignment, Constraint, Flex, Layout, Rect},
+    text::{Line, Span},
     Frame,
 };
+use tokio::task::AbortHandle;
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+use crate::error::FinTrakError;
+use crate::money::Money;
 
 pub struct InputField {
     pub label: String,
-    pub content: String,
+    content: String,
+    cursor: usize, // Byte offset into `content`, always between two graphemes
     pub is_password: bool,
+    pub error: Option<String>, // Shown in red on the border until the input is fixed
 }
 
 impl InputField {
@@ -17,57 +27,1252 @@ impl InputField {
         Self {
             label: label.to_string(),
             content: String::new(),
+            cursor: 0,
             is_password,
+            error: None,
         }
     }
 
-    pub fn render(&self, f: &mut Frame, area: Rect, is_active: bool) {
-        // Display asterisks if it's a password field, otherwise show the content
-        let display_content = if self.is_password {
-            let stars = "*".repeat(self.content.len());
-            if is_active {
-                format!("{}|", stars) // Add a cursor at the end of the input
-            } else {
-                stars
+    pub fn content(&self) -> &str {
+        &self.content
+    }
+
+    /// Replace the text, the cursor goes to the end.
+    pub fn set_content(&mut self, content: impl Into<String>) {
+        self.content = content.into();
+        self.cursor = self.content.len();
+    }
+
+    pub fn clear(&mut self) {
+        self.content.clear();
+        self.cursor = 0;
+    }
+
+    /// Insert pasted text at the cursor. It is one line, so line breaks and tabs
+    /// become spaces and other control characters are dropped.
+    pub fn paste(&mut self, text: &str) {
+        let text: String = text
+            .trim_end_matches(['\r', '\n'])
+            .chars()
+            .filter_map(|c| match c {
+                '\n' | '\t' => Some(' '),
+                c if c.is_control() => None,
+                c => Some(c),
+            })
+            .collect();
+        self.content.insert_str(self.cursor, &text);
+        self.cursor += text.len();
+    }
+
+    /// Start of the grapheme before the cursor.
+    fn previous_boundary(&self) -> usize {
+        self.content[..self.cursor]
+            .grapheme_indices(true)
+            .next_back()
+            .map_or(0, |(i, _)| i)
+    }
+
+    /// End of the grapheme after the cursor.
+    fn next_boundary(&self) -> usize {
+        self.content[self.cursor..]
+            .graphemes(true)
+            .next()
+            .map_or(self.cursor, |g| self.cursor + g.len())
+    }
+
+    /// Start of the word before the cursor, skipping the spaces in between.
+    fn previous_word(&self) -> usize {
+        let before = self.content[..self.cursor].trim_end();
+        before
+            .char_indices()
+            .rev()
+            .find(|(_, c)| c.is_whitespace())
+            .map_or(0, |(i, c)| i + c.len_utf8())
+    }
+
+    /// Edit the text or move the cursor, returning whether the key was used.
+    pub fn handle_input(&mut self, key: KeyCode, modifiers: KeyModifiers) -> bool {
+        // AltGr arrives as Ctrl+Alt on some terminals and still types a character
+        let control = modifiers.contains(KeyModifiers::CONTROL) && !modifiers.contains(KeyModifiers::ALT);
+        match key {
+            KeyCode::Char('w') if control => {
+                let start = self.previous_word();
+                self.content.replace_range(start..self.cursor, "");
+                self.cursor = start;
             }
-        } else {
-            if is_active {
-                format!("{}|", self.content) // Add a cursor at the end of the input
-            } else {
-                self.content.clone()
+            KeyCode::Char('u') if control => {
+                self.content.replace_range(..self.cursor, "");
+                self.cursor = 0;
             }
-        };
+            KeyCode::Char(_) if control => return false,
+            KeyCode::Char(c) => {
+                self.content.insert(self.cursor, c);
+                self.cursor += c.len_utf8();
+            }
+            KeyCode::Backspace => {
+                let start = self.previous_boundary();
+                self.content.replace_range(start..self.cursor, "");
+                self.cursor = start;
+            }
+            KeyCode::Delete => {
+                let end = self.next_boundary();
+                self.content.replace_range(self.cursor..end, "");
+            }
+            KeyCode::Left => self.cursor = self.previous_boundary(),
+            KeyCode::Right => self.cursor = self.next_boundary(),
+            KeyCode::Home => self.cursor = 0,
+            KeyCode::End => self.cursor = self.content.len(),
+            _ => return false,
+        }
+        true
+    }
 
-        // Highlight the border in gold if the field is active
-        let border_style = if is_active {
+    /// The part of the text that fits in `width` columns and the cursor's column in it.
+    fn visible(&self, width: usize) -> (String, usize) {
+        // Each grapheme with the columns it takes, a password shows one star for each
+        let graphemes: Vec<(&str, usize)> = self
+            .content
+            .graphemes(true)
+            .map(|g| if self.is_password { ("*", 1) } else { (g, g.width()) })
+            .collect();
+        let cursor_column: usize = self.content[..self.cursor]
+            .graphemes(true)
+            .map(|g| if self.is_password { 1 } else { g.width() })
+            .sum();
+
+        // Scroll just far enough to keep the cursor in view, with a column for it to sit in
+        let offset = (cursor_column + 1).saturating_sub(width);
+        let mut column = 0;
+        let mut display_content = String::new();
+        for (grapheme, grapheme_width) in graphemes {
+            if column >= offset && column + grapheme_width <= offset + width {
+                display_content.push_str(grapheme);
+            }
+            column += grapheme_width;
+        }
+        (display_content, cursor_column - offset)
+    }
+
+    pub fn render(&self, f: &mut Frame, area: Rect, is_active: bool) {
+        let width = area.width.saturating_sub(2) as usize;
+        let (display_content, cursor_column) = self.visible(width);
+
+        // Highlight the border in gold if the field is active, red while it is invalid
+        let border_style = if self.error.is_some() {
+            Style::default().fg(Color::Red) // Red border for invalid input
+        } else if is_active {
             Style::default().fg(Color::Yellow) // Gold border for active input
         } else {
             Style::default().fg(Color::Black) // Black border for inactive input
         };
+        let title = match self.error {
+            Some(ref error) => format!("{}: {}", self.label, error),
+            None => self.label.clone(),
+        };
 
         // Render the input field
         let paragraph = Paragraph::new(display_content)
             .style(Style::default().fg(Color::Black).bg(Color::White)) // Set text to black on white background
             .block(
                 Block::default()
-                    .title(self.label.as_str())
+                    .title(title)
                     .borders(Borders::ALL)
                     .style(Style::default().bg(Color::White).fg(Color::Black)) // White background, black text
                     .border_style(border_style), // Border changes color based on active status
             );
 
         f.render_widget(paragraph, area);
+
+        // The terminal's own cursor, shown only while a field has the focus
+        if is_active && width > 0 {
+            let x = area.x + 1 + cursor_column as u16;
+            f.set_cursor_position((x, area.y + 1));
+        }
+    }
+
+    /// Just the text on one line, without the box, for inputs inside a status line.
+    pub fn render_line(&self, f: &mut Frame, area: Rect, style: Style, is_active: bool) {
+        let (display_content, cursor_column) = self.visible(area.width as usize);
+        f.render_widget(Paragraph::new(display_content).style(style), area);
+        if is_active && area.width > 0 {
+            f.set_cursor_position((area.x + cursor_column as u16, area.y));
+        }
+    }
+}
+
+const SPINNER_FRAMES: [&str; 10] = ["⠋", "⠙", "⠹", "⠸", "⠼", "⠴", "⠦", "⠧", "⠇", "⠏"];
+
+/// The request a page is waiting on: what it is, how to abort it and a spinner frame.
+#[derive(Default)]
+pub struct Loading {
+    task: Option<(String, AbortHandle)>,
+    frame: usize,
+}
+
+impl Loading {
+    pub fn start(&mut self, label: &str, handle: AbortHandle) {
+        self.task = Some((label.to_string(), handle));
+        self.frame = 0;
+    }
+
+    pub fn finish(&mut self) {
+        self.task = None;
+    }
+
+    /// Abort the in-flight request, returning its label if there was one.
+    pub fn cancel(&mut self) -> Option<String> {
+        self.task.take().map(|(label, handle)| {
+            handle.abort();
+            label
+        })
+    }
+
+    pub fn is_loading(&self) -> bool {
+        self.task.is_some()
+    }
+
+    /// Advance the spinner, called on every tick of the event loop.
+    pub fn tick(&mut self) {
+        if self.is_loading() {
+            self.frame = (self.frame + 1) % SPINNER_FRAMES.len();
+        }
+    }
+
+    /// Footer text: the spinner and what is loading, or `idle_help` when nothing is.
+    pub fn footer(&self, idle_help: &str) -> String {
+        match self.task {
+            Some((ref label, _)) => {
+                format!("{} {} | ESC: Cancel", SPINNER_FRAMES[self.frame], label)
+            }
+            None => idle_help.to_string(),
+        }
+    }
+}
+
+/// A failed request shown on top of the page.
+///
+/// `R` says how to repeat the request, it is only kept when retrying can help
+/// (see `FinTrakError::is_retryable`).
+pub struct ErrorPopup<R> {
+    error: FinTrakError,
+    retry: Option<R>,
+}
+
+/// What a key press did to an `ErrorPopup`.
+pub enum PopupOutcome<R> {
+    /// Still showing, put it back.
+    Open(ErrorPopup<R>),
+    Dismissed,
+    Retry(R),
+}
+
+impl<R> ErrorPopup<R> {
+    pub fn new(error: FinTrakError, retry: R) -> Self {
+        let retry = if error.is_retryable() { Some(retry) } else { None };
+        Self { error, retry }
+    }
+
+    /// R retries when offered, Esc or Enter closes, every other key is swallowed.
+    pub fn handle_input(self, key: KeyCode) -> PopupOutcome<R> {
+        match (key, self.retry) {
+            (KeyCode::Char('r') | KeyCode::Char('R'), Some(retry)) => PopupOutcome::Retry(retry),
+            (KeyCode::Esc | KeyCode::Enter, _) => PopupOutcome::Dismissed,
+            (_, retry) => PopupOutcome::Open(Self {
+                error: self.error,
+                retry,
+            }),
+        }
+    }
+
+    pub fn render(&self, f: &mut Frame) {
+        let help = if self.retry.is_some() {
+            "R: Retry | Esc: Dismiss"
+        } else {
+            "Esc: Dismiss"
+        };
+        let lines = vec![
+            Line::raw(self.error.to_string()),
+            Line::raw(""),
+            Line::raw(help).style(Style::default().fg(Color::DarkGray)),
+        ];
+
+        // Centered box, a bit taller than the message needs
+        let [area] = Layout::horizontal([Constraint::Percentage(60)])
+            .flex(Flex::Center)
+            .areas(f.area());
+        let [area] = Layout::vertical([Constraint::Length(9)])
+            .flex(Flex::Center)
+            .areas(area);
+
+        let popup = Paragraph::new(lines)
+            .style(Style::default().fg(Color::Black).bg(Color::White))
+            .wrap(Wrap { trim: true })
+            .block(
+                Block::default()
+                    .title(self.error.title())
+                    .title_style(Style::default().fg(Color::Red).add_modifier(Modifier::BOLD))
+                    .borders(Borders::ALL)
+                    .border_style(Style::default().fg(Color::Red)),
+            );
+        f.render_widget(Clear, area);
+        f.render_widget(popup, area);
+    }
+}
+
+/// Asks before something is deleted, shown on top of the page.
+///
+/// `A` is the deletion to run once the user agrees. Only `y` agrees, so a stray
+/// Enter or a repeated `d` cannot delete anything.
+pub struct ConfirmDialog<A> {
+    title: String,
+    lines: Vec<String>,
+    hint: Option<String>,
+    action: A,
+}
+
+/// What a key press did to a `ConfirmDialog`.
+pub enum ConfirmOutcome<A> {
+    /// Still asking, put it back.
+    Open(ConfirmDialog<A>),
+    Cancelled,
+    Confirmed(A),
+}
+
+impl<A> ConfirmDialog<A> {
+    /// `lines` say what goes and what else it takes with it.
+    pub fn new(title: impl Into<String>, lines: Vec<String>, action: A) -> Self {
+        Self {
+            title: title.into(),
+            lines,
+            hint: None,
+            action,
+        }
+    }
+
+    /// A note under the message, like how to take the deletion back.
+    pub fn hint(mut self, hint: impl Into<String>) -> Self {
+        self.hint = Some(hint.into());
+        self
+    }
+
+    /// Y confirms, N or Esc cancels, every other key is swallowed.
+    pub fn handle_input(self, key: KeyCode) -> ConfirmOutcome<A> {
+        match key {
+            KeyCode::Char('y') | KeyCode::Char('Y') => ConfirmOutcome::Confirmed(self.action),
+            KeyCode::Char('n') | KeyCode::Char('N') | KeyCode::Esc => ConfirmOutcome::Cancelled,
+            _ => ConfirmOutcome::Open(self),
+        }
+    }
+
+    pub fn render(&self, f: &mut Frame) {
+        let mut lines: Vec<Line> = self.lines.iter().map(|line| Line::raw(line.as_str())).collect();
+        if let Some(ref hint) = self.hint {
+            lines.push(Line::raw(""));
+            lines.push(Line::raw(hint.as_str()));
+        }
+        lines.push(Line::raw(""));
+        lines.push(Line::raw("Y: Delete | N/Esc: Cancel").style(Style::default().fg(Color::DarkGray)));
+
+        // Centered box, as tall as the message plus the borders
+        let [area] = Layout::horizontal([Constraint::Percentage(60)])
+            .flex(Flex::Center)
+            .areas(f.area());
+        let [area] = Layout::vertical([Constraint::Length(lines.len() as u16 + 4)])
+            .flex(Flex::Center)
+            .areas(area);
+
+        let dialog = Paragraph::new(lines)
+            .style(Style::default().fg(Color::Black).bg(Color::White))
+            .alignment(Alignment::Center)
+            .wrap(Wrap { trim: true })
+            .block(
+                Block::default()
+                    .title(self.title.as_str())
+                    .title_style(Style::default().fg(Color::Red).add_modifier(Modifier::BOLD))
+                    .borders(Borders::ALL)
+                    .border_style(Style::default().fg(Color::Red)),
+            );
+        f.render_widget(Clear, area);
+        f.render_widget(dialog, area);
+    }
+}
+
+/// Ticks a toast stays up (6 seconds).
+const TOAST_TICKS: u32 = 60;
+
+/// A short note in the bottom right corner that goes away on its own.
+///
+/// `key` is a plain key that acts on the note while it is shown, like `u` to undo
+/// what it reports.
+pub struct Toast {
+    message: String,
+    pub key: Option<char>,
+    is_error: bool,
+    ticks_left: u32,
+}
+
+impl Toast {
+    pub fn new(message: String, key: Option<char>) -> Self {
+        Self {
+            message,
+            key,
+            is_error: false,
+            ticks_left: TOAST_TICKS,
+        }
+    }
+
+    pub fn error(message: String) -> Self {
+        Self {
+            is_error: true,
+            ..Self::new(message, None)
+        }
+    }
+
+    /// Count down, returns `false` once it is time to go.
+    pub fn tick(&mut self) -> bool {
+        self.ticks_left = self.ticks_left.saturating_sub(1);
+        self.ticks_left > 0
+    }
+
+    pub fn render(&self, f: &mut Frame) {
+        let color = if self.is_error { Color::Red } else { Color::Blue };
+        let width = (self.message.chars().count() as u16 + 4).min(f.area().width);
+        let [area] = Layout::horizontal([Constraint::Length(width)])
+            .flex(Flex::End)
+            .areas(f.area());
+        let [area] = Layout::vertical([Constraint::Length(3)])
+            .flex(Flex::End)
+            .areas(area);
+
+        let toast = Paragraph::new(self.message.as_str())
+            .style(Style::default().fg(Color::Black).bg(Color::White))
+            .alignment(Alignment::Center)
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .border_style(Style::default().fg(color)),
+            );
+        f.render_widget(Clear, area);
+        f.render_widget(toast, area);
+    }
+}
+
+/// A date input with a keyboard-driven calendar popup.
+///
+/// On the field itself `t` picks today, `y` yesterday and Space opens the calendar.
+/// In the calendar the arrows move by a day or a week, PgUp/PgDn by a month,
+/// Enter keeps the highlighted day and Esc closes it unchanged.
+pub struct DateField {
+    pub label: String,
+    pub date: NaiveDate,
+    calendar: Option<NaiveDate>, // Highlighted day while the calendar is open
+}
+
+impl DateField {
+    /// Starts on today.
+    pub fn new(label: &str) -> Self {
+        Self {
+            label: label.to_string(),
+            date: Local::now().date_naive(),
+            calendar: None,
+        }
+    }
+
+    /// The calendar is open and wants every key, Enter and Esc included.
+    pub fn is_open(&self) -> bool {
+        self.calendar.is_some()
     }
 
     pub fn handle_input(&mut self, key: KeyCode) {
+        let today = Local::now().date_naive();
+        let Some(cursor) = self.calendar else {
+            match key {
+                KeyCode::Char(' ') => self.calendar = Some(self.date),
+                KeyCode::Char('t') => self.date = today,
+                KeyCode::Char('y') => self.date = today - Days::new(1),
+                _ => {}
+            }
+            return;
+        };
+
+        // Out of chrono's range the cursor just stays put
+        let moved = match key {
+            KeyCode::Left => cursor.checked_sub_days(Days::new(1)),
+            KeyCode::Right => cursor.checked_add_days(Days::new(1)),
+            KeyCode::Up => cursor.checked_sub_days(Days::new(7)),
+            KeyCode::Down => cursor.checked_add_days(Days::new(7)),
+            KeyCode::PageUp => cursor.checked_sub_months(Months::new(1)),
+            KeyCode::PageDown => cursor.checked_add_months(Months::new(1)),
+            KeyCode::Char('t') => Some(today),
+            KeyCode::Char('y') => Some(today - Days::new(1)),
+            KeyCode::Enter => {
+                self.date = cursor;
+                self.calendar = None;
+                return;
+            }
+            KeyCode::Esc => {
+                self.calendar = None;
+                return;
+            }
+            _ => None,
+        };
+        self.calendar = Some(moved.unwrap_or(cursor));
+    }
+
+    pub fn render(&self, f: &mut Frame, area: Rect, is_active: bool) {
+        let display_content = if is_active {
+            format!("{} ({})  Space: calendar | t: today | y: yesterday", self.date, self.date.weekday())
+        } else {
+            format!("{} ({})", self.date, self.date.weekday())
+        };
+
+        let border_style = if is_active {
+            Style::default().fg(Color::Yellow)
+        } else {
+            Style::default().fg(Color::Black)
+        };
+
+        let paragraph = Paragraph::new(display_content)
+            .style(Style::default().fg(Color::Black).bg(Color::White))
+            .block(
+                Block::default()
+                    .title(self.label.as_str())
+                    .borders(Borders::ALL)
+                    .style(Style::default().bg(Color::White).fg(Color::Black))
+                    .border_style(border_style),
+            );
+
+        f.render_widget(paragraph, area);
+    }
+
+    /// Draw the calendar on top of the page, call it after everything else.
+    pub fn render_calendar(&self, f: &mut Frame) {
+        let Some(cursor) = self.calendar else {
+            return;
+        };
+        let today = Local::now().date_naive();
+        let first = cursor.with_day(1).unwrap_or(cursor);
+
+        let mut lines = vec![Line::raw("Mo Tu We Th Fr Sa Su ").style(Style::default().fg(Color::DarkGray))];
+        // Blank cells up to the weekday the month starts on, then one cell per day
+        let mut week: Vec<Span> = (0..first.weekday().num_days_from_monday())
+            .map(|_| Span::raw("   "))
+            .collect();
+        for day in first.iter_days().take_while(|day| day.month() == first.month()) {
+            let mut style = Style::default();
+            if day == today {
+                style = style.add_modifier(Modifier::UNDERLINED);
+            }
+            if day == cursor {
+                style = style.fg(Color::Black).bg(Color::Yellow).add_modifier(Modifier::BOLD);
+            }
+            week.push(Span::styled(format!("{:>2}", day.day()), style));
+            week.push(Span::raw(" "));
+            if day.weekday().num_days_from_monday() == 6 {
+                lines.push(Line::from(std::mem::take(&mut week)));
+            }
+        }
+        if !week.is_empty() {
+            // Pad the last week so centering keeps the columns aligned,
+            // every day above is two spans (number, gap)
+            for _ in week.len() / 2..7 {
+                week.push(Span::raw("   "));
+            }
+            lines.push(Line::from(week));
+        }
+        lines.push(Line::raw(""));
+        lines.push(Line::raw("←→↑↓ PgUp/PgDn | Enter | Esc").style(Style::default().fg(Color::DarkGray)));
+
+        // Seven 3-wide columns plus borders, and up to six weeks
+        let [area] = Layout::horizontal([Constraint::Length(32)])
+            .flex(Flex::Center)
+            .areas(f.area());
+        let [area] = Layout::vertical([Constraint::Length(lines.len() as u16 + 2)])
+            .flex(Flex::Center)
+            .areas(area);
+
+        let calendar = Paragraph::new(lines)
+            .style(Style::default().fg(Color::Black).bg(Color::White))
+            .alignment(Alignment::Center)
+            .block(
+                Block::default()
+                    .title(cursor.format(" %B %Y ").to_string())
+                    .title_alignment(Alignment::Center)
+                    .borders(Borders::ALL)
+                    .border_style(Style::default().fg(Color::Yellow)),
+            );
+        f.render_widget(Clear, area);
+        f.render_widget(calendar, area);
+    }
+}
+
+/// Most suggestions a `Picker` lists under its field.
+const PICKER_ROWS: usize = 5;
+
+/// What a key press did to a `Picker`.
+pub enum PickerOutcome {
+    Handled,
+    /// Not for the picker, e.g. Enter on a name that is already complete.
+    Unhandled,
+    /// The user chose to create the typed name.
+    Create(String),
+}
+
+/// A text field that completes to one of a known set of names.
+///
+/// Typing filters the names with a fuzzy match, Up/Down highlight a suggestion and
+/// Enter takes it. When the text matches no name exactly the last entry offers to
+/// create it, unless built with `without_create`. Until `set_options` is called any
+/// text is accepted.
+pub struct Picker {
+    pub input: InputField,
+    options: Option<Vec<String>>,
+    highlighted: usize,
+    creatable: bool,
+}
+
+impl Picker {
+    pub fn new(label: &str) -> Self {
+        Self {
+            input: InputField::new(label, false),
+            options: None,
+            highlighted: 0,
+            creatable: true,
+        }
+    }
+
+    /// A picker limited to the known names, it never offers to create one.
+    pub fn without_create(label: &str) -> Self {
+        Self {
+            creatable: false,
+            ..Self::new(label)
+        }
+    }
+
+    pub fn set_options(&mut self, mut options: Vec<String>) {
+        options.sort_by_key(|option| option.to_lowercase());
+        options.dedup();
+        self.options = Some(options);
+        self.highlighted = 0;
+    }
+
+    pub fn add_option(&mut self, option: String) {
+        let mut options = self.options.take().unwrap_or_default();
+        options.push(option);
+        self.set_options(options);
+    }
+
+    /// Whether names are checked at all, they are not until the options are loaded.
+    pub fn is_checked(&self) -> bool {
+        self.options.is_some()
+    }
+
+    /// The known name the text stands for, ignoring case and surrounding spaces.
+    pub fn known(&self) -> Option<&str> {
+        let text = self.input.content().trim();
+        self.options
+            .as_ref()?
+            .iter()
+            .find(|option| option.eq_ignore_ascii_case(text))
+            .map(String::as_str)
+    }
+
+    /// Known names matching the text, best first.
+    pub fn matches(&self) -> Vec<&str> {
+        let Some(ref options) = self.options else {
+            return Vec::new();
+        };
+        let query = self.input.content().trim();
+        let mut scored: Vec<(i32, &str)> = options
+            .iter()
+            .filter_map(|option| fuzzy_score(query, option).map(|score| (score, option.as_str())))
+            .collect();
+        // Stable sort, equal scores stay in alphabetical order
+        scored.sort_by_key(|(score, _)| std::cmp::Reverse(*score));
+        scored.into_iter().take(PICKER_ROWS).map(|(_, option)| option).collect()
+    }
+
+    /// Suggestions plus the create entry, `None` stands for the create entry.
+    fn entries(&self) -> Vec<Option<&str>> {
+        let mut entries: Vec<Option<&str>> = self.matches().into_iter().map(Some).collect();
+        if self.creatable
+            && self.is_checked()
+            && !self.input.content().trim().is_empty()
+            && self.known().is_none()
+        {
+            entries.push(None);
+        }
+        entries
+    }
+
+    pub fn handle_input(&mut self, key: KeyCode, modifiers: KeyModifiers) -> PickerOutcome {
+        let count = self.entries().len();
         match key {
-            KeyCode::Backspace => {
-                self.content.pop();
+            KeyCode::Up if count > 0 => {
+                self.highlighted = (self.highlighted + count - 1) % count;
             }
-            KeyCode::Char(c) => {
-                self.content.push(c);
+            KeyCode::Down if count > 0 => {
+                self.highlighted = (self.highlighted + 1) % count;
             }
+            // A complete name leaves Enter to the form
+            KeyCode::Enter if count == 0 || self.known().is_some() => {
+                return PickerOutcome::Unhandled
+            }
+            KeyCode::Enter => {
+                let entry = self.entries()[self.highlighted.min(count - 1)].map(str::to_string);
+                self.highlighted = 0;
+                match entry {
+                    Some(option) => self.input.set_content(option),
+                    None => return PickerOutcome::Create(self.input.content().trim().to_string()),
+                }
+            }
+            _ if self.input.handle_input(key, modifiers) => self.highlighted = 0,
+            _ => return PickerOutcome::Unhandled,
+        }
+        PickerOutcome::Handled
+    }
+
+    pub fn paste(&mut self, text: &str) {
+        self.input.paste(text);
+        self.highlighted = 0;
+    }
+
+    pub fn render(&self, f: &mut Frame, area: Rect, is_active: bool) {
+        self.input.render(f, area, is_active);
+    }
+
+    /// Draw the suggestions under the field at `area`, call it after the fields below.
+    pub fn render_dropdown(&self, f: &mut Frame, area: Rect) {
+        // Nothing to suggest once the name is complete
+        if self.known().is_some() {
+            return;
+        }
+        let entries = self.entries();
+        if entries.is_empty() {
+            return;
+        }
+        let lines: Vec<Line> = entries
+            .iter()
+            .enumerate()
+            .map(|(i, entry)| {
+                let text = match entry {
+                    Some(option) => option.to_string(),
+                    None => format!("+ Create \"{}\"", self.input.content().trim()),
+                };
+                let style = match (i == self.highlighted, entry) {
+                    (true, _) => Style::default().fg(Color::Black).bg(Color::Yellow).add_modifier(Modifier::BOLD),
+                    (false, None) => Style::default().fg(Color::Blue),
+                    (false, Some(_)) => Style::default().fg(Color::Black),
+                };
+                Line::styled(text, style)
+            })
+            .collect();
+
+        // Right under the field, clipped to the screen
+        let screen = f.area();
+        let top = (area.y + area.height).min(screen.height);
+        let dropdown = Rect {
+            x: area.x + 1,
+            y: top,
+            width: area.width.saturating_sub(2).min(40),
+            height: (lines.len() as u16 + 2).min(screen.height - top),
+        };
+        let list = Paragraph::new(lines)
+            .style(Style::default().bg(Color::White))
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .border_style(Style::default().fg(Color::Yellow)),
+            );
+        f.render_widget(Clear, dropdown);
+        f.render_widget(list, dropdown);
+    }
+}
+
+/// What is wrong with a field's trimmed text, if anything. Runs after the built-in
+/// checks, so a money validator can count on the text parsing.
+pub type Validator = fn(&str) -> Result<(), String>;
+
+/// The widget behind a form field.
+enum Input {
+    Text(InputField), // Passwords too
+    Money(InputField),
+    Date(DateField),
+    Choice {
+        options: Vec<(String, Color)>, // Label and highlight colour
+        selected: usize,
+    },
+    Picker(Picker),
+    Checkbox(bool),
+}
+
+/// One field of a `Form`, looked up by its `key`.
+pub struct Field {
+    key: &'static str,
+    label: String,
+    input: Input,
+    required: bool,
+    validator: Option<Validator>,
+}
+
+impl Field {
+    fn new(key: &'static str, label: &str, input: Input) -> Self {
+        Self {
+            key,
+            label: label.to_string(),
+            input,
+            required: false,
+            validator: None,
+        }
+    }
+
+    pub fn text(key: &'static str, label: &str) -> Self {
+        Self::new(key, label, Input::Text(InputField::new(label, false)))
+    }
+
+    pub fn password(key: &'static str, label: &str) -> Self {
+        Self::new(key, label, Input::Text(InputField::new(label, true)))
+    }
+
+    /// Text that has to parse as `Money`.
+    pub fn money(key: &'static str, label: &str) -> Self {
+        Self::new(key, label, Input::Money(InputField::new(label, false)))
+    }
+
+    /// Starts on today, see `DateField`.
+    pub fn date(key: &'static str, label: &str) -> Self {
+        Self::new(key, label, Input::Date(DateField::new(label)))
+    }
+
+    /// One of `options`, side by side, changed with ←→ or Space. Starts on the first.
+    /// There has to be at least one option to cycle through.
+    pub fn choice(key: &'static str, label: &str, options: Vec<(String, Color)>) -> Self {
+        assert!(!options.is_empty(), "choice field {:?} has no options", key);
+        Self::new(key, label, Input::Choice { options, selected: 0 })
+    }
+
+    /// The picker keeps its own label, given when it was built.
+    pub fn picker(key: &'static str, picker: Picker) -> Self {
+        let label = picker.input.label.clone();
+        Self::new(key, &label, Input::Picker(picker))
+    }
+
+    pub fn checkbox(key: &'static str, label: &str) -> Self {
+        Self::new(key, label, Input::Checkbox(false))
+    }
+
+    /// Empty text is an error on submit.
+    pub fn required(mut self) -> Self {
+        self.required = true;
+        self
+    }
+
+    pub fn validate(mut self, validator: Validator) -> Self {
+        self.validator = Some(validator);
+        self
+    }
+
+    fn height(&self) -> u16 {
+        match self.input {
+            Input::Checkbox(_) => 1,
+            _ => 3,
+        }
+    }
+
+    /// The text input, for the fields that have one.
+    fn text_input(&self) -> Option<&InputField> {
+        match self.input {
+            Input::Text(ref input) | Input::Money(ref input) => Some(input),
+            Input::Picker(ref picker) => Some(&picker.input),
+            _ => None,
+        }
+    }
+
+    fn text_input_mut(&mut self) -> Option<&mut InputField> {
+        match self.input {
+            Input::Text(ref mut input) | Input::Money(ref mut input) => Some(input),
+            Input::Picker(ref mut picker) => Some(&mut picker.input),
+            _ => None,
+        }
+    }
+
+    /// Check the text and show the outcome on the field. `submitting` also flags an
+    /// empty required field and a name the picker does not know.
+    fn check(&mut self, submitting: bool) -> Result<(), String> {
+        let required = self.required;
+        let validator = self.validator;
+        let result = match self.input {
+            Input::Text(ref input) | Input::Money(ref input) | Input::Picker(Picker { ref input, .. })
+                if input.content().trim().is_empty() =>
+            {
+                if required && submitting {
+                    Err("required".to_string())
+                } else {
+                    Ok(())
+                }
+            }
+            Input::Money(ref input) => Money::parse(input.content().trim()).map(drop),
+            Input::Picker(ref picker) if submitting && picker.is_checked() && picker.known().is_none() => {
+                Err(if picker.creatable {
+                    "unknown, pick a suggestion or choose + Create".to_string()
+                } else {
+                    "unknown, pick a suggestion".to_string()
+                })
+            }
+            _ => Ok(()),
+        };
+        let result = match (result, self.text_input(), validator) {
+            (Ok(()), Some(input), Some(validator)) if !input.content().trim().is_empty() => {
+                validator(input.content().trim())
+            }
+            (result, _, _) => result,
+        };
+        if let Some(input) = self.text_input_mut() {
+            input.error = result.as_ref().err().cloned();
+        }
+        result
+    }
+
+    fn render(&self, f: &mut Frame, area: Rect, is_active: bool) {
+        match self.input {
+            Input::Text(ref input) | Input::Money(ref input) => input.render(f, area, is_active),
+            Input::Date(ref date) => date.render(f, area, is_active),
+            Input::Picker(ref picker) => picker.render(f, area, is_active),
+            Input::Choice {
+                ref options,
+                selected,
+            } => {
+                let mut spans = Vec::new();
+                for (i, (label, color)) in options.iter().enumerate() {
+                    let style = if i == selected {
+                        Style::default().fg(Color::White).bg(*color).add_modifier(Modifier::BOLD)
+                    } else {
+                        Style::default().fg(Color::DarkGray)
+                    };
+                    spans.push(Span::styled(format!(" {} ", label), style));
+                    spans.push(Span::raw("  "));
+                }
+                if is_active {
+                    spans.push(Span::styled("←→ to change", Style::default().fg(Color::DarkGray)));
+                }
+                let border_style = if is_active {
+                    Style::default().fg(Color::Yellow)
+                } else {
+                    Style::default().fg(Color::Black)
+                };
+                let paragraph = Paragraph::new(Line::from(spans))
+                    .style(Style::default().fg(Color::Black).bg(Color::White))
+                    .block(
+                        Block::default()
+                            .title(self.label.as_str())
+                            .borders(Borders::ALL)
+                            .border_style(border_style),
+                    );
+                f.render_widget(paragraph, area);
+            }
+            Input::Checkbox(checked) => {
+                // Highlighted like an input border when focused
+                let text = format!("[{}] {}", if checked { "x" } else { " " }, self.label);
+                let style = if is_active {
+                    Style::default().fg(Color::Yellow).bg(Color::White)
+                } else {
+                    Style::default().fg(Color::Black).bg(Color::White)
+                };
+                let paragraph = Paragraph::new(text).style(style).alignment(Alignment::Center);
+                f.render_widget(paragraph, area);
+            }
+        }
+    }
+}
+
+/// What a key press did to a `Form`.
+pub enum FormOutcome {
+    Handled,
+    /// Enter outside a suggestion list: time for the page to send the form.
+    Submit,
+    /// The picker under `key` offered to create `name` and the user took it.
+    Create { key: &'static str, name: String },
+    /// Not a key the form uses, e.g. Esc.
+    Unhandled,
+}
+
+/// Fields in Tab order, with validation and error display.
+///
+/// The page owns what happens on submit: `handle_input` returns `FormOutcome::Submit`
+/// on Enter, the page calls `check` and reads the typed values by key.
+pub struct Form {
+    fields: Vec<Field>,
+    active: usize,
+}
+
+impl Form {
+    pub fn new(fields: Vec<Field>) -> Self {
+        Self { fields, active: 0 }
+    }
+
+    /// Rows the fields take, to size the page layout.
+    pub fn height(&self) -> u16 {
+        self.fields.iter().map(Field::height).sum()
+    }
+
+    /// A misspelt key is a bug, but only worth a panic in debug builds. Release
+    /// builds treat the field as empty and leave the form alone.
+    fn index(&self, key: &str) -> Option<usize> {
+        let index = self.fields.iter().position(|field| field.key == key);
+        debug_assert!(index.is_some(), "no form field {:?}", key);
+        index
+    }
+
+    fn field(&self, key: &str) -> Option<&Field> {
+        self.index(key).map(|index| &self.fields[index])
+    }
+
+    fn field_mut(&mut self, key: &str) -> Option<&mut Field> {
+        self.index(key).map(|index| &mut self.fields[index])
+    }
+
+    pub fn focus(&mut self, key: &str) {
+        if let Some(index) = self.index(key) {
+            self.active = index;
+        }
+    }
+
+    /// The calendar of a date field is open and wants every key, Esc included.
+    pub fn is_open(&self) -> bool {
+        self.fields
+            .iter()
+            .any(|field| matches!(field.input, Input::Date(ref date) if date.is_open()))
+    }
+
+    /// The text of a text, password, money or picker field, as typed.
+    pub fn text(&self, key: &str) -> &str {
+        self.field(key)
+            .and_then(Field::text_input)
+            .map_or("", InputField::content)
+    }
+
+    pub fn set_text(&mut self, key: &str, text: impl Into<String>) {
+        if let Some(input) = self.field_mut(key).and_then(Field::text_input_mut) {
+            input.set_content(text);
+            input.error = None;
+        }
+    }
+
+    /// The amount of a money field, `None` while empty or invalid.
+    pub fn money(&self, key: &str) -> Option<Money> {
+        Money::parse(self.text(key).trim()).ok()
+    }
+
+    pub fn date(&self, key: &str) -> NaiveDate {
+        match self.field(key).map(|field| &field.input) {
+            Some(Input::Date(date)) => date.date,
+            _ => Local::now().date_naive(),
+        }
+    }
+
+    pub fn set_date(&mut self, key: &str, value: NaiveDate) {
+        if let Some(Input::Date(date)) = self.field_mut(key).map(|field| &mut field.input) {
+            date.date = value;
+        }
+    }
+
+    /// Index of the chosen option.
+    pub fn choice(&self, key: &str) -> usize {
+        match self.field(key).map(|field| &field.input) {
+            Some(Input::Choice { selected, .. }) => *selected,
+            _ => 0,
+        }
+    }
+
+    pub fn set_choice(&mut self, key: &str, index: usize) {
+        if let Some(Input::Choice { options, selected }) =
+            self.field_mut(key).map(|field| &mut field.input)
+        {
+            *selected = index.min(options.len().saturating_sub(1));
+        }
+    }
+
+    pub fn checked(&self, key: &str) -> bool {
+        matches!(self.field(key).map(|field| &field.input), Some(Input::Checkbox(true)))
+    }
+
+    /// The name a picker stands for: the known spelling, or the text if unchecked.
+    pub fn name(&self, key: &str) -> String {
+        match self.field(key).map(|field| &field.input) {
+            Some(Input::Picker(picker)) => picker
+                .known()
+                .unwrap_or(picker.input.content().trim())
+                .to_string(),
+            _ => self.text(key).trim().to_string(),
+        }
+    }
+
+    /// `None` if `key` is not a picker field.
+    pub fn picker_mut(&mut self, key: &str) -> Option<&mut Picker> {
+        match self.field_mut(key).map(|field| &mut field.input) {
+            Some(Input::Picker(picker)) => Some(picker),
+            _ => None,
+        }
+    }
+
+    /// Empty every text field and go back to the first field.
+    pub fn clear(&mut self) {
+        for field in &mut self.fields {
+            if let Some(input) = field.text_input_mut() {
+                input.clear();
+                input.error = None;
+            }
+        }
+        self.active = 0;
+    }
+
+    /// Show `error` on a field and move there, for checks across fields.
+    pub fn set_error(&mut self, key: &str, error: &str) {
+        if let Some(input) = self.field_mut(key).and_then(Field::text_input_mut) {
+            input.error = Some(error.to_string());
+        }
+        self.focus(key);
+    }
+
+    /// Check every field before sending. On failure the first bad field gets the
+    /// focus and the message says what is wrong with it.
+    pub fn check(&mut self) -> Result<(), String> {
+        let mut first = None;
+        for (i, field) in self.fields.iter_mut().enumerate() {
+            if let Err(error) = field.check(true) {
+                first.get_or_insert((i, format!("{}: {}", field.label, error)));
+            }
+        }
+        match first {
+            Some((i, message)) => {
+                self.active = i;
+                Err(message)
+            }
+            None => Ok(()),
+        }
+    }
+
+    pub fn handle_input(&mut self, key: KeyCode, modifiers: KeyModifiers) -> FormOutcome {
+        let count = self.fields.len();
+        let field = &mut self.fields[self.active];
+
+        // The open calendar takes every key until a day is picked or it is closed
+        if let Input::Date(ref mut date) = field.input {
+            if date.is_open() {
+                date.handle_input(key);
+                return FormOutcome::Handled;
+            }
+        }
+
+        match key {
+            KeyCode::Tab => self.active = (self.active + 1) % count,
+            KeyCode::BackTab => self.active = (self.active + count - 1) % count,
+            KeyCode::Esc => return FormOutcome::Unhandled,
+            _ => match field.input {
+                // The pickers use Up/Down/Enter for their suggestions
+                Input::Picker(ref mut picker) => match picker.handle_input(key, modifiers) {
+                    PickerOutcome::Handled => {
+                        picker.input.error = None;
+                    }
+                    PickerOutcome::Create(name) => {
+                        return FormOutcome::Create {
+                            key: field.key,
+                            name,
+                        }
+                    }
+                    PickerOutcome::Unhandled if key == KeyCode::Enter => return FormOutcome::Submit,
+                    PickerOutcome::Unhandled => return FormOutcome::Unhandled,
+                },
+                _ if key == KeyCode::Enter => return FormOutcome::Submit,
+                Input::Text(ref mut input) | Input::Money(ref mut input) => {
+                    input.handle_input(key, modifiers);
+                    // Flag bad input as it is typed, an empty field only on submit
+                    let _ = field.check(false);
+                }
+                Input::Date(ref mut date) => date.handle_input(key),
+                Input::Choice {
+                    ref options,
+                    ref mut selected,
+                } => {
+                    let count = options.len();
+                    match key {
+                        KeyCode::Right | KeyCode::Char(' ') => *selected = (*selected + 1) % count,
+                        KeyCode::Left => *selected = (*selected + count - 1) % count,
+                        _ => return FormOutcome::Unhandled,
+                    }
+                }
+                Input::Checkbox(ref mut checked) => match key {
+                    KeyCode::Char(' ') => *checked = !*checked,
+                    _ => return FormOutcome::Unhandled,
+                },
+            },
+        }
+        FormOutcome::Handled
+    }
+
+    /// Insert pasted text into the focused field, if it takes text.
+    pub fn paste(&mut self, text: &str) {
+        let field = &mut self.fields[self.active];
+        match field.input {
+            Input::Text(ref mut input) | Input::Money(ref mut input) => {
+                input.paste(text);
+                let _ = field.check(false);
+            }
+            Input::Picker(ref mut picker) => {
+                picker.paste(text);
+                picker.input.error = None;
+            }
+            _ => {}
+        }
+    }
+
+    fn areas(&self, area: Rect) -> Vec<Rect> {
+        let mut constraints: Vec<Constraint> = self
+            .fields
+            .iter()
+            .map(|field| Constraint::Length(field.height()))
+            .collect();
+        constraints.push(Constraint::Min(0));
+        Layout::vertical(constraints).split(area).to_vec()
+    }
+
+    pub fn render(&self, f: &mut Frame, area: Rect) {
+        for (i, (field, area)) in self.fields.iter().zip(self.areas(area)).enumerate() {
+            field.render(f, area, i == self.active);
+        }
+    }
+
+    /// Draw the suggestions of the active picker and an open calendar on top of the
+    /// page, call it after everything else with the same `area` as `render`.
+    pub fn render_popups(&self, f: &mut Frame, area: Rect) {
+        let areas = self.areas(area);
+        match self.fields[self.active].input {
+            Input::Picker(ref picker) => picker.render_dropdown(f, areas[self.active]),
+            Input::Date(ref date) => date.render_calendar(f),
             _ => {}
         }
     }
 }
+
+/// How well `query` matches `candidate` as a case-insensitive subsequence, `None` if
+/// it does not. Runs of consecutive letters and matches at word starts score higher.
+fn fuzzy_score(query: &str, candidate: &str) -> Option<i32> {
+    let candidate: Vec<char> = candidate.to_lowercase().chars().collect();
+    let mut score = 0;
+    let mut next = 0; // Where to look for the next query letter
+    let mut previous: Option<usize> = None;
+    for wanted in query.to_lowercase().chars().filter(|c| !c.is_whitespace()) {
+        let found = next + candidate[next..].iter().position(|c| *c == wanted)?;
+        score += 1;
+        if previous.is_some_and(|previous| previous + 1 == found) {
+            score += 5;
+        }
+        if found == 0 || !candidate[found - 1].is_alphanumeric() {
+            score += 3;
+        }
+        score -= (found - next) as i32; // Skipped letters
+        previous = Some(found);
+        next = found + 1;
+    }
+    Some(score)
+}
@@ -1,7 +1,11 @@
-use crate::ui::report::create_lines;
+use crate::error::FinTrakError;
+use crate::offline::Conflict;
+use crate::ui::components::ErrorPopup;
+use crate::ui::report::{create_lines, CashFlow};
 use ratatui::{
-    layout::{Alignment, Constraint, Direction, Layout},
-    style::{Color, Modifier, Style},
+    layout::{Alignment, Constraint, Direction, Layout, Rect},
+    style::{Color, Modifier, Style, Stylize},
+    text::Line,
     widgets::{Block, Borders, Paragraph, Wrap},
     Frame,
 };
@@ -12,25 +16,107 @@ pub struct Homepage {
     pub report_overview: Vec<String>,
     pub category_overview: Vec<String>,
     pub account_overview: Vec<String>,
+    pub cash_flow: Option<CashFlow>, // Income, expenses and net over every transaction
+    pub offline: bool,            // Overviews come from the local cache
+    pub queued: usize,            // Writes waiting in the offline journal
+    pub conflicts: Vec<Conflict>, // Queued writes the server rejected
+    pub show_conflicts: bool,     // Conflict report replaces the main blocks
+    pub sync_message: String,     // Outcome of the last journal replay
+    pub error: Option<ErrorPopup<()>>, // Retrying refreshes the overviews
 }
 
 impl Homepage {
-    pub fn new(
-        username: String,
-        email: String,
-        report_overview: Vec<String>,
-        category_overview: Vec<String>,
-        account_overview: Vec<String>
-    ) -> Self {
+    /// The overviews start as placeholders until `Action::Overviews` arrives.
+    pub fn new(username: String, email: String) -> Self {
+        let loading = || vec!["Loading...".to_string()];
         Self {
             username,
             email,
-            report_overview,
-            category_overview,
-            account_overview,
+            report_overview: loading(),
+            category_overview: loading(),
+            account_overview: loading(),
+            cash_flow: None,
+            offline: false,
+            queued: 0,
+            conflicts: Vec::new(),
+            show_conflicts: false,
+            sync_message: String::new(),
+            error: None,
         }
     }
 
+    /// Show the fresh overviews, with a placeholder and a popup for those that failed.
+    pub fn overviews_loaded(
+        &mut self,
+        report: Result<Vec<String>, FinTrakError>,
+        category: Result<Vec<String>, FinTrakError>,
+        account: Result<Vec<String>, FinTrakError>,
+        cash_flow: Result<CashFlow, FinTrakError>,
+    ) {
+        let mut failure = None;
+        let mut lines = |result: Result<Vec<String>, FinTrakError>| {
+            result.unwrap_or_else(|e| {
+                // The three usually fail for the same reason, one popup is enough
+                failure.get_or_insert(e);
+                vec!["Unavailable".to_string()]
+            })
+        };
+        self.report_overview = lines(report);
+        self.category_overview = lines(category);
+        self.account_overview = lines(account);
+        self.cash_flow = match cash_flow {
+            Ok(cash_flow) => Some(cash_flow),
+            Err(e) => {
+                failure.get_or_insert(e);
+                None
+            }
+        };
+        if let Some(e) = failure {
+            self.error = Some(ErrorPopup::new(e, ()));
+        }
+    }
+
+    /// One line about the offline journal, empty when everything is in sync.
+    fn sync_status(&self) -> String {
+        let mut parts = Vec::new();
+        if self.offline {
+            parts.push("Offline, showing saved data".to_string());
+        }
+        if self.queued > 0 {
+            parts.push(format!("{} change(s) waiting to sync", self.queued));
+        }
+        if !self.conflicts.is_empty() {
+            parts.push(format!(
+                "{} rejected, press 'c' to review",
+                self.conflicts.len()
+            ));
+        }
+        if parts.is_empty() {
+            self.sync_message.clone()
+        } else {
+            parts.join(" | ")
+        }
+    }
+
+    fn render_conflicts(&self, f: &mut Frame, area: Rect) {
+        let mut lines = vec![Line::raw(
+            "These offline changes were rejected by the server and were not applied:",
+        )];
+        for conflict in &self.conflicts {
+            lines.push(Line::raw(""));
+            lines.push(Line::raw(conflict.entry.write.describe()).bold());
+            lines.push(Line::raw(format!("  {}", conflict.reason)).red());
+        }
+        let block = Block::default()
+            .title("Sync conflicts")
+            .borders(Borders::ALL);
+        let paragraph = Paragraph::new(lines)
+            .style(Style::default().fg(Color::Black).bg(Color::White))
+            .wrap(Wrap { trim: false })
+            .block(block);
+        f.render_widget(paragraph, area);
+    }
+
     pub fn render(&self, f: &mut Frame) {
         // Set white background for the whole page
         let background = Block::default().style(Style::default().bg(Color::White));
@@ -63,7 +149,8 @@ impl Homepage {
             .split(chunks[0]);
 
         let greeting = format!("Welcome back, {}", self.username);
-        let greeting_paragraph = Paragraph::new(greeting)
+        let greeting_lines = vec![Line::raw(greeting), Line::raw(self.sync_status()).red()];
+        let greeting_paragraph = Paragraph::new(greeting_lines)
             .style(Style::default().fg(Color::Black).bg(Color::White))
             .alignment(Alignment::Left);
 
@@ -81,6 +168,41 @@ impl Homepage {
         f.render_widget(greeting_paragraph, horizontal_layout[0]);
         f.render_widget(title, horizontal_layout[1]);
 
+        if self.show_conflicts {
+            self.render_conflicts(f, chunks[1]);
+        } else {
+            self.render_overviews(f, chunks[1]);
+        }
+
+        // Bottom notice for navigation instructions (Esc to quit, etc.)
+        let notice = Paragraph::new("Esc to quit | 1 to Account | 2 to Category | 3 to Report | 4 to Transactions")
+            .style(Style::default().fg(Color::DarkGray).bg(Color::White))
+            .alignment(Alignment::Center);
+        f.render_widget(notice, chunks[2]);
+
+        // Additional notice for transaction creation
+        let second_notice = if self.show_conflicts {
+            "'c' to close the report | 'x' to dismiss the conflicts"
+        } else {
+            "Press 'n' to create a new transaction | 't' to transfer | 'l' to log out"
+        };
+        let create_transaction_notice = Paragraph::new(second_notice)
+            .style(Style::default().fg(Color::DarkGray).bg(Color::White))
+            .alignment(Alignment::Center);
+        f.render_widget(
+            create_transaction_notice,
+            Layout::default()
+                .direction(Direction::Vertical)
+                .constraints([Constraint::Length(1), Constraint::Length(1)].as_ref())
+                .split(chunks[2])[1],
+        ); // Place it right below the first notice
+
+        if let Some(ref popup) = self.error {
+            popup.render(f);
+        }
+    }
+
+    fn render_overviews(&self, f: &mut Frame, area: Rect) {
         // Main horizontal blocks: Accounts, Categories, Report
         let main_chunks = Layout::default()
             .direction(Direction::Horizontal)
@@ -92,7 +214,7 @@ impl Homepage {
                 ]
                 .as_ref(),
             )
-            .split(chunks[1]);
+            .split(area);
 
         // Accounts block (press 1 to jump) with account overview
         let accounts_block = Block::default().title("Accounts").borders(Borders::ALL);
@@ -110,27 +232,13 @@ impl Homepage {
 
         // Report block (press 3 to jump) with report overview
         let report_block = Block::default().title("Report").borders(Borders::ALL);
-        let report_paragraph = Paragraph::new(create_lines(self.report_overview.clone()))
+        let mut report_lines = create_lines(self.report_overview.clone());
+        if let Some(ref cash_flow) = self.cash_flow {
+            report_lines.splice(0..0, [cash_flow.line(), Line::raw("")]);
+        }
+        let report_paragraph = Paragraph::new(report_lines)
             .wrap(Wrap { trim: true })
             .block(report_block);
         f.render_widget(report_paragraph, main_chunks[2]);
-
-        // Bottom notice for navigation instructions (Esc to quit, etc.)
-        let notice = Paragraph::new("Esc to quit | 1 to Account | 2 to Category | 3 to Report")
-            .style(Style::default().fg(Color::DarkGray).bg(Color::White))
-            .alignment(Alignment::Center);
-        f.render_widget(notice, chunks[2]);
-
-        // Additional notice for transaction creation
-        let create_transaction_notice = Paragraph::new("Press 'n' to create a new transaction")
-            .style(Style::default().fg(Color::DarkGray).bg(Color::White))
-            .alignment(Alignment::Center);
-        f.render_widget(
-            create_transaction_notice,
-            Layout::default()
-                .direction(Direction::Vertical)
-                .constraints([Constraint::Length(1), Constraint::Length(1)].as_ref())
-                .split(chunks[2])[1],
-        ); // Place it right below the first notice
     }
 }
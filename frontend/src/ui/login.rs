@@ -5,37 +5,32 @@ use ratatui::{
     widgets::{Block, Paragraph},
     Frame,
 };
-use reqwest::Client;
-#[allow(unused_imports)]
-use serde::{Deserialize, Serialize};
-
-use crate::ui::components::InputField;
-use crate::ui::homepage::Homepage;
-use crate::ui::report::*;
-
-#[derive(Serialize)]
-struct SignupData {
-    username: String,
-    email: String,
-    password: String,
-}
+
+use crate::action::{Action, Dispatcher};
+use crate::error::FinTrakError;
+use crate::session::{self, StoredSession};
+use crate::ui::components::{ErrorPopup, Field, Form, FormOutcome, Loading, PopupOutcome};
 
 pub struct LoginPage {
-    pub email: InputField,
-    pub password: InputField,
-    pub active_field: usize,
+    form: Form, // The remember me checkbox keeps the session on disk for the next launch
     pub response_message: String,
-    pub report_overview: Vec<String>,
+    dispatcher: Dispatcher,
+    loading: Loading,
+    error: Option<ErrorPopup<()>>, // Retrying just submits the form again
 }
 
 impl LoginPage {
-    pub fn new() -> Self {
+    pub fn new(dispatcher: Dispatcher) -> Self {
         Self {
-            email: InputField::new("Email", false),
-            password: InputField::new("Password", true),
-            active_field: 0,
+            form: Form::new(vec![
+                Field::text("email", "Email").required(),
+                Field::password("password", "Password").required(),
+                Field::checkbox("remember_me", "Remember me"),
+            ]),
             response_message: String::new(),
-            report_overview: vec![],
+            dispatcher,
+            loading: Loading::default(),
+            error: None,
         }
     }
 
@@ -49,8 +44,7 @@ impl LoginPage {
             .constraints(
                 [
                     Constraint::Length(8), // ASCII Title
-                    Constraint::Length(3), // Email Input
-                    Constraint::Length(3), // Password Input
+                    Constraint::Length(self.form.height()), // Inputs and remember me
                     Constraint::Length(3), // Response message
                     Constraint::Length(3), // Bottom Notice
                 ]
@@ -73,106 +67,132 @@ impl LoginPage {
             .alignment(Alignment::Center);
         f.render_widget(title_paragraph, chunks[0]);
 
-        self.email.render(f, chunks[1], self.active_field == 0);
-        self.password.render(f, chunks[2], self.active_field == 1);
+        self.form.render(f, chunks[1]);
 
         // Response message displayed between the password and the bottom notice
         let response_paragraph = Paragraph::new(self.response_message.clone())
             .style(Style::default().fg(Color::Red).bg(Color::White)) // Red message for errors
             .alignment(Alignment::Center);
-        f.render_widget(response_paragraph, chunks[3]);
+        f.render_widget(response_paragraph, chunks[2]);
 
         // Render the bottom notice
-        let notice_text = "Esc to quit | Hit Enter to login";
-        let notice_paragraph = Paragraph::new(notice_text)
+        let notice_text = "Esc to quit | Tab to switch fields | Space to toggle remember me | Hit Enter to login";
+        let notice_paragraph = Paragraph::new(self.loading.footer(notice_text))
             .style(Style::default().fg(Color::DarkGray).bg(Color::White)) // Grey text, white background
             .alignment(Alignment::Center);
-        f.render_widget(notice_paragraph, chunks[4]);
-    }
+        f.render_widget(notice_paragraph, chunks[3]);
 
-    pub async fn handle_input(
-        &mut self,
-        key: KeyCode,
-        _modifiers: KeyModifiers,
-        homepage: &mut Option<Homepage>,
-    ) -> bool {
-        if key == KeyCode::Esc {
-            return true; // Signal to quit
+        if let Some(ref popup) = self.error {
+            popup.render(f);
         }
+    }
 
-        match key {
-            KeyCode::Tab => {
-                self.active_field = (self.active_field + 1) % 2; // Cycle through input fields
-            }
-            KeyCode::BackTab => {
-                self.active_field = if self.active_field == 0 {
-                    1
-                } else {
-                    self.active_field - 1
-                };
+    pub fn handle_input(&mut self, key: KeyCode, modifiers: KeyModifiers) {
+        // While a request runs, Esc cancels it and everything else waits
+        if self.loading.is_loading() {
+            if key == KeyCode::Esc {
+                if let Some(label) = self.loading.cancel() {
+                    self.response_message = format!("Cancelled: {}", label);
+                }
             }
-            KeyCode::Enter => {
-                self.submit(homepage).await;
+            return;
+        }
 
-                // Transition to homepage if login is successful
-                if homepage.is_some() {
-                    return true; // Signal that we should transition state
-                }
+        // An error popup takes every key until it is closed
+        if let Some(popup) = self.error.take() {
+            match popup.handle_input(key) {
+                PopupOutcome::Open(popup) => self.error = Some(popup),
+                PopupOutcome::Dismissed => {}
+                PopupOutcome::Retry(()) => self.submit(),
             }
-            _ => match self.active_field {
-                0 => self.email.handle_input(key),
-                1 => self.password.handle_input(key),
-                _ => {}
-            },
+            return;
+        }
+        if let FormOutcome::Submit = self.form.handle_input(key, modifiers) {
+            self.submit();
+        }
+    }
+
+    /// Insert pasted text into the focused field.
+    pub fn handle_paste(&mut self, text: &str) {
+        if !self.loading.is_loading() && self.error.is_none() {
+            self.form.paste(text);
+        }
+    }
+
+    pub fn submit(&mut self) {
+        if let Err(e) = self.form.check() {
+            self.response_message = e;
+            return;
         }
-        false
+        let email = self.form.text("email").trim().to_string();
+        let password = self.form.text("password").to_string();
+        self.response_message.clear();
+        let handle = self.dispatcher.spawn(move |client| async move {
+            let result = client.login(&email, &password).await;
+            Action::LoggedIn { email, result }
+        });
+        self.loading.start("Logging in...", handle);
+    }
+
+    /// Prefill the email, for a stored session or the demo account.
+    pub fn set_email(&mut self, email: &str) {
+        self.form.set_text("email", email);
     }
 
-    pub async fn submit(&mut self, homepage: &mut Option<Homepage>) {
-        let client = Client::new();
-        let login_data = SignupData {
-            username: "_login".to_string(),
-            email: self.email.content.clone(),
-            password: self.password.content.clone(),
-        };
-
-        match client
-            .post("http://0.0.0.0:8000/signup")
-            .json(&login_data)
-            .send()
-            .await
-        {
-            Ok(response) => {
-                let status = response.status();
-                let raw_body = response.text().await.unwrap_or_default();
-
-                if status == 200 && raw_body.contains("Login successful") {
-                    self.report_overview = get_report_overview(login_data.email.clone()).await;
-                    let category_overview = get_category_overview(login_data.email.clone()).await;
-                    let account_overview = get_account_overview(login_data.email.clone()).await;
-                    if let Some(username) = raw_body.split_whitespace().next() {
-                        *homepage = Some(Homepage::new(
-                            username.to_string(),
-                            self.email.content.clone(),
-                            self.report_overview.clone(),
-                            category_overview,
-                            account_overview,
-                        ));
-                        self.response_message =
-                            "Login successful! Redirecting to homepage...".to_string();
+    pub fn set_password(&mut self, password: &str) {
+        self.form.set_text("password", password);
+    }
+
+    /// The token is all we need once logged in, don't keep the password around.
+    pub fn clear_password(&mut self) {
+        self.form.set_text("password", "");
+    }
+
+    /// A request or an error popup is up, Esc belongs to them rather than the app.
+    pub fn is_busy(&self) -> bool {
+        self.loading.is_loading() || self.error.is_some()
+    }
+
+    /// Advance the loading spinner.
+    pub fn tick(&mut self) {
+        self.loading.tick();
+    }
+
+    /// Handle the `/login` answer, returning the username when we are in. An answer
+    /// nobody waits for any more, because the login was cancelled, is dropped.
+    pub fn login_finished(&mut self, email: &str, result: Result<String, FinTrakError>) -> Option<String> {
+        if !self.loading.is_loading() {
+            return None;
+        }
+        self.loading.finish();
+        match result {
+            Ok(username) => {
+                self.clear_password();
+                self.response_message = "Login successful! Redirecting to homepage...".to_string();
+                // A demo token means nothing to the real backend, never store it
+                if !self.dispatcher.client().is_demo() {
+                    if self.form.checked("remember_me") {
+                        if let Some(token) = self.dispatcher.client().token() {
+                            let stored = StoredSession {
+                                email: email.to_string(),
+                                username: username.clone(),
+                                token,
+                            };
+                            if let Err(e) = session::save(&stored) {
+                                self.response_message = format!("Could not remember session: {}", e);
+                            }
+                        }
+                    } else if let Err(e) = session::clear() {
+                        // Otherwise whoever was remembered before would be resumed next launch
+                        self.response_message = format!("Could not forget the old session: {}", e);
                     }
-                } else if status == 400 {
-                    self.response_message = "Invalid password. Please try again.".to_string();
-                } else if status == 201 {
-                    self.response_message =
-                        "Email not registered. Please sign up first.".to_string();
-                } else {
-                    self.response_message =
-                        format!("ERROR_CODE: {}\nMessage: {}", status, raw_body);
                 }
+                Some(username)
             }
             Err(e) => {
-                self.response_message = format!("Request failed: {}", e);
+                self.response_message = "Login failed.".to_string();
+                self.error = Some(ErrorPopup::new(e, ()));
+                None
             }
         }
     }
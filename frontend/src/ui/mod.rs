@@ -5,6 +5,10 @@ pub mod login;
 pub mod report;
 pub mod signup;
 pub mod account_main;
+pub mod account_detail;
 pub mod category_main;
+pub mod category_detail;
 pub mod report_main;
-pub mod transaction_create;
\ No newline at end of file
+pub mod transaction_create;
+pub mod transaction_main;
+pub mod transfer_create;
\ No newline at end of file
@@ -1,92 +1,120 @@
-use ratatui::{style::Stylize, text::Line};
-use reqwest::Client;
-
-pub async fn get_account_overview(user_email: String) -> Vec<String> {
-    let client = Client::new();
-    let url = format!(
-        "http://localhost:8000/account_summary?email={}",
-        user_email
-    );
+use ratatui::{
+    style::{Color, Style, Stylize},
+    text::{Line, Span},
+};
+
+use crate::api::models::{Transaction, TransactionKind};
+use crate::api::FinTrakClient;
+use crate::error::FinTrakError;
+use crate::money::Money;
 
-    match client.get(&url).send().await {
-        Ok(response) => {
-            match response.status() {
-                reqwest::StatusCode::OK => {
-                    if let Ok(accounts) = response.json::<Vec<crate::ui::account_main::Account>>().await {
-                        accounts.iter().map(|acc| {
-                            format!(
-                                "{}: {}",
-                                acc.account_name,
-                                acc.account_type
-                            )
-                        }).collect()
-                    } else {
-                        vec!["Error parsing account data".to_string()]
-                    }
-                }
-                _ => vec!["Failed to fetch accounts".to_string()]
+/// Money in and money out over some transactions, transfers between accounts left out.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct CashFlow {
+    pub income: Money,
+    pub expenses: Money, // Refunds already taken off
+}
+
+impl CashFlow {
+    pub fn of<'a>(transactions: impl IntoIterator<Item = &'a Transaction>) -> Self {
+        let mut flow = CashFlow::default();
+        for transaction in transactions.into_iter().filter(|t| !t.is_transfer()) {
+            match transaction.kind {
+                TransactionKind::Income => flow.income += transaction.amount,
+                TransactionKind::Expense => flow.expenses += transaction.amount,
+                TransactionKind::Refund => flow.expenses = flow.expenses - transaction.amount,
             }
         }
-        Err(_) => vec!["Error connecting to server".to_string()]
+        flow
     }
-}
 
-pub async fn get_category_overview(user_email: String) -> Vec<String> {
-    let client = Client::new();
-    let url = format!(
-        "http://localhost:8000/category_summary?email={}",
-        user_email
-    );
+    pub fn net(&self) -> Money {
+        self.income - self.expenses
+    }
 
-    match client.get(&url).send().await {
-        Ok(response) => {
-            match response.status() {
-                reqwest::StatusCode::OK => {
-                    if let Ok(categories) = response.json::<Vec<crate::ui::category_main::Category>>().await {
-                        categories.iter().map(|cat| {
-                            format!(
-                                "{}: {} (Budget: ${} {})",
-                                cat.nickname,
-                                cat.category_type,
-                                cat.budget,
-                                cat.budget_freq
-                            )
-                        }).collect()
-                    } else {
-                        vec!["Error parsing category data".to_string()]
-                    }
-                }
-                _ => vec!["Failed to fetch categories".to_string()]
-            }
-        }
-        Err(_) => vec!["Error connecting to server".to_string()]
+    /// `Income +X | Expenses -Y | Net Z`, each amount in its colour.
+    pub fn line(&self) -> Line<'static> {
+        Line::from(vec![
+            Span::raw("Income "),
+            signed_span(self.income),
+            Span::raw(" | Expenses "),
+            signed_span(-self.expenses),
+            Span::raw(" | Net "),
+            signed_span(self.net()),
+        ])
     }
 }
 
-pub async fn get_report_overview(user_email: String) -> Vec<String> {
-    let client = Client::new();
-    let overview_url = format!(
-        "http://0.0.0.0:8000/report_overview?email={}",
-        user_email.clone().as_str()
-    );
-    match client.get(overview_url).send().await {
-        Ok(response) => {
-            let status = response.status();
-            let raw_body = response.text().await.unwrap_or_default();
-            let body_vec: Vec<String> = serde_json::from_str(raw_body.as_str()).unwrap();
-
-            if status.is_success() {
-                return body_vec;
-            } else {
-                return vec!["Error querying report overview!".to_string()];
-            }
-        }
-        Err(_) => {
-            return vec!["Error querying report overview!".to_string()];
-        }
+/// Green for money coming in, red for money going out.
+pub fn amount_style(amount: Money) -> Style {
+    if amount.is_negative() {
+        Style::default().fg(Color::Red)
+    } else if amount.is_zero() {
+        Style::default().fg(Color::Black)
+    } else {
+        Style::default().fg(Color::Green)
     }
 }
 
+/// A signed amount (`+12.00`, `-3.50`) in its colour.
+pub fn signed_span(amount: Money) -> Span<'static> {
+    Span::styled(format!("{:+}", amount), amount_style(amount))
+}
+
+/// The account overview and the cash flow, both worked out from one fetch of the ledger.
+pub async fn get_account_overview_and_cash_flow(
+    client: &FinTrakClient,
+    user_email: &str,
+) -> (
+    Result<Vec<String>, FinTrakError>,
+    Result<CashFlow, FinTrakError>,
+) {
+    let (accounts, transactions) = tokio::join!(
+        client.account_summary(user_email),
+        client.transactions(user_email)
+    );
+    // Without the ledger the accounts are still worth listing, just without balances
+    let overview = accounts.map(|accounts| {
+        accounts
+            .iter()
+            .map(|acc| match transactions {
+                Ok(ref transactions) => format!(
+                    "{}: {} | ${}",
+                    acc.account_name,
+                    acc.account_type,
+                    acc.balance(transactions)
+                ),
+                Err(_) => format!("{}: {}", acc.account_name, acc.account_type),
+            })
+            .collect()
+    });
+    let cash_flow = transactions.map(|transactions| CashFlow::of(&transactions));
+    (overview, cash_flow)
+}
+
+pub async fn get_category_overview(
+    client: &FinTrakClient,
+    user_email: &str,
+) -> Result<Vec<String>, FinTrakError> {
+    let categories = client.category_summary(user_email).await?;
+    Ok(categories
+        .iter()
+        .map(|cat| {
+            format!(
+                "{}: {} (Budget: ${} {})",
+                cat.nickname, cat.category_type, cat.budget, cat.budget_freq
+            )
+        })
+        .collect())
+}
+
+pub async fn get_report_overview(
+    client: &FinTrakClient,
+    user_email: &str,
+) -> Result<Vec<String>, FinTrakError> {
+    client.report_overview(user_email).await
+}
+
 // helper function to render summary tab
 /// Create some lines to display in the paragraph.
 pub fn create_lines(summary_lines: Vec<String>) -> Vec<Line<'static>> {
@@ -103,3 +131,57 @@ pub fn create_lines(summary_lines: Vec<String>) -> Vec<Line<'static>> {
     }
     formatted_lines
 }
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+
+    use super::*;
+
+    fn transaction(kind: TransactionKind, cents: i64, transfer_id: Option<i32>) -> Transaction {
+        Transaction {
+            trans_id: 1,
+            email: "a@b.c".to_string(),
+            category_name: String::new(),
+            account_name: "Checking".to_string(),
+            amount: Money::from_cents(cents),
+            kind,
+            notes: String::new(),
+            date: NaiveDate::from_ymd_opt(2024, 3, 1).unwrap(),
+            transfer_id,
+        }
+    }
+
+    #[test]
+    fn cash_flow_takes_refunds_off_expenses_and_skips_transfers() {
+        let transactions = [
+            transaction(TransactionKind::Income, 300_000, None),
+            transaction(TransactionKind::Expense, 12_000, None),
+            transaction(TransactionKind::Refund, 2_000, None),
+            transaction(TransactionKind::Expense, 50_000, Some(9)),
+            transaction(TransactionKind::Income, 50_000, Some(9)),
+        ];
+        let flow = CashFlow::of(&transactions);
+        assert_eq!(flow.income, Money::from_cents(300_000));
+        assert_eq!(flow.expenses, Money::from_cents(10_000));
+        assert_eq!(flow.net(), Money::from_cents(290_000));
+    }
+
+    #[test]
+    fn cash_flow_can_be_negative() {
+        let transactions = [
+            transaction(TransactionKind::Income, 1_000, None),
+            transaction(TransactionKind::Expense, 4_000, None),
+        ];
+        assert_eq!(CashFlow::of(&transactions).net(), Money::from_cents(-3_000));
+        assert_eq!(CashFlow::of(&[]).net(), Money::ZERO);
+    }
+
+    #[test]
+    fn amounts_are_coloured_by_sign() {
+        assert_eq!(amount_style(Money::from_cents(-1)).fg, Some(Color::Red));
+        assert_eq!(amount_style(Money::ZERO).fg, Some(Color::Black));
+        assert_eq!(amount_style(Money::from_cents(1)).fg, Some(Color::Green));
+        assert_eq!(signed_span(Money::from_cents(250)).content, "+2.50");
+    }
+}
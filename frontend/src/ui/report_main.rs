@@ -1,4 +1,5 @@
 use std::borrow::BorrowMut;
+use std::collections::HashMap;
 
 use crossterm::event::{KeyCode, KeyModifiers};
 #[allow(unused_imports)]
@@ -12,20 +13,21 @@ use ratatui::{
     },
     Frame,
 };
-use reqwest::Client;
-use serde::{Deserialize, Serialize};
 
-#[derive(Deserialize, Debug, Serialize)]
-pub struct CategorySummary {
-    pub nickname: String,
-    pub budget: f64,
-    pub budget_freq: String,
-    pub overbudget: bool,
-    pub total: f64,
-    // a vector of corresponding transaction ids of the entries below
-    pub transaction_idz: Vec<i32>,
-    // a vector of all the relevant transactions within budget freq frame
-    pub cat_trans: Vec<String>,
+use crate::action::{Action, Dispatcher};
+use crate::api::models::{CategorySummary, Transaction};
+use crate::api::Sent;
+use crate::error::FinTrakError;
+use crate::history::{Mutation, UNDO_HINT};
+use crate::money::Money;
+use crate::ui::components::{ConfirmDialog, ConfirmOutcome, ErrorPopup, Loading, PopupOutcome};
+use crate::ui::report::{signed_span, CashFlow};
+
+/// The request behind an error popup, repeated when the user retries.
+enum Retry {
+    Fetch,
+    Delete(i32),
+    Edit(i32),
 }
 
 // https://ratatui.rs/examples/widgets/block/
@@ -33,18 +35,18 @@ pub struct CategorySummary {
 fn title_block(
     cat_name: &str,
     overbudget: bool,
-    budget: f64,
+    budget: Money,
     budget_freq: String,
-    spent: f64,
+    spent: Money,
     is_active: bool,
-) -> Block {
+) -> Block<'_> {
     // a line on the budget information:
-    let budget_str = format!("{:.2}", budget);
-    let spent_str = format!("{:.2}", spent);
+    let budget_str = budget.to_string();
+    let spent_str = spent.to_string();
     let mut budget_freq_str = budget_freq.to_string();
     budget_freq_str.make_ascii_uppercase();
     let budget_suffix = format!("{} budget: {}", budget_freq_str, budget_str);
-    let new_block = match overbudget {
+    match overbudget {
         false => Block::bordered()
             .title(cat_name.blue().on_white().bold())
             .title(spent_str.green().on_white().bold())
@@ -63,8 +65,7 @@ fn title_block(
             } else {
                 Color::Black
             })),
-    };
-    new_block
+    }
 }
 
 /*fn generate_report_block(budget: f64, budget_freq: String, spent: f64) -> Vec<Line<'static>> {
@@ -97,48 +98,112 @@ pub struct ReportMain {
     pub email: String,
     // each element in vector is a block to be rendered
     pub summary_blocks: Vec<CategorySummary>,
-    pub client: Client,
+    pub dispatcher: Dispatcher,
     // we need multiple list states as we have multiple summary blocks
     pub list_states: Vec<ListState>,
     // store currently selected category
     pub active_cat: usize,
     // trans_mapping[active_cat][list_states[active_cat].selected] = transaction_id
     pub trans_mapping: Vec<Vec<i32>>,
+    // the request currently in flight, if any
+    pub loading: Loading,
+    // the failed request on display, if any
+    error: Option<ErrorPopup<Retry>>,
+    // the transaction to delete once the user confirms
+    confirm: Option<ConfirmDialog<i32>>,
+    // the full transactions behind the summary lines, by id, to colour amounts by kind
+    transactions: HashMap<i32, Transaction>,
+    // income, expenses and net over every transaction, none if the ledger failed to load
+    cash_flow: Option<CashFlow>,
+    // what the last deletion did, shown above the key help
+    message: String,
 }
 
 impl ReportMain {
-    pub fn new(email: String) -> Self {
-        let instance = Self {
+    pub fn new(dispatcher: Dispatcher, email: String) -> Self {
+        Self {
             summary_blocks: Vec::new(),
-            email: email.clone(),
-            client: Client::new(),
+            email,
+            dispatcher,
             list_states: Vec::new(),
             active_cat: 0,
             trans_mapping: Vec::new(),
-        };
-        instance
+            loading: Loading::default(),
+            error: None,
+            confirm: None,
+            transactions: HashMap::new(),
+            cash_flow: None,
+            message: String::new(),
+        }
     }
 
-    // mimicking what account_main does: not sure how this works, hopefully just magically.
-    pub async fn initialize(&mut self) {
-        self.get_categorical_summary().await;
+    // mimicking what account_main does: the summary arrives later as Action::ReportDetails
+    // also called to refresh the summaries after a transaction was edited
+    pub fn initialize(&mut self) {
+        self.get_categorical_summary();
     }
 
-    async fn get_categorical_summary(&mut self) {
-        let url = format!("http://localhost:8000/report_details?email={}", self.email);
-        match self.client.get(&url).send().await {
-            Ok(response) => match response.status() {
-                reqwest::StatusCode::OK => {
-                    if let Ok(mut cat_sum) = response.json::<Vec<CategorySummary>>().await {
-                        // sort the categories by nickname
-                        cat_sum.sort_unstable_by_key(|item| (item.nickname.clone()));
-                        self.summary_blocks = cat_sum;
-                    }
-                }
-                _ => {}
-            },
-            Err(_e) => {}
+    fn get_categorical_summary(&mut self) {
+        let email = self.email.clone();
+        let handle = self.dispatcher.spawn(move |client| async move {
+            let (summaries, transactions) =
+                tokio::join!(client.report_details(&email), client.transactions(&email));
+            Action::ReportDetails {
+                summaries,
+                transactions,
+            }
+        });
+        self.loading.start("Loading report...", handle);
+    }
+
+    /// Advance the loading spinner.
+    pub fn tick(&mut self) {
+        self.loading.tick();
+    }
+
+    pub fn report_loaded(
+        &mut self,
+        result: Result<Vec<CategorySummary>, FinTrakError>,
+        transactions: Result<Vec<Transaction>, FinTrakError>,
+    ) {
+        self.loading.finish();
+        // without the ledger the summary lines are shown as the server wrote them
+        let transactions = transactions.unwrap_or_default();
+        self.cash_flow = (!transactions.is_empty()).then(|| CashFlow::of(&transactions));
+        self.transactions = transactions
+            .into_iter()
+            .map(|transaction| (transaction.trans_id, transaction))
+            .collect();
+        let mut cat_sum = match result {
+            Ok(cat_sum) => cat_sum,
+            Err(e) => {
+                self.error = Some(ErrorPopup::new(e, Retry::Fetch));
+                return;
+            }
+        };
+        // sort the categories by nickname
+        cat_sum.sort_unstable_by_key(|item| item.nickname.clone());
+        // one list state (first entry selected) and id mapping per category block,
+        // the mapping only covers transactions that are actually listed
+        self.list_states = cat_sum
+            .iter()
+            .map(|_| ListState::default().with_selected(Some(0)))
+            .collect();
+        self.trans_mapping = cat_sum
+            .iter()
+            .map(|summary| {
+                summary
+                    .transaction_idz
+                    .iter()
+                    .take(summary.cat_trans.len())
+                    .copied()
+                    .collect()
+            })
+            .collect();
+        if self.active_cat >= cat_sum.len() {
+            self.active_cat = 0;
         }
+        self.summary_blocks = cat_sum;
     }
 
     pub fn render(&mut self, f: &mut Frame) {
@@ -159,7 +224,12 @@ impl ReportMain {
             )
             .split(f.area());
 
-        let title = Paragraph::new("REPORT (Category Based)")
+        // title, then income, expenses and net over the whole ledger when it loaded
+        let mut title_lines = vec![Line::from("REPORT (Category Based)")];
+        if let Some(ref flow) = self.cash_flow {
+            title_lines.push(flow.line());
+        }
+        let title = Paragraph::new(title_lines)
             .style(
                 Style::default()
                     .fg(Color::Black)
@@ -188,19 +258,29 @@ impl ReportMain {
         }
 
         // Bottom notice for navigation instructions (Esc to quit, etc.)
-        let notice = Paragraph::new("Esc: Back | Tab: Switch between Categories | ↑↓: Switch between Transactions | D: Delete Transaction")
+        let notice = Paragraph::new(vec![
+            Line::from(self.message.as_str()),
+            Line::from(self.loading.footer("Esc: Back | Tab: Switch between Categories | ↑↓: Switch between Transactions | E: Edit Transaction | D: Delete Transaction")),
+        ])
             .style(Style::default().fg(Color::DarkGray).bg(Color::White))
             .wrap(Wrap { trim: true })
             .alignment(Alignment::Center);
         f.render_widget(notice, chunks[2]);
+
+        if let Some(ref dialog) = self.confirm {
+            dialog.render(f);
+        }
+        if let Some(ref popup) = self.error {
+            popup.render(f);
+        }
     }
 
     fn render_nested_blocks(&mut self, frame: &mut Frame, area: Rect, i: usize) {
         let cat_title_str = self.summary_blocks[i].nickname.as_str();
-        let overbudget_status = self.summary_blocks[i].overbudget.clone();
-        let budget = self.summary_blocks[i].budget.clone();
+        let overbudget_status = self.summary_blocks[i].overbudget;
+        let budget = self.summary_blocks[i].budget;
         let budget_freq = self.summary_blocks[i].budget_freq.clone();
-        let spent = self.summary_blocks[i].total.clone();
+        let spent = self.summary_blocks[i].total;
         let trans_ids = self.summary_blocks[i].transaction_idz.clone();
         let transactions: Vec<String> = self.summary_blocks[i].cat_trans.clone();
         // divide each summary chunk into budget information line + transaction list
@@ -219,24 +299,20 @@ impl ReportMain {
             .title("Relevant Transactions: ");
         let inner = outer_block.borrow_mut().inner(area);
         frame.render_widget(outer_block, area);
-        // render inner block
-        // create list state for this block, and select the first entry
-        let mut new_list_state: ListState = ListState::default();
-        new_list_state.select(Some(0));
-        self.list_states.push(new_list_state);
-        let mut items: Vec<ListItem> = vec![];
-        // set up transaction mapping
-        let mut id_mapping: Vec<i32> = vec![];
-        for i in 0..transactions.len() {
-            let new_item = ListItem::new(format!(
-                "{}: {}",
-                trans_ids[i].clone(),
-                transactions[i].clone(),
-            ));
-            items.push(new_item);
-            id_mapping.push(trans_ids[i]);
-        }
-        self.trans_mapping.push(id_mapping);
+        // render inner block, list states and id mapping are set up in report_loaded
+        // zip: a server that sends fewer ids than transactions must not crash us
+        let items: Vec<ListItem> = trans_ids
+            .iter()
+            .zip(transactions.iter())
+            .map(|(id, line)| match self.transactions.get(id) {
+                Some(t) => ListItem::new(Line::from(vec![
+                    Span::raw(format!("{}: ", id)),
+                    signed_span(t.signed_amount()),
+                    Span::raw(format!(" | {} | {} | {}", t.date, t.account_name, t.notes)),
+                ])),
+                None => ListItem::new(format!("{}: {}", id, line)),
+            })
+            .collect();
         let list = List::new(items)
             .block(inner_block)
             .style(Style::default().fg(Color::Black))
@@ -249,65 +325,189 @@ impl ReportMain {
         frame.render_stateful_widget(list, inner, &mut self.list_states[i]);
     }
 
-    async fn handle_list_input(&mut self, key: KeyCode) {
+    fn handle_list_input(&mut self, key: KeyCode) {
         match key {
             // tab switches between category blocks
-            KeyCode::Tab => {
+            KeyCode::Tab if !self.summary_blocks.is_empty() => {
                 self.active_cat = (self.active_cat + 1) % self.summary_blocks.len();
             }
             KeyCode::Up => {
-                let selected = self.list_states[self.active_cat].selected().unwrap_or(0);
-                self.list_states[self.active_cat].select(Some(if selected == 0 {
-                    self.summary_blocks[self.active_cat].cat_trans.len() - 1
-                } else {
-                    selected - 1
-                }));
+                let len = self.transaction_count();
+                if let Some(list_state) = self.list_states.get_mut(self.active_cat) {
+                    if len > 0 {
+                        let selected = list_state.selected().unwrap_or(0);
+                        list_state.select(Some(if selected == 0 { len - 1 } else { selected - 1 }));
+                    }
+                }
             }
             KeyCode::Down => {
-                let selected = self.list_states[self.active_cat].selected().unwrap_or(0);
-                self.list_states[self.active_cat].select(Some(
-                    if selected >= self.summary_blocks[self.active_cat].cat_trans.len() - 1 {
-                        0
-                    } else {
-                        selected + 1
-                    },
-                ));
+                let len = self.transaction_count();
+                if let Some(list_state) = self.list_states.get_mut(self.active_cat) {
+                    if len > 0 {
+                        let selected = list_state.selected().unwrap_or(0);
+                        list_state.select(Some(if selected + 1 >= len { 0 } else { selected + 1 }));
+                    }
+                }
+            }
+            KeyCode::Char('e') => {
+                if let Some(to_edit_id) = self.selected_transaction() {
+                    self.edit_transaction(to_edit_id);
+                }
             }
             KeyCode::Char('d') => {
-                if let Some(selected) = self.list_states[self.active_cat].selected() {
-                    let to_delete_id = self.trans_mapping[self.active_cat][selected].clone();
-                    // pop the transaction from the mapping
-                    self.trans_mapping[self.active_cat].remove(selected);
-                    // delete the transaction
-                    self.delete_transaction(to_delete_id).await;
+                if let Some(to_delete_id) = self.selected_transaction() {
+                    // the mapping is rebuilt by the refresh after the delete
+                    self.confirm = Some(self.confirm_delete(to_delete_id));
                 }
             }
             _ => {}
         }
     }
 
-    pub async fn handle_input(&mut self, key: KeyCode, _modifiers: KeyModifiers) -> bool {
+    // what the delete confirmation says about the transaction, the summary line if
+    // the ledger failed to load
+    fn confirm_delete(&self, trans_id: i32) -> ConfirmDialog<i32> {
+        let category = self
+            .summary_blocks
+            .get(self.active_cat)
+            .map_or("", |summary| summary.nickname.as_str());
+        let details = match self.transactions.get(&trans_id) {
+            Some(t) => format!(
+                "{:+} on {} from {} ({})",
+                t.signed_amount(),
+                t.date,
+                t.account_name,
+                t.notes
+            ),
+            None => self
+                .list_states
+                .get(self.active_cat)
+                .and_then(|list_state| list_state.selected())
+                .and_then(|selected| self.summary_blocks.get(self.active_cat)?.cat_trans.get(selected))
+                .cloned()
+                .unwrap_or_default(),
+        };
+        let lines = vec![
+            format!("Delete transaction #{} in {}?", trans_id, category),
+            details,
+        ];
+        ConfirmDialog::new("Delete transaction", lines, trans_id).hint(UNDO_HINT)
+    }
+
+    /// Showing the report with nothing in the way, so a page-level key can act on it.
+    pub fn is_browsing(&self) -> bool {
+        !self.loading.is_loading() && self.error.is_none() && self.confirm.is_none()
+    }
+
+    // number of transactions listed in the active category block
+    fn transaction_count(&self) -> usize {
+        self.trans_mapping
+            .get(self.active_cat)
+            .map_or(0, |ids| ids.len())
+    }
+
+    // id of the highlighted transaction in the active category block
+    fn selected_transaction(&self) -> Option<i32> {
+        let selected = self.list_states.get(self.active_cat)?.selected()?;
+        self.trans_mapping.get(self.active_cat)?.get(selected).copied()
+    }
+
+    pub fn handle_input(&mut self, key: KeyCode, _modifiers: KeyModifiers) -> bool {
+        // While a request runs, Esc cancels it and everything else waits
+        if self.loading.is_loading() {
+            if key == KeyCode::Esc {
+                self.loading.cancel();
+            }
+            return false;
+        }
+        // An error popup takes every key until it is closed
+        if let Some(popup) = self.error.take() {
+            match popup.handle_input(key) {
+                PopupOutcome::Open(popup) => self.error = Some(popup),
+                PopupOutcome::Dismissed => {}
+                PopupOutcome::Retry(Retry::Fetch) => self.get_categorical_summary(),
+                PopupOutcome::Retry(Retry::Delete(trans_id)) => self.delete_transaction(trans_id),
+                PopupOutcome::Retry(Retry::Edit(trans_id)) => self.edit_transaction(trans_id),
+            }
+            return false;
+        }
+        // so does the delete confirmation
+        if let Some(dialog) = self.confirm.take() {
+            match dialog.handle_input(key) {
+                ConfirmOutcome::Open(dialog) => self.confirm = Some(dialog),
+                ConfirmOutcome::Cancelled => {}
+                ConfirmOutcome::Confirmed(trans_id) => self.delete_transaction(trans_id),
+            }
+            return false;
+        }
         if key == KeyCode::Esc {
             return true;
         }
-        self.handle_list_input(key).await;
+        self.handle_list_input(key);
         false
     }
 
-    async fn delete_transaction(&mut self, trans_id: i32) {
-        let url = format!("http://localhost:8000/delete_trans?trans_id={}", trans_id);
+    // the summary only has a line of text per transaction, the form needs the fields
+    fn edit_transaction(&mut self, trans_id: i32) {
+        let email = self.email.clone();
+        let handle = self.dispatcher.spawn(move |client| async move {
+            let result = client.transaction(&email, trans_id).await;
+            Action::TransactionLoaded { trans_id, result }
+        });
+        self.loading.start("Loading transaction...", handle);
+    }
 
-        match self.client.delete(&url).send().await {
-            Ok(response) => {
-                let status = response.status();
-                match status {
-                    reqwest::StatusCode::OK => {
-                        self.get_categorical_summary().await;
-                    }
-                    _ => {}
-                }
+    /// The transaction to open in the edit form, `None` if the lookup failed.
+    pub fn transaction_loaded(
+        &mut self,
+        trans_id: i32,
+        result: Result<Transaction, FinTrakError>,
+    ) -> Option<Transaction> {
+        self.loading.finish();
+        match result {
+            Ok(transaction) => Some(transaction),
+            Err(e) => {
+                self.error = Some(ErrorPopup::new(e, Retry::Edit(trans_id)));
+                None
+            }
+        }
+    }
+
+    fn delete_transaction(&mut self, trans_id: i32) {
+        let email = self.email.clone();
+        let handle = self.dispatcher.spawn(move |client| async move {
+            let result = client.delete_transaction(&email, trans_id).await;
+            Action::TransactionDeleted { trans_id, result }
+        });
+        self.loading.start("Deleting transaction...", handle);
+    }
+
+    /// The deletion to keep for undo, `None` if it failed or the ledger never loaded.
+    pub fn transaction_deleted(
+        &mut self,
+        trans_id: i32,
+        result: Result<Sent, FinTrakError>,
+    ) -> Option<Mutation> {
+        self.loading.finish();
+        match result {
+            Ok(sent) => {
+                self.message = match sent {
+                    Sent::Delivered => "Transaction deleted successfully".to_string(),
+                    Sent::Queued => "Offline: deletion queued, it will sync when the server is back".to_string(),
+                };
+                // the ledger still has it until the refresh comes back
+                let mutation = self.transactions.get(&trans_id).map(|t| Mutation::DeleteTransaction {
+                    trans_id,
+                    transaction: t.data(),
+                    restored: None,
+                });
+                self.get_categorical_summary();
+                mutation
+            }
+            Err(e) => {
+                self.error = Some(ErrorPopup::new(e, Retry::Delete(trans_id)));
+                None
             }
-            Err(_e) => {}
         }
     }
 }
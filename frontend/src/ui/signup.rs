@@ -5,36 +5,32 @@ use ratatui::{
     style::{Color, Style},
     Frame,
 };
-use serde::Serialize;
-use reqwest::Client;
 
-use crate::ui::components::InputField;
-
-#[derive(Serialize)]
-struct SignupData {
-    username: String,
-    email: String,
-    password: String,
-}
+use crate::action::{Action, Dispatcher};
+use crate::error::FinTrakError;
+use crate::ui::components::{ErrorPopup, Field, Form, FormOutcome, Loading, PopupOutcome};
 
 pub struct SignupPage {
-    pub username: InputField,
-    pub email: InputField,
-    pub password: InputField,
-    pub confirm_password: InputField,
-    pub active_field: usize,
+    pub form: Form,
     pub response_message: String,
+    dispatcher: Dispatcher,
+    loading: Loading,
+    error: Option<ErrorPopup<()>>, // Retrying just submits the form again
 }
 
 impl SignupPage {
-    pub fn new() -> Self {
+    pub fn new(dispatcher: Dispatcher) -> Self {
         Self {
-            username: InputField::new("Username", false),
-            email: InputField::new("Email", false),
-            password: InputField::new("Password", true),
-            confirm_password: InputField::new("Confirm Password", true),
-            active_field: 0,
+            form: Form::new(vec![
+                Field::text("username", "Username").required(),
+                Field::text("email", "Email").required().validate(check_email),
+                Field::password("password", "Password").required(),
+                Field::password("confirm_password", "Confirm Password").required(),
+            ]),
             response_message: String::new(),
+            dispatcher,
+            loading: Loading::default(),
+            error: None,
         }
     }
 
@@ -48,10 +44,7 @@ impl SignupPage {
             .constraints(
                 [
                     Constraint::Length(8),  // ASCII Title
-                    Constraint::Length(3),  // Username Input
-                    Constraint::Length(3),  // Email Input
-                    Constraint::Length(3),  // Password Input
-                    Constraint::Length(3),  // Confirm Password Input
+                    Constraint::Length(self.form.height()), // Inputs
                     Constraint::Length(3),  // Response message
                     Constraint::Length(3),  // Bottom Notice
                 ]
@@ -74,98 +67,114 @@ impl SignupPage {
             .alignment(Alignment::Center);
         f.render_widget(title_paragraph, chunks[0]);
 
-        self.username.render(f, chunks[1], self.active_field == 0);
-        self.email.render(f, chunks[2], self.active_field == 1);
-        self.password.render(f, chunks[3], self.active_field == 2);
-        self.confirm_password.render(f, chunks[4], self.active_field == 3);
+        self.form.render(f, chunks[1]);
 
         // Response message displayed between the inputs and the bottom notice
         let response_paragraph = Paragraph::new(self.response_message.clone())
             .style(Style::default().fg(Color::Red).bg(Color::White)) // Response message in red
             .alignment(Alignment::Center);
-        f.render_widget(response_paragraph, chunks[5]);
+        f.render_widget(response_paragraph, chunks[2]);
 
         // Bottom notice
         let notice_text = "Esc to quit | Hit Enter to create new user";
-        let notice_paragraph = Paragraph::new(notice_text)
+        let notice_paragraph = Paragraph::new(self.loading.footer(notice_text))
             .style(Style::default().fg(Color::DarkGray).bg(Color::White)) // Grey text, white background
             .alignment(Alignment::Center);
-        f.render_widget(notice_paragraph, chunks[6]);
-    }
+        f.render_widget(notice_paragraph, chunks[3]);
 
-    pub async fn handle_input(&mut self, key: KeyCode, _modifiers: KeyModifiers) -> bool {
-        if key == KeyCode::Esc {
-            return false; // Navigate back to Cover
+        if let Some(ref popup) = self.error {
+            popup.render(f);
         }
+    }
 
-        match key {
-            KeyCode::Tab => {
-                self.active_field = (self.active_field + 1) % 4; // Cycle through input fields
-            }
-            KeyCode::BackTab => {
-                self.active_field = if self.active_field == 0 { 3 } else { self.active_field - 1 };
-            }
-            KeyCode::Enter => {
-                return self.submit().await;
-            }
-            _ => {
-                match self.active_field {
-                    0 => self.username.handle_input(key),
-                    1 => self.email.handle_input(key),
-                    2 => self.password.handle_input(key),
-                    3 => self.confirm_password.handle_input(key),
-                    _ => {}
+    pub fn handle_input(&mut self, key: KeyCode, modifiers: KeyModifiers) {
+        // While a request runs, Esc cancels it and everything else waits
+        if self.loading.is_loading() {
+            if key == KeyCode::Esc {
+                if let Some(label) = self.loading.cancel() {
+                    self.response_message = format!("Cancelled: {}", label);
                 }
             }
+            return;
+        }
+
+        // An error popup takes every key until it is closed
+        if let Some(popup) = self.error.take() {
+            match popup.handle_input(key) {
+                PopupOutcome::Open(popup) => self.error = Some(popup),
+                PopupOutcome::Dismissed => {}
+                PopupOutcome::Retry(()) => self.submit(),
+            }
+            return;
+        }
+        if let FormOutcome::Submit = self.form.handle_input(key, modifiers) {
+            self.submit();
+        }
+    }
+
+    /// Insert pasted text into the focused field.
+    pub fn handle_paste(&mut self, text: &str) {
+        if !self.loading.is_loading() && self.error.is_none() {
+            self.form.paste(text);
         }
-        false
     }
 
-    pub async fn submit(&mut self) -> bool {
-        if self.password.content != self.confirm_password.content {
+    pub fn submit(&mut self) {
+        if let Err(e) = self.form.check() {
+            self.response_message = e;
+            return;
+        }
+        if self.form.text("password") != self.form.text("confirm_password") {
+            self.form.set_error("confirm_password", "does not match");
             self.response_message = "Passwords do not match".to_string();
-            return false;
+            return;
         }
 
-        let client = Client::new();
-        let signup_data = SignupData {
-            username: self.username.content.clone(),
-            email: self.email.content.clone(),
-            password: self.password.content.clone(),
-        };
-
-        match client.post("http://0.0.0.0:8000/signup")
-            .json(&signup_data)
-            .send()
-            .await
-        {
-            Ok(response) => {
-                let status = response.status();
-                let message = response
-                    .text()
-                    .await
-                    .unwrap_or_else(|_| "Failed to parse response body".to_string());
-
-                match status.as_u16() {
-                    201 => {
-                        self.response_message = "Signup successful! Redirecting to login...".to_string();
-                        return true; // Return true to navigate to login
-                    }
-                    200 if message.contains("Login successful") => {
-                        self.response_message = "Email already registered. Please try another one.".to_string();
-                    }
-                    400 => {
-                        self.response_message = "Failed to sign up. Please check your inputs.".to_string();
-                    }
-                    _ => {
-                        self.response_message = format!("ERROR_CODE: {}\nMessage: {}", status, message);
-                    }
-                }
+        let username = self.form.text("username").trim().to_string();
+        let email = self.form.text("email").trim().to_string();
+        let password = self.form.text("password").to_string();
+        self.response_message.clear();
+        let handle = self.dispatcher.spawn(move |client| async move {
+            Action::SignedUp(client.signup(&username, &email, &password).await)
+        });
+        self.loading.start("Signing up...", handle);
+    }
+
+    /// A request or an error popup is up, Esc belongs to them rather than the app.
+    pub fn is_busy(&self) -> bool {
+        self.loading.is_loading() || self.error.is_some()
+    }
+
+    /// Advance the loading spinner.
+    pub fn tick(&mut self) {
+        self.loading.tick();
+    }
+
+    /// Handle the `/signup` answer, returning `true` to navigate to login. A
+    /// cancelled signup's answer is dropped.
+    pub fn signup_finished(&mut self, result: Result<(), FinTrakError>) -> bool {
+        if !self.loading.is_loading() {
+            return false;
+        }
+        self.loading.finish();
+        match result {
+            Ok(()) => {
+                self.response_message = "Signup successful! Redirecting to login...".to_string();
+                true
             }
             Err(e) => {
-                self.response_message = format!("Request failed: {}", e);
+                self.response_message = "Failed to sign up.".to_string();
+                self.error = Some(ErrorPopup::new(e, ()));
+                false
             }
         }
-        false
+    }
+}
+
+/// Just enough to catch a username typed into the email field.
+fn check_email(email: &str) -> Result<(), String> {
+    match email.split_once('@') {
+        Some((user, domain)) if !user.is_empty() && domain.contains('.') => Ok(()),
+        _ => Err("not an email address".to_string()),
     }
 }
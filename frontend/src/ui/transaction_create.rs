@@ -1,47 +1,172 @@
 use ratatui::{
-    layout::{Alignment, Constraint, Direction, Layout},
+    layout::{Alignment, Constraint, Direction, Flex, Layout},
     style::{Color, Style},
-    widgets::{Block, Paragraph},
+    widgets::{Block, Borders, Clear, Paragraph},
     Frame,
 };
 use crossterm::event::{KeyCode, KeyModifiers};
-use reqwest::Client;
-use serde::Serialize;
-
-use crate::ui::components::InputField;
-
-#[derive(Serialize)]
-struct TransactionData {
-    email: String,
-    category_name: String,
-    amount: f64,
-    notes: String,
-    account_name: String,
+
+use crate::action::{Action, Dispatcher};
+use crate::api::models::{
+    Account, Category, NewAccount, NewCategory, Transaction, TransactionData, TransactionKind,
+};
+use crate::api::Sent;
+use crate::error::FinTrakError;
+use crate::history::Mutation;
+use crate::money::Money;
+use crate::ui::category_main::check_budget;
+use crate::ui::components::{
+    ErrorPopup, Field, Form, FormOutcome, Loading, Picker, PopupOutcome,
+};
+
+/// The request behind an error popup, repeated when the user retries.
+enum Retry {
+    Submit,
+    LoadOptions,
+    CreateOption,
+}
+
+/// Which picker the inline panel adds a name to.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum OptionKind {
+    Category,
+    Account,
+}
+
+/// Creates a missing category or account without leaving the form.
+struct InlineCreate {
+    kind: OptionKind,
+    name: String,
+    form: Form,
+}
+
+impl InlineCreate {
+    fn new(kind: OptionKind, name: String) -> Self {
+        let form = match kind {
+            OptionKind::Category => {
+                let mut form = Form::new(vec![
+                    Field::text("category_type", "Category Type").required(),
+                    Field::money("budget", "Budget").required().validate(check_budget),
+                    Field::text("budget_freq", "Budget Frequency (daily/weekly/monthly)").required(),
+                ]);
+                form.set_text("category_type", "Expense");
+                form.set_text("budget_freq", "monthly");
+                form
+            }
+            OptionKind::Account => Form::new(vec![Field::text("account_type", "Account Type").required()]),
+        };
+        Self { kind, name, form }
+    }
+
+    fn title(&self) -> String {
+        match self.kind {
+            OptionKind::Category => format!("New category \"{}\"", self.name),
+            OptionKind::Account => format!("New account \"{}\"", self.name),
+        }
+    }
 }
 
 pub struct TransactionCreate {
-    pub category_name: InputField,
-    pub amount: InputField,
-    pub account_name: InputField,
-    pub notes: InputField,
-    pub active_field: usize,
+    pub form: Form,
     pub response_message: String,
     pub email: String, // The email is passed from the homepage
+    editing: Option<Transaction>, // The transaction being edited, `None` creates a new one
+    creating: Option<InlineCreate>, // The inline category/account panel, when open
+    dispatcher: Dispatcher,
+    loading: Loading,
+    error: Option<ErrorPopup<Retry>>,
 }
 
 impl TransactionCreate {
-    pub fn new(email: String) -> Self {
+    pub fn new(dispatcher: Dispatcher, email: String) -> Self {
         Self {
-            category_name: InputField::new("Category Name", false),
-            amount: InputField::new("Amount", false),
-            account_name: InputField::new("Account Name", false),
-            notes: InputField::new("Notes", false),
-            active_field: 0,
+            form: Form::new(vec![
+                Field::picker("category_name", Picker::new("Category Name")).required(),
+                Field::money("amount", "Amount").required().validate(check_amount),
+                Field::choice("kind", "Kind", kind_options()),
+                Field::picker("account_name", Picker::new("Account Name")).required(),
+                Field::text("notes", "Notes"),
+                Field::date("date", "Date"),
+            ]),
             response_message: String::new(),
             email,
+            editing: None,
+            creating: None,
+            dispatcher,
+            loading: Loading::default(),
+            error: None,
+        }
+    }
+
+    /// The same form prefilled with `transaction`, saving updates it in place.
+    pub fn edit(dispatcher: Dispatcher, transaction: &Transaction) -> Self {
+        let mut page = Self::new(dispatcher, transaction.email.clone());
+        let form = &mut page.form;
+        form.set_text("category_name", transaction.category_name.clone());
+        form.set_text("amount", transaction.amount.to_string());
+        let kind = TransactionKind::ALL.iter().position(|kind| *kind == transaction.kind);
+        form.set_choice("kind", kind.unwrap_or(0));
+        form.set_text("account_name", transaction.account_name.clone());
+        form.set_text("notes", transaction.notes.clone());
+        form.set_date("date", transaction.date);
+        page.editing = Some(transaction.clone());
+        page
+    }
+
+    /// Fetch the names the category and account pickers complete to.
+    pub fn initialize(&mut self) {
+        self.load_options();
+    }
+
+    fn load_options(&mut self) {
+        let email = self.email.clone();
+        let handle = self.dispatcher.spawn(move |client| async move {
+            let (categories, accounts) =
+                tokio::join!(client.category_summary(&email), client.account_summary(&email));
+            Action::TransactionOptions {
+                categories,
+                accounts,
+            }
+        });
+        self.loading.start("Loading categories and accounts...", handle);
+    }
+
+    /// Without the lists the pickers accept any name and the server has the last word.
+    pub fn options_loaded(
+        &mut self,
+        categories: Result<Vec<Category>, FinTrakError>,
+        accounts: Result<Vec<Account>, FinTrakError>,
+    ) {
+        self.loading.finish();
+        let mut failure = None;
+        match categories {
+            Ok(categories) => {
+                if let Some(picker) = self.form.picker_mut("category_name") {
+                    picker.set_options(categories.into_iter().map(|c| c.nickname).collect());
+                }
+            }
+            Err(e) => failure = Some(e),
+        }
+        match accounts {
+            Ok(accounts) => {
+                if let Some(picker) = self.form.picker_mut("account_name") {
+                    picker.set_options(accounts.into_iter().map(|a| a.account_name).collect());
+                }
+            }
+            Err(e) => failure = Some(e),
+        }
+        if let Some(e) = failure {
+            self.response_message =
+                "Could not load categories and accounts, names are not checked".to_string();
+            self.error = Some(ErrorPopup::new(e, Retry::LoadOptions));
         }
     }
 
+    /// Editing an existing transaction rather than creating one.
+    pub fn is_edit(&self) -> bool {
+        self.editing.is_some()
+    }
+
     pub fn render(&self, f: &mut Frame) {
         let background = Block::default().style(Style::default().bg(Color::White));
         f.render_widget(background, f.area());
@@ -52,10 +177,7 @@ impl TransactionCreate {
             .constraints(
                 [
                     Constraint::Length(8),   // Title section
-                    Constraint::Length(3),   // Category Name
-                    Constraint::Length(3),   // Amount
-                    Constraint::Length(3),   // Account Name
-                    Constraint::Length(3),   // Notes (larger)
+                    Constraint::Length(self.form.height()), // Inputs
                     Constraint::Min(3),      // Response message
                     Constraint::Length(3),   // Navigation notice
                 ]
@@ -64,92 +186,321 @@ impl TransactionCreate {
             .split(f.area());
 
         // Title
-        let title = Paragraph::new("CREATE NEW TRANSACTION")
+        let title_text = match self.editing {
+            Some(ref transaction) => format!("EDIT TRANSACTION #{}", transaction.trans_id),
+            None => "CREATE NEW TRANSACTION".to_string(),
+        };
+        let title = Paragraph::new(title_text)
             .style(Style::default().fg(Color::Black).bg(Color::White))
             .alignment(Alignment::Center);
         f.render_widget(title, chunks[0]);
 
         // Input fields
-        self.category_name.render(f, chunks[1], self.active_field == 0);
-        self.amount.render(f, chunks[2], self.active_field == 1);
-        self.account_name.render(f, chunks[3], self.active_field == 2);
-        self.notes.render(f, chunks[4], self.active_field == 3);
+        self.form.render(f, chunks[1]);
 
         // Response message
         let response_paragraph = Paragraph::new(self.response_message.clone())
             .style(Style::default().fg(Color::DarkGray).bg(Color::White))
             .alignment(Alignment::Center);
-        f.render_widget(response_paragraph, chunks[5]);
+        f.render_widget(response_paragraph, chunks[2]);
 
         // Bottom notice
-        let notice_text = "Esc to quit | Enter to submit";
+        let notice_text = self.loading.footer(if self.is_edit() {
+            "Esc to cancel | Enter to save | ↑↓ Enter: Pick a suggestion"
+        } else {
+            "Esc to quit | Enter to submit | ↑↓ Enter: Pick a suggestion"
+        });
         let notice_paragraph = Paragraph::new(notice_text)
             .style(Style::default().fg(Color::DarkGray).bg(Color::White))
             .alignment(Alignment::Center);
-        f.render_widget(notice_paragraph, chunks[6]);
-    }
+        f.render_widget(notice_paragraph, chunks[3]);
+
+        // Suggestions cover the fields below their picker, so they go last
+        self.form.render_popups(f, chunks[1]);
+        if let Some(ref creating) = self.creating {
+            Self::render_inline_create(f, creating);
+        }
 
-    pub async fn handle_input(&mut self, key: KeyCode, _modifiers: KeyModifiers) -> bool {
-        if key == KeyCode::Esc {
-            return true; // Return to homepage
+        if let Some(ref popup) = self.error {
+            popup.render(f);
         }
+    }
+
+    fn render_inline_create(f: &mut Frame, creating: &InlineCreate) {
+        let height = creating.form.height() + 3;
+        let [area] = Layout::horizontal([Constraint::Percentage(60)])
+            .flex(Flex::Center)
+            .areas(f.area());
+        let [area] = Layout::vertical([Constraint::Length(height)])
+            .flex(Flex::Center)
+            .areas(area);
+
+        let block = Block::default()
+            .title(creating.title())
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(Color::Yellow))
+            .style(Style::default().bg(Color::White));
+        let inner = block.inner(area);
+        f.render_widget(Clear, area);
+        f.render_widget(block, area);
 
-        match key {
-            KeyCode::Tab => {
-                self.active_field = (self.active_field + 1) % 4; // Cycle through input fields
+        let [fields, help_area] =
+            Layout::vertical([Constraint::Length(creating.form.height()), Constraint::Length(1)])
+                .areas(inner);
+        creating.form.render(f, fields);
+        let help = Paragraph::new("TAB: Next Field | ENTER: Create | ESC: Cancel")
+            .style(Style::default().fg(Color::DarkGray))
+            .alignment(Alignment::Center);
+        f.render_widget(help, help_area);
+    }
+
+    pub fn handle_input(&mut self, key: KeyCode, modifiers: KeyModifiers) {
+        // While a request runs, Esc cancels it and everything else waits
+        if self.loading.is_loading() {
+            if key == KeyCode::Esc {
+                if let Some(label) = self.loading.cancel() {
+                    self.response_message = format!("Cancelled: {}", label);
+                }
             }
-            KeyCode::BackTab => {
-                self.active_field = if self.active_field == 0 { 3 } else { self.active_field - 1 };
+            return;
+        }
+
+        // An error popup takes every key until it is closed
+        if let Some(popup) = self.error.take() {
+            match popup.handle_input(key) {
+                PopupOutcome::Open(popup) => self.error = Some(popup),
+                PopupOutcome::Dismissed => {}
+                PopupOutcome::Retry(Retry::Submit) => self.submit(),
+                PopupOutcome::Retry(Retry::LoadOptions) => self.load_options(),
+                PopupOutcome::Retry(Retry::CreateOption) => self.create_option(),
             }
-            KeyCode::Enter => {
-                return self.submit().await;
+            return;
+        }
+
+        // The inline panel takes every key until the name is created or it is closed
+        if let Some(ref mut creating) = self.creating {
+            match creating.form.handle_input(key, modifiers) {
+                FormOutcome::Unhandled if key == KeyCode::Esc => self.creating = None,
+                FormOutcome::Submit => self.create_option(),
+                _ => {}
             }
-            _ => {
-                match self.active_field {
-                    0 => self.category_name.handle_input(key),
-                    1 => self.amount.handle_input(key),
-                    2 => self.account_name.handle_input(key),
-                    3 => self.notes.handle_input(key),
-                    _ => {}
-                }
+            return;
+        }
+
+        match self.form.handle_input(key, modifiers) {
+            FormOutcome::Submit => self.submit(),
+            FormOutcome::Create { key, name } => {
+                let kind = if key == "category_name" {
+                    OptionKind::Category
+                } else {
+                    OptionKind::Account
+                };
+                self.creating = Some(InlineCreate::new(kind, name));
             }
+            FormOutcome::Handled | FormOutcome::Unhandled => {}
+        }
+    }
+
+    /// Insert pasted text into the focused field, of the inline panel when it is open.
+    pub fn handle_paste(&mut self, text: &str) {
+        if self.loading.is_loading() || self.error.is_some() {
+            return;
+        }
+        match self.creating {
+            Some(ref mut creating) => creating.form.paste(text),
+            None => self.form.paste(text),
         }
-        false
     }
 
-    pub async fn submit(&mut self) -> bool {
-        let client = Client::new();
+    pub fn submit(&mut self) {
+        if let Err(e) = self.form.check() {
+            self.response_message = e;
+            return;
+        }
+        let form = &self.form;
         let transaction_data = TransactionData {
             email: self.email.clone(),
-            category_name: self.category_name.content.clone(),
-            amount: self.amount.content.parse().unwrap_or(0.0),
-            notes: self.notes.content.clone(),
-            account_name: self.account_name.content.clone(),
+            category_name: form.name("category_name"),
+            amount: form.money("amount").unwrap_or(Money::ZERO),
+            kind: TransactionKind::ALL[form.choice("kind")],
+            notes: form.text("notes").to_string(),
+            account_name: form.name("account_name"),
+            date: form.date("date"),
         };
 
-        match client.post("http://0.0.0.0:8000/add_trans")
-            .json(&transaction_data)
-            .send()
-            .await
-        {
-            Ok(response) => {
-                let status = response.status();
-                let message = response
-                    .text()
-                    .await
-                    .unwrap_or_else(|_| "Failed to parse response body".to_string());
-
-                if status == 201 {
-                    self.response_message = "Transaction successfully created!".to_string();
-                    return true; // Success, return to homepage
-                } else {
-                    self.response_message = format!("ERROR_CODE: {}\nMessage: {}", status, message);
+        self.response_message.clear();
+        if let Some(ref original) = self.editing {
+            let trans_id = original.trans_id;
+            let mutation = Mutation::UpdateTransaction {
+                trans_id,
+                before: original.data(),
+                after: transaction_data.clone(),
+            };
+            let handle = self.dispatcher.spawn(move |client| async move {
+                let result = client.update_transaction(trans_id, &transaction_data).await;
+                Action::TransactionUpdated { mutation, result }
+            });
+            self.loading.start("Saving transaction...", handle);
+        } else {
+            let mutation = Mutation::CreateTransaction {
+                transaction: transaction_data.clone(),
+            };
+            let handle = self.dispatcher.spawn(move |client| async move {
+                let result = client.add_transaction(&transaction_data).await;
+                Action::TransactionCreated { mutation, result }
+            });
+            self.loading.start("Creating transaction...", handle);
+        }
+    }
+
+    /// Send the inline panel: a new category or account named as typed in the picker.
+    fn create_option(&mut self) {
+        let Some(ref mut creating) = self.creating else {
+            return;
+        };
+        if let Err(e) = creating.form.check() {
+            self.response_message = e;
+            return;
+        }
+        let form = &creating.form;
+        let email = self.email.clone();
+        let name = creating.name.clone();
+        let handle = match creating.kind {
+            OptionKind::Category => {
+                let new_category = NewCategory {
+                    email,
+                    nickname: name.clone(),
+                    category_type: form.text("category_type").trim().to_string(),
+                    budget: form.money("budget").unwrap_or(Money::ZERO),
+                    budget_freq: form.text("budget_freq").trim().to_string(),
+                };
+                let mutation = Mutation::CreateCategory {
+                    category: new_category.clone(),
+                };
+                self.dispatcher.spawn(move |client| async move {
+                    let result = client.create_category(&new_category).await;
+                    Action::InlineCategoryCreated {
+                        nickname: name,
+                        mutation,
+                        result,
+                    }
+                })
+            }
+            OptionKind::Account => {
+                let new_account = NewAccount {
+                    email,
+                    account_type: form.text("account_type").trim().to_string(),
+                    account_name: name.clone(),
+                    opening_balance: Money::ZERO,
+                };
+                let mutation = Mutation::CreateAccount {
+                    account: new_account.clone(),
+                };
+                self.dispatcher.spawn(move |client| async move {
+                    let result = client.create_account(&new_account).await;
+                    Action::InlineAccountCreated {
+                        account_name: name,
+                        mutation,
+                        result,
+                    }
+                })
+            }
+        };
+        self.loading.start("Creating...", handle);
+    }
+
+    /// Handle the answer to the inline panel, the new name goes into its picker.
+    pub fn category_created(&mut self, nickname: String, result: Result<Sent, FinTrakError>) {
+        self.option_created(OptionKind::Category, nickname, result);
+    }
+
+    pub fn account_created(&mut self, account_name: String, result: Result<Sent, FinTrakError>) {
+        self.option_created(OptionKind::Account, account_name, result);
+    }
+
+    fn option_created(&mut self, kind: OptionKind, name: String, result: Result<Sent, FinTrakError>) {
+        self.loading.finish();
+        let (key, what) = match kind {
+            OptionKind::Category => ("category_name", "Category"),
+            OptionKind::Account => ("account_name", "Account"),
+        };
+        match result {
+            Ok(sent) => {
+                self.response_message = match sent {
+                    Sent::Delivered => format!("{} \"{}\" created", what, name),
+                    Sent::Queued => format!("Offline: {} \"{}\" saved, it will sync when the server is back", what.to_lowercase(), name),
+                };
+                if let Some(picker) = self.form.picker_mut(key) {
+                    picker.add_option(name.clone());
                 }
+                self.form.set_text(key, name);
+                self.creating = None;
+            }
+            Err(e) => {
+                self.response_message = format!("Failed to create the {}", what.to_lowercase());
+                self.error = Some(ErrorPopup::new(e, Retry::CreateOption));
+            }
+        }
+    }
+
+    /// A request, an error popup, the calendar or the inline panel is up, Esc belongs
+    /// to them rather than the app.
+    pub fn is_busy(&self) -> bool {
+        self.loading.is_loading()
+            || self.error.is_some()
+            || self.form.is_open()
+            || self.creating.is_some()
+    }
+
+    /// Advance the loading spinner.
+    pub fn tick(&mut self) {
+        self.loading.tick();
+    }
+
+    /// Handle the `/add_trans` or `/update_trans` answer, returning `true` to leave the form.
+    pub fn transaction_saved(&mut self, result: Result<Sent, FinTrakError>) -> bool {
+        self.loading.finish();
+        let verb = if self.is_edit() { "updated" } else { "created" };
+        match result {
+            Ok(sent) => {
+                self.response_message = match sent {
+                    Sent::Delivered => format!("Transaction successfully {}!", verb),
+                    Sent::Queued => "Offline: transaction saved, it will sync when the server is back".to_string(),
+                };
+                true
             }
             Err(e) => {
-                self.response_message = format!("Request failed: {}", e);
+                self.response_message = if self.is_edit() {
+                    "Failed to update the transaction".to_string()
+                } else {
+                    "Failed to create the transaction".to_string()
+                };
+                self.error = Some(ErrorPopup::new(e, Retry::Submit));
+                false
             }
         }
-        false
+    }
+}
+
+/// The kinds in `TransactionKind::ALL` order, expenses in red and income in green.
+fn kind_options() -> Vec<(String, Color)> {
+    TransactionKind::ALL
+        .iter()
+        .map(|kind| match kind {
+            TransactionKind::Expense => (format!("- {}", kind.label()), Color::Red),
+            _ => (format!("+ {}", kind.label()), Color::Green),
+        })
+        .collect()
+}
+
+/// Amounts are entered positive, the kind sets the sign.
+fn check_amount(text: &str) -> Result<(), String> {
+    match Money::parse(text) {
+        Ok(amount) if amount.is_zero() => Err("must not be zero".to_string()),
+        Ok(amount) if amount.is_negative() => {
+            Err("leave out the sign, the kind sets it".to_string())
+        }
+        Ok(_) => Ok(()),
+        Err(e) => Err(e),
     }
 }
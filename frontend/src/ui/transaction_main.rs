@@ -0,0 +1,586 @@
+use std::cmp::Ordering;
+
+use chrono::NaiveDate;
+use crossterm::event::{KeyCode, KeyModifiers};
+use ratatui::{
+    layout::{Alignment, Constraint, Direction, Layout, Rect},
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Cell, Paragraph, Row, Table, TableState},
+    Frame,
+};
+use unicode_width::UnicodeWidthStr;
+
+use crate::action::{Action, Dispatcher};
+use crate::api::models::Transaction;
+use crate::error::FinTrakError;
+use crate::ui::components::{ErrorPopup, InputField, Loading, PopupOutcome};
+use crate::ui::report::{signed_span, CashFlow};
+
+/// How many rows PageUp/PageDown move.
+const PAGE: usize = 10;
+
+/// Columns of the ledger, in display order. `s` cycles the sort through them.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Column {
+    Date,
+    Amount,
+    Category,
+    Account,
+    Notes,
+}
+
+impl Column {
+    const ALL: [Column; 5] = [
+        Column::Date,
+        Column::Amount,
+        Column::Category,
+        Column::Account,
+        Column::Notes,
+    ];
+
+    fn title(self) -> &'static str {
+        match self {
+            Column::Date => "Date",
+            Column::Amount => "Amount",
+            Column::Category => "Category",
+            Column::Account => "Account",
+            Column::Notes => "Notes",
+        }
+    }
+
+    fn next(self) -> Self {
+        let index = Column::ALL.iter().position(|c| *c == self).unwrap_or(0);
+        Column::ALL[(index + 1) % Column::ALL.len()]
+    }
+
+    fn compare(self, a: &Transaction, b: &Transaction) -> Ordering {
+        let text = |a: &str, b: &str| a.to_lowercase().cmp(&b.to_lowercase());
+        match self {
+            Column::Date => a.date.cmp(&b.date),
+            Column::Amount => a.signed_amount().cmp(&b.signed_amount()),
+            Column::Category => text(a.category_label(), b.category_label()),
+            Column::Account => text(&a.account_name, &b.account_name),
+            Column::Notes => text(&a.notes, &b.notes),
+        }
+    }
+}
+
+/// What the keyboard is driving.
+#[derive(PartialEq, Eq)]
+enum Mode {
+    Browse,
+    Search,
+    Filter,
+}
+
+/// Narrows the ledger down, every part is optional.
+#[derive(Default)]
+struct Filters {
+    account: String,  // Case-insensitive substring
+    category: String, // Case-insensitive substring
+    from: Option<NaiveDate>,
+    to: Option<NaiveDate>,
+}
+
+impl Filters {
+    fn matches(&self, transaction: &Transaction) -> bool {
+        contains(&transaction.account_name, &self.account)
+            && contains(transaction.category_label(), &self.category)
+            && self.from.is_none_or(|from| transaction.date >= from)
+            && self.to.is_none_or(|to| transaction.date <= to)
+    }
+
+    fn is_active(&self) -> bool {
+        !self.account.is_empty()
+            || !self.category.is_empty()
+            || self.from.is_some()
+            || self.to.is_some()
+    }
+
+    fn describe(&self) -> String {
+        let mut parts = Vec::new();
+        if !self.account.is_empty() {
+            parts.push(format!("account ~ {}", self.account));
+        }
+        if !self.category.is_empty() {
+            parts.push(format!("category ~ {}", self.category));
+        }
+        match (self.from, self.to) {
+            (Some(from), Some(to)) => parts.push(format!("{} to {}", from, to)),
+            (Some(from), None) => parts.push(format!("from {}", from)),
+            (None, Some(to)) => parts.push(format!("until {}", to)),
+            (None, None) => {}
+        }
+        parts.join(", ")
+    }
+}
+
+fn contains(haystack: &str, needle: &str) -> bool {
+    needle.is_empty() || haystack.to_lowercase().contains(&needle.to_lowercase())
+}
+
+/// An empty field means no bound, anything else has to be a `YYYY-MM-DD` date.
+fn parse_date(label: &str, raw: &str) -> Result<Option<NaiveDate>, String> {
+    let raw = raw.trim();
+    if raw.is_empty() {
+        return Ok(None);
+    }
+    NaiveDate::parse_from_str(raw, "%Y-%m-%d")
+        .map(Some)
+        .map_err(|_| format!("{} must be a date like 2024-01-31", label))
+}
+
+pub struct TransactionMain {
+    transactions: Vec<Transaction>,
+    rows: Vec<usize>, // Indices into `transactions` after search, filters and sorting
+    table_state: TableState,
+    email: String,
+    message: String,
+    mode: Mode,
+    search: InputField, // Only its text and cursor are drawn, on the query line
+    sort_by: Column,
+    descending: bool,
+    filters: Filters,
+    filter_fields: [InputField; 4], // Account, Category, From, To
+    active_field: usize,
+    dispatcher: Dispatcher,
+    loading: Loading,
+    error: Option<ErrorPopup<()>>, // Retrying fetches the ledger again
+}
+
+impl TransactionMain {
+    pub fn new(dispatcher: Dispatcher, email: String) -> Self {
+        Self {
+            transactions: Vec::new(),
+            rows: Vec::new(),
+            table_state: TableState::default(),
+            email,
+            message: "Loading transactions...".to_string(),
+            mode: Mode::Browse,
+            search: InputField::new("Search", false),
+            sort_by: Column::Date,
+            descending: true, // Newest first
+            filters: Filters::default(),
+            filter_fields: [
+                InputField::new("Account", false),
+                InputField::new("Category", false),
+                InputField::new("From (YYYY-MM-DD)", false),
+                InputField::new("To (YYYY-MM-DD)", false),
+            ],
+            active_field: 0,
+            dispatcher,
+            loading: Loading::default(),
+            error: None,
+        }
+    }
+
+    pub fn initialize(&mut self) {
+        self.fetch_transactions();
+    }
+
+    fn fetch_transactions(&mut self) {
+        let email = self.email.clone();
+        let handle = self.dispatcher.spawn(move |client| async move {
+            Action::Transactions(client.transactions(&email).await)
+        });
+        self.loading.start("Loading transactions...", handle);
+    }
+
+    /// Advance the loading spinner.
+    pub fn tick(&mut self) {
+        self.loading.tick();
+    }
+
+    pub fn transactions_loaded(&mut self, result: Result<Vec<Transaction>, FinTrakError>) {
+        self.loading.finish();
+        match result {
+            Ok(transactions) => {
+                self.transactions = transactions;
+                self.message = format!("Loaded {} transactions", self.transactions.len());
+                if self.dispatcher.client().is_offline() {
+                    self.message.push_str(" (offline copy)");
+                }
+                self.refresh_rows();
+            }
+            Err(e) => {
+                self.message = "Failed to fetch transactions".to_string();
+                self.error = Some(ErrorPopup::new(e, ()));
+            }
+        }
+    }
+
+    /// Recompute which transactions are listed, and in what order.
+    fn refresh_rows(&mut self) {
+        let query = self.search.content().to_lowercase();
+        let mut rows: Vec<usize> = self
+            .transactions
+            .iter()
+            .enumerate()
+            .filter(|(_, t)| self.filters.matches(t) && matches_search(t, &query))
+            .map(|(index, _)| index)
+            .collect();
+        rows.sort_by(|a, b| {
+            let (a, b) = (&self.transactions[*a], &self.transactions[*b]);
+            // Ties keep a stable, predictable order
+            let ordering = self
+                .sort_by
+                .compare(a, b)
+                .then_with(|| a.trans_id.cmp(&b.trans_id));
+            if self.descending {
+                ordering.reverse()
+            } else {
+                ordering
+            }
+        });
+        self.rows = rows;
+
+        let selected = self.table_state.selected().unwrap_or(0);
+        self.table_state.select(if self.rows.is_empty() {
+            None
+        } else {
+            Some(selected.min(self.rows.len() - 1))
+        });
+    }
+
+    fn move_selection(&mut self, delta: isize) {
+        if self.rows.is_empty() {
+            return;
+        }
+        let last = self.rows.len() - 1;
+        let selected = self.table_state.selected().unwrap_or(0);
+        let target = selected.saturating_add_signed(delta).min(last);
+        self.table_state.select(Some(target));
+    }
+
+    pub fn render(&mut self, f: &mut Frame) {
+        let background = Block::default().style(Style::default().bg(Color::White));
+        f.render_widget(background, f.area());
+
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .margin(1)
+            .constraints(
+                [
+                    Constraint::Length(2),                                             // Title
+                    Constraint::Length(if self.mode == Mode::Filter { 3 } else { 1 }), // Search / filters
+                    Constraint::Min(5),                                                // Ledger
+                    Constraint::Length(1),                                             // Totals
+                    Constraint::Length(1),                                             // Message
+                    Constraint::Length(2), // Navigation help
+                ]
+                .as_ref(),
+            )
+            .split(f.area());
+
+        let title = Paragraph::new("TRANSACTIONS")
+            .style(
+                Style::default()
+                    .fg(Color::Black)
+                    .bg(Color::White)
+                    .add_modifier(Modifier::BOLD),
+            )
+            .alignment(Alignment::Center);
+        f.render_widget(title, chunks[0]);
+
+        if self.mode == Mode::Filter {
+            self.render_filter_form(f, chunks[1]);
+        } else {
+            self.render_query_line(f, chunks[1]);
+        }
+
+        self.render_table(f, chunks[2]);
+
+        let cash_flow = CashFlow::of(self.rows.iter().map(|i| &self.transactions[*i]));
+        let mut totals_line = cash_flow.line();
+        totals_line.spans.insert(
+            0,
+            Span::raw(format!(
+                "{} of {} transactions | ",
+                self.rows.len(),
+                self.transactions.len()
+            )),
+        );
+        let totals = Paragraph::new(totals_line)
+            .style(Style::default().fg(Color::Black))
+            .alignment(Alignment::Right);
+        f.render_widget(totals, chunks[3]);
+
+        let message_style = if self.message.contains("Failed") || self.message.contains("must") {
+            Style::default().fg(Color::Red)
+        } else {
+            Style::default().fg(Color::Green)
+        };
+        let message = Paragraph::new(self.message.clone())
+            .style(message_style)
+            .alignment(Alignment::Center);
+        f.render_widget(message, chunks[4]);
+
+        let help_text = self.loading.footer(match self.mode {
+            Mode::Browse => {
+                "ESC: Back | ↑↓ PgUp PgDn: Navigate | /: Search | S: Sort column | Shift+S: Reverse | F: Filter | X: Clear"
+            }
+            Mode::Search => "Type to search | ENTER: Keep | ESC: Clear search",
+            Mode::Filter => "TAB: Next Field | ENTER: Apply | ESC: Cancel | Empty fields match everything",
+        });
+        let help = Paragraph::new(help_text)
+            .style(Style::default().fg(Color::DarkGray))
+            .alignment(Alignment::Center);
+        f.render_widget(help, chunks[5]);
+
+        if let Some(ref popup) = self.error {
+            popup.render(f);
+        }
+    }
+
+    fn render_query_line(&self, f: &mut Frame, area: Rect) {
+        let style = Style::default().fg(Color::Blue);
+        let filters = if self.filters.is_active() {
+            format!("Filters: {}", self.filters.describe())
+        } else {
+            String::new()
+        };
+        if self.mode == Mode::Search {
+            // The search is typed in place, the filters keep the end of the line
+            let filters = if filters.is_empty() {
+                filters
+            } else {
+                format!(" | {}", filters)
+            };
+            let [label, input, rest] = Layout::horizontal([
+                Constraint::Length(8),
+                Constraint::Min(10),
+                Constraint::Length(filters.width() as u16),
+            ])
+            .areas(area);
+            f.render_widget(Paragraph::new("Search: ").style(style), label);
+            self.search.render_line(f, input, style, true);
+            f.render_widget(Paragraph::new(filters).style(style), rest);
+            return;
+        }
+        let mut parts = Vec::new();
+        if !self.search.content().is_empty() {
+            parts.push(format!("Search: {}", self.search.content()));
+        }
+        if !filters.is_empty() {
+            parts.push(filters);
+        }
+        let line = Paragraph::new(parts.join(" | ")).style(style);
+        f.render_widget(line, area);
+    }
+
+    fn render_filter_form(&self, f: &mut Frame, area: Rect) {
+        let columns = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Ratio(1, 4); 4].as_ref())
+            .split(area);
+        for (i, field) in self.filter_fields.iter().enumerate() {
+            field.render(f, columns[i], self.active_field == i);
+        }
+    }
+
+    fn render_table(&mut self, f: &mut Frame, area: Rect) {
+        let header = Row::new(Column::ALL.iter().map(|column| {
+            let arrow = match (*column == self.sort_by, self.descending) {
+                (true, true) => " ▼",
+                (true, false) => " ▲",
+                (false, _) => "",
+            };
+            Cell::from(format!("{}{}", column.title(), arrow))
+        }))
+        .style(
+            Style::default()
+                .fg(Color::Black)
+                .add_modifier(Modifier::BOLD),
+        );
+
+        let rows = self.rows.iter().map(|index| {
+            let t = &self.transactions[*index];
+            Row::new(vec![
+                Cell::from(t.date.to_string()),
+                Cell::from(Line::from(signed_span(t.signed_amount())).alignment(Alignment::Right)),
+                Cell::from(t.category_label().to_string()),
+                Cell::from(t.account_name.clone()),
+                Cell::from(t.notes.clone()),
+            ])
+        });
+
+        let table = Table::new(
+            rows,
+            [
+                Constraint::Length(10),
+                Constraint::Length(12),
+                Constraint::Length(16),
+                Constraint::Length(14),
+                Constraint::Min(10),
+            ],
+        )
+        .header(header)
+        .column_spacing(2)
+        .style(Style::default().fg(Color::Black))
+        .row_highlight_style(
+            Style::default()
+                .add_modifier(Modifier::BOLD)
+                .fg(Color::Yellow),
+        )
+        .block(Block::default().borders(Borders::ALL));
+
+        f.render_stateful_widget(table, area, &mut self.table_state);
+    }
+
+    /// Handle one key press. Returns `true` to go back to the homepage.
+    pub fn handle_input(&mut self, key: KeyCode, modifiers: KeyModifiers) -> bool {
+        // While a request runs, Esc cancels it and everything else waits
+        if self.loading.is_loading() {
+            if key == KeyCode::Esc {
+                if let Some(label) = self.loading.cancel() {
+                    self.message = format!("Cancelled: {}", label);
+                }
+            }
+            return false;
+        }
+
+        // An error popup takes every key until it is closed
+        if let Some(popup) = self.error.take() {
+            match popup.handle_input(key) {
+                PopupOutcome::Open(popup) => self.error = Some(popup),
+                PopupOutcome::Dismissed => {}
+                PopupOutcome::Retry(()) => self.fetch_transactions(),
+            }
+            return false;
+        }
+
+        match self.mode {
+            Mode::Browse => return self.handle_browse_input(key),
+            Mode::Search => self.handle_search_input(key, modifiers),
+            Mode::Filter => self.handle_filter_input(key, modifiers),
+        }
+        false
+    }
+
+    /// Pasted text goes into the search or the focused filter, browsing ignores it.
+    pub fn handle_paste(&mut self, text: &str) {
+        if self.loading.is_loading() || self.error.is_some() {
+            return;
+        }
+        match self.mode {
+            Mode::Browse => {}
+            Mode::Search => {
+                self.search.paste(text);
+                self.refresh_rows();
+            }
+            Mode::Filter => self.filter_fields[self.active_field].paste(text),
+        }
+    }
+
+    fn handle_browse_input(&mut self, key: KeyCode) -> bool {
+        match key {
+            KeyCode::Esc => return true,
+            KeyCode::Up => self.move_selection(-1),
+            KeyCode::Down => self.move_selection(1),
+            KeyCode::PageUp => self.move_selection(-(PAGE as isize)),
+            KeyCode::PageDown => self.move_selection(PAGE as isize),
+            KeyCode::Home => self.move_selection(isize::MIN),
+            KeyCode::End => self.move_selection(isize::MAX),
+            KeyCode::Char('/') => self.mode = Mode::Search,
+            KeyCode::Char('s') => {
+                self.sort_by = self.sort_by.next();
+                self.refresh_rows();
+            }
+            KeyCode::Char('S') => {
+                self.descending = !self.descending;
+                self.refresh_rows();
+            }
+            KeyCode::Char('f') => {
+                // Start from what is applied now
+                self.filter_fields[0].set_content(self.filters.account.clone());
+                self.filter_fields[1].set_content(self.filters.category.clone());
+                self.filter_fields[2]
+                    .set_content(self.filters.from.map(|d| d.to_string()).unwrap_or_default());
+                self.filter_fields[3]
+                    .set_content(self.filters.to.map(|d| d.to_string()).unwrap_or_default());
+                self.active_field = 0;
+                self.mode = Mode::Filter;
+            }
+            KeyCode::Char('x') => {
+                self.search.clear();
+                self.filters = Filters::default();
+                self.message = "Search and filters cleared".to_string();
+                self.refresh_rows();
+            }
+            _ => {}
+        }
+        false
+    }
+
+    fn handle_search_input(&mut self, key: KeyCode, modifiers: KeyModifiers) {
+        match key {
+            KeyCode::Enter => self.mode = Mode::Browse,
+            KeyCode::Esc => {
+                self.search.clear();
+                self.mode = Mode::Browse;
+                self.refresh_rows();
+            }
+            _ => {
+                if self.search.handle_input(key, modifiers) {
+                    self.refresh_rows();
+                }
+            }
+        }
+    }
+
+    fn handle_filter_input(&mut self, key: KeyCode, modifiers: KeyModifiers) {
+        match key {
+            KeyCode::Esc => self.mode = Mode::Browse,
+            KeyCode::Tab => self.active_field = (self.active_field + 1) % 4,
+            KeyCode::BackTab => self.active_field = (self.active_field + 3) % 4,
+            KeyCode::Enter => self.apply_filters(),
+            _ => {
+                self.filter_fields[self.active_field].handle_input(key, modifiers);
+            }
+        }
+    }
+
+    fn apply_filters(&mut self) {
+        let dates = parse_date("From", self.filter_fields[2].content()).and_then(|from| {
+            parse_date("To", self.filter_fields[3].content()).map(|to| (from, to))
+        });
+        let (from, to) = match dates {
+            Ok(dates) => dates,
+            Err(message) => {
+                self.message = message;
+                return;
+            }
+        };
+        self.filters = Filters {
+            account: self.filter_fields[0].content().trim().to_string(),
+            category: self.filter_fields[1].content().trim().to_string(),
+            from,
+            to,
+        };
+        self.mode = Mode::Browse;
+        self.message = if self.filters.is_active() {
+            "Filters applied".to_string()
+        } else {
+            "Filters cleared".to_string()
+        };
+        self.refresh_rows();
+    }
+}
+
+/// Incremental search over every column, `query` is already lowercase.
+fn matches_search(transaction: &Transaction, query: &str) -> bool {
+    if query.is_empty() {
+        return true;
+    }
+    [
+        transaction.date.to_string(),
+        format!("{:+}", transaction.signed_amount()),
+        transaction.kind.label().to_lowercase(),
+        transaction.category_label().to_lowercase(),
+        transaction.account_name.to_lowercase(),
+        transaction.notes.to_lowercase(),
+    ]
+    .iter()
+    .any(|field| field.contains(query))
+}
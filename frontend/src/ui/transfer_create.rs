@@ -0,0 +1,269 @@
+use crossterm::event::{KeyCode, KeyModifiers};
+use ratatui::{
+    layout::{Alignment, Constraint, Direction, Layout},
+    style::{Color, Style},
+    widgets::{Block, Paragraph},
+    Frame,
+};
+
+use crate::action::{Action, Dispatcher};
+use crate::api::models::{Account, TransferData};
+use crate::api::Sent;
+use crate::error::FinTrakError;
+use crate::history::Mutation;
+use crate::money::Money;
+use crate::ui::components::{ErrorPopup, Field, Form, FormOutcome, Loading, Picker, PopupOutcome};
+
+/// The request behind an error popup, repeated when the user retries.
+enum Retry {
+    Submit,
+    LoadAccounts,
+}
+
+/// Moves money from one account to another.
+///
+/// The server keeps it as a pair of linked transactions without a category, so it
+/// shows up in both accounts but in no budget.
+pub struct TransferCreate {
+    pub form: Form,
+    pub response_message: String,
+    pub email: String,
+    from_accounts: bool, // Opened from the accounts page, which is where it goes back to
+    dispatcher: Dispatcher,
+    loading: Loading,
+    error: Option<ErrorPopup<Retry>>,
+}
+
+impl TransferCreate {
+    pub fn new(dispatcher: Dispatcher, email: String) -> Self {
+        Self {
+            form: Form::new(vec![
+                Field::picker("from_account", Picker::without_create("From Account")).required(),
+                Field::picker("to_account", Picker::without_create("To Account")).required(),
+                Field::money("amount", "Amount")
+                    .required()
+                    .validate(check_amount),
+                Field::text("notes", "Notes"),
+                Field::date("date", "Date"),
+            ]),
+            response_message: String::new(),
+            email,
+            from_accounts: false,
+            dispatcher,
+            loading: Loading::default(),
+            error: None,
+        }
+    }
+
+    /// The form opened from the accounts page, moving money out of `account_name`.
+    pub fn from_account(
+        dispatcher: Dispatcher,
+        email: String,
+        account_name: Option<String>,
+    ) -> Self {
+        let mut page = Self::new(dispatcher, email);
+        if let Some(account_name) = account_name {
+            page.form.set_text("from_account", account_name);
+            page.form.focus("to_account");
+        }
+        page.from_accounts = true;
+        page
+    }
+
+    /// Leaving goes back to the accounts page rather than the homepage.
+    pub fn is_from_accounts(&self) -> bool {
+        self.from_accounts
+    }
+
+    /// Fetch the names the account pickers complete to.
+    pub fn initialize(&mut self) {
+        self.load_accounts();
+    }
+
+    fn load_accounts(&mut self) {
+        let email = self.email.clone();
+        let handle = self.dispatcher.spawn(move |client| async move {
+            Action::TransferAccounts(client.account_summary(&email).await)
+        });
+        self.loading.start("Loading accounts...", handle);
+    }
+
+    /// Without the list the pickers accept any name and the server has the last word.
+    pub fn accounts_loaded(&mut self, result: Result<Vec<Account>, FinTrakError>) {
+        self.loading.finish();
+        match result {
+            Ok(accounts) => {
+                let names: Vec<String> = accounts.into_iter().map(|a| a.account_name).collect();
+                if names.len() < 2 {
+                    self.response_message =
+                        "A transfer needs two accounts, create another one first".to_string();
+                }
+                if let Some(picker) = self.form.picker_mut("from_account") {
+                    picker.set_options(names.clone());
+                }
+                if let Some(picker) = self.form.picker_mut("to_account") {
+                    picker.set_options(names);
+                }
+            }
+            Err(e) => {
+                self.response_message =
+                    "Could not load accounts, names are not checked".to_string();
+                self.error = Some(ErrorPopup::new(e, Retry::LoadAccounts));
+            }
+        }
+    }
+
+    pub fn render(&self, f: &mut Frame) {
+        let background = Block::default().style(Style::default().bg(Color::White));
+        f.render_widget(background, f.area());
+
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .margin(1)
+            .constraints(
+                [
+                    Constraint::Length(8),                  // Title section
+                    Constraint::Length(self.form.height()), // Inputs
+                    Constraint::Min(3),                     // Response message
+                    Constraint::Length(3),                  // Navigation notice
+                ]
+                .as_ref(),
+            )
+            .split(f.area());
+
+        let title = Paragraph::new("TRANSFER BETWEEN ACCOUNTS")
+            .style(Style::default().fg(Color::Black).bg(Color::White))
+            .alignment(Alignment::Center);
+        f.render_widget(title, chunks[0]);
+
+        self.form.render(f, chunks[1]);
+
+        let response_paragraph = Paragraph::new(self.response_message.clone())
+            .style(Style::default().fg(Color::DarkGray).bg(Color::White))
+            .alignment(Alignment::Center);
+        f.render_widget(response_paragraph, chunks[2]);
+
+        let notice_paragraph = Paragraph::new(
+            self.loading
+                .footer("Esc to cancel | Enter to transfer | ↑↓ Enter: Pick a suggestion"),
+        )
+        .style(Style::default().fg(Color::DarkGray).bg(Color::White))
+        .alignment(Alignment::Center);
+        f.render_widget(notice_paragraph, chunks[3]);
+
+        // Suggestions cover the fields below their picker, so they go last
+        self.form.render_popups(f, chunks[1]);
+
+        if let Some(ref popup) = self.error {
+            popup.render(f);
+        }
+    }
+
+    pub fn handle_input(&mut self, key: KeyCode, modifiers: KeyModifiers) {
+        // While a request runs, Esc cancels it and everything else waits
+        if self.loading.is_loading() {
+            if key == KeyCode::Esc {
+                if let Some(label) = self.loading.cancel() {
+                    self.response_message = format!("Cancelled: {}", label);
+                }
+            }
+            return;
+        }
+
+        // An error popup takes every key until it is closed
+        if let Some(popup) = self.error.take() {
+            match popup.handle_input(key) {
+                PopupOutcome::Open(popup) => self.error = Some(popup),
+                PopupOutcome::Dismissed => {}
+                PopupOutcome::Retry(Retry::Submit) => self.submit(),
+                PopupOutcome::Retry(Retry::LoadAccounts) => self.load_accounts(),
+            }
+            return;
+        }
+
+        if let FormOutcome::Submit = self.form.handle_input(key, modifiers) {
+            self.submit();
+        }
+    }
+
+    /// Insert pasted text into the focused field.
+    pub fn handle_paste(&mut self, text: &str) {
+        if !self.loading.is_loading() && self.error.is_none() {
+            self.form.paste(text);
+        }
+    }
+
+    pub fn submit(&mut self) {
+        if let Err(e) = self.form.check() {
+            self.response_message = e;
+            return;
+        }
+        let from_account = self.form.name("from_account");
+        let to_account = self.form.name("to_account");
+        if from_account == to_account {
+            self.form
+                .set_error("to_account", "same as the from account");
+            self.response_message = "Pick two different accounts".to_string();
+            return;
+        }
+        let transfer = TransferData {
+            email: self.email.clone(),
+            from_account,
+            to_account,
+            amount: self.form.money("amount").unwrap_or(Money::ZERO),
+            notes: self.form.text("notes").to_string(),
+            date: self.form.date("date"),
+        };
+
+        self.response_message.clear();
+        let mutation = Mutation::CreateTransfer {
+            transfer: transfer.clone(),
+        };
+        let handle = self.dispatcher.spawn(move |client| async move {
+            let result = client.add_transfer(&transfer).await;
+            Action::TransferCreated { mutation, result }
+        });
+        self.loading.start("Transferring...", handle);
+    }
+
+    /// A request, an error popup or the calendar is up, Esc belongs to them rather
+    /// than the app.
+    pub fn is_busy(&self) -> bool {
+        self.loading.is_loading() || self.error.is_some() || self.form.is_open()
+    }
+
+    /// Advance the loading spinner.
+    pub fn tick(&mut self) {
+        self.loading.tick();
+    }
+
+    /// Handle the `/add_transfer` answer, returning `true` to leave the form.
+    pub fn transfer_saved(&mut self, result: Result<Sent, FinTrakError>) -> bool {
+        self.loading.finish();
+        match result {
+            Ok(sent) => {
+                self.response_message = match sent {
+                    Sent::Delivered => "Transfer recorded!".to_string(),
+                    Sent::Queued => {
+                        "Offline: transfer saved, it will sync when the server is back".to_string()
+                    }
+                };
+                true
+            }
+            Err(e) => {
+                self.response_message = "Failed to record the transfer".to_string();
+                self.error = Some(ErrorPopup::new(e, Retry::Submit));
+                false
+            }
+        }
+    }
+}
+
+/// A transfer always moves a positive amount, the direction says which way.
+fn check_amount(text: &str) -> Result<(), String> {
+    match Money::parse(text) {
+        Ok(amount) if amount <= Money::ZERO => Err("must be more than zero".to_string()),
+        Ok(_) => Ok(()),
+        Err(e) => Err(e),
+    }
+}